@@ -0,0 +1,7 @@
+//! converlex 的转换引擎：格式表、任务模型、ffmpeg 命令构建与运行。
+//! 不依赖任何 GUI，错误一律通过返回值或 `ProgressMsg::Error` 交给调用方处理。
+//! 启用 `vizia` feature 后模型会派生 `Data` / `Lens`。
+
+pub mod macros;
+pub mod models;
+pub mod utils;
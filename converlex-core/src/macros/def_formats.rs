@@ -10,12 +10,15 @@ macro_rules! def_formats {
                     $( (ext=$ext:expr) )?       // 可选的扩展名(ext="...")
 
                     $( (decs=$decs:expr) )?     // 可选的描述(decs="...")
+
+                    $( (mux=$mux:expr) )?       // 可选的 ffmpeg 封装器名(mux="...")，默认同扩展名
                 ),*
             }
         ),*
     ) => {
         $(
-            #[derive(Clone, Debug, PartialEq, EnumIter, AsRefStr,Data)]
+            #[derive(Clone, Debug, PartialEq, EnumIter, AsRefStr)]
+            #[cfg_attr(feature = "vizia", derive(Data))]
             pub enum $enum_name {
                 $(
                     $variant
@@ -33,6 +36,16 @@ macro_rules! def_formats {
                     }
                 }
 
+                pub fn mux(&self) -> &'static str {
+                    match self {
+                        $(
+                            $enum_name::$variant => {
+                                def_formats!(@get_mux self.ext() $(, $mux )?)
+                            }
+                        ),*
+                    }
+                }
+
                 pub fn ext(&self) -> &'static str {
                     match self {
                         $(
@@ -54,6 +67,14 @@ macro_rules! def_formats {
         None
     };
 
+    // 辅助规则：mux
+    (@get_mux $_default:expr, $m:expr) => {
+        $m
+    };
+    (@get_mux $default:expr) => {
+        $default
+    };
+
     // 辅助规则：ext
     (@get_ext $_default:expr, $e:expr) => {
         $e
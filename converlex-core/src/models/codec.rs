@@ -0,0 +1,465 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use super::convertible_format::FormatType;
+use super::media_format::{Audio, Image, Subtitle, Video};
+
+/// 序列化名与 [`VideoCodec::id`] 一致
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum VideoCodec {
+    H264,
+    Hevc,
+    Av1,
+    Vp9,
+    Vp8,
+    Mpeg4,
+    Mpeg2,
+    Theora,
+    ProRes,
+    Wmv2,
+    Flv1,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 11] = [
+        VideoCodec::H264,
+        VideoCodec::Hevc,
+        VideoCodec::Av1,
+        VideoCodec::Vp9,
+        VideoCodec::Vp8,
+        VideoCodec::Mpeg4,
+        VideoCodec::Mpeg2,
+        VideoCodec::Theora,
+        VideoCodec::ProRes,
+        VideoCodec::Wmv2,
+        VideoCodec::Flv1,
+    ];
+
+    /// 命令行和配置文件里使用的名字
+    pub fn id(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "h264",
+            VideoCodec::Hevc => "hevc",
+            VideoCodec::Av1 => "av1",
+            VideoCodec::Vp9 => "vp9",
+            VideoCodec::Vp8 => "vp8",
+            VideoCodec::Mpeg4 => "mpeg4",
+            VideoCodec::Mpeg2 => "mpeg2",
+            VideoCodec::Theora => "theora",
+            VideoCodec::ProRes => "prores",
+            VideoCodec::Wmv2 => "wmv2",
+            VideoCodec::Flv1 => "flv1",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        let id = id.to_lowercase();
+        Self::ALL.into_iter().find(|c| c.id() == id)
+    }
+
+    /// ffprobe 报告的 codec_name 对应的编码，不认识的返回 `None`
+    pub fn from_probe_name(name: &str) -> Option<Self> {
+        match name {
+            "mpeg2video" => Some(VideoCodec::Mpeg2),
+            name => Self::from_id(name),
+        }
+    }
+
+    /// 使用的 ffmpeg 编码器（`-c:v`）
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "libx264",
+            VideoCodec::Hevc => "libx265",
+            VideoCodec::Av1 => "libaom-av1",
+            VideoCodec::Vp9 => "libvpx-vp9",
+            VideoCodec::Vp8 => "libvpx",
+            VideoCodec::Mpeg4 => "mpeg4",
+            VideoCodec::Mpeg2 => "mpeg2video",
+            VideoCodec::Theora => "libtheora",
+            VideoCodec::ProRes => "prores_ks",
+            VideoCodec::Wmv2 => "wmv2",
+            VideoCodec::Flv1 => "flv",
+        }
+    }
+}
+
+impl Display for VideoCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            VideoCodec::H264 => "H.264",
+            VideoCodec::Hevc => "HEVC",
+            VideoCodec::Av1 => "AV1",
+            VideoCodec::Vp9 => "VP9",
+            VideoCodec::Vp8 => "VP8",
+            VideoCodec::Mpeg4 => "MPEG-4 Part 2",
+            VideoCodec::Mpeg2 => "MPEG-2",
+            VideoCodec::Theora => "Theora",
+            VideoCodec::ProRes => "ProRes",
+            VideoCodec::Wmv2 => "WMV 8",
+            VideoCodec::Flv1 => "Sorenson H.263",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// 序列化名与 [`AudioCodec::id`] 一致
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum AudioCodec {
+    Aac,
+    Mp3,
+    Opus,
+    Vorbis,
+    Flac,
+    Alac,
+    Ac3,
+    Eac3,
+    Pcm,
+    Wma,
+}
+
+impl AudioCodec {
+    pub const ALL: [AudioCodec; 10] = [
+        AudioCodec::Aac,
+        AudioCodec::Mp3,
+        AudioCodec::Opus,
+        AudioCodec::Vorbis,
+        AudioCodec::Flac,
+        AudioCodec::Alac,
+        AudioCodec::Ac3,
+        AudioCodec::Eac3,
+        AudioCodec::Pcm,
+        AudioCodec::Wma,
+    ];
+
+    /// 命令行和配置文件里使用的名字
+    pub fn id(&self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "aac",
+            AudioCodec::Mp3 => "mp3",
+            AudioCodec::Opus => "opus",
+            AudioCodec::Vorbis => "vorbis",
+            AudioCodec::Flac => "flac",
+            AudioCodec::Alac => "alac",
+            AudioCodec::Ac3 => "ac3",
+            AudioCodec::Eac3 => "eac3",
+            AudioCodec::Pcm => "pcm",
+            AudioCodec::Wma => "wma",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        let id = id.to_lowercase();
+        Self::ALL.into_iter().find(|c| c.id() == id)
+    }
+
+    /// ffprobe 报告的 codec_name 对应的编码，不认识的返回 `None`。
+    /// PCM 只认和编码器输出一致的 16 位小端，其它位深复制过去容器未必能装
+    pub fn from_probe_name(name: &str) -> Option<Self> {
+        match name {
+            "pcm_s16le" => Some(AudioCodec::Pcm),
+            "wmav2" => Some(AudioCodec::Wma),
+            "pcm" | "wma" => None,
+            name => Self::from_id(name),
+        }
+    }
+
+    /// 编码器支持的采样率，`None` 表示不限制
+    pub fn sample_rates(&self) -> Option<&'static [u32]> {
+        match self {
+            AudioCodec::Opus => Some(&[8000, 12000, 16000, 24000, 48000]),
+            AudioCodec::Mp3 => Some(&[8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000]),
+            AudioCodec::Ac3 | AudioCodec::Eac3 => Some(&[32000, 44100, 48000]),
+            AudioCodec::Wma => Some(&[8000, 11025, 16000, 22050, 32000, 44100, 48000]),
+            _ => None,
+        }
+    }
+
+    /// 使用的 ffmpeg 编码器（`-c:a`）
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "aac",
+            AudioCodec::Mp3 => "libmp3lame",
+            AudioCodec::Opus => "libopus",
+            AudioCodec::Vorbis => "libvorbis",
+            AudioCodec::Flac => "flac",
+            AudioCodec::Alac => "alac",
+            AudioCodec::Ac3 => "ac3",
+            AudioCodec::Eac3 => "eac3",
+            AudioCodec::Pcm => "pcm_s16le",
+            AudioCodec::Wma => "wmav2",
+        }
+    }
+}
+
+impl Display for AudioCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            AudioCodec::Aac => "AAC",
+            AudioCodec::Mp3 => "MP3",
+            AudioCodec::Opus => "Opus",
+            AudioCodec::Vorbis => "Vorbis",
+            AudioCodec::Flac => "FLAC",
+            AudioCodec::Alac => "ALAC",
+            AudioCodec::Ac3 => "AC-3",
+            AudioCodec::Eac3 => "E-AC-3",
+            AudioCodec::Pcm => "PCM 16-bit",
+            AudioCodec::Wma => "WMA v2",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Video {
+    /// 容器能装的视频编码，空表示只用 ffmpeg 的默认编码
+    pub fn video_codecs(&self) -> &'static [VideoCodec] {
+        use VideoCodec::*;
+        match self {
+            Video::Mp4 => &[H264, Hevc, Av1, Vp9, Mpeg4, Mpeg2],
+            Video::Mkv => &[H264, Hevc, Av1, Vp9, Vp8, Mpeg4, Mpeg2, Theora, ProRes],
+            Video::Mov => &[H264, Hevc, Av1, Mpeg4, Mpeg2, ProRes],
+            Video::Webm => &[Vp9, Vp8, Av1],
+            Video::Avi => &[H264, Mpeg4, Mpeg2],
+            Video::Mpegts => &[H264, Hevc, Mpeg2],
+            Video::Ogv => &[Theora, Vp8],
+            Video::Flv => &[H264, Flv1],
+            Video::Wmv => &[Wmv2, Mpeg4],
+            Video::ThreeGp | Video::ThreeG2 => &[H264, Mpeg4],
+            _ => &[],
+        }
+    }
+
+    /// 容器能装的音频编码，空表示只用 ffmpeg 的默认编码
+    pub fn audio_codecs(&self) -> &'static [AudioCodec] {
+        use AudioCodec::*;
+        match self {
+            Video::Mp4 => &[Aac, Mp3, Opus, Flac, Alac, Ac3, Eac3],
+            Video::Mkv => &[Aac, Mp3, Opus, Vorbis, Flac, Alac, Ac3, Eac3, Pcm],
+            Video::Mov => &[Aac, Mp3, Alac, Ac3, Pcm],
+            Video::Webm => &[Opus, Vorbis],
+            Video::Avi => &[Mp3, Ac3, Pcm],
+            Video::Mpegts => &[Aac, Mp3, Opus, Ac3, Eac3],
+            Video::Ogv => &[Vorbis, Opus, Flac],
+            Video::Flv => &[Aac, Mp3],
+            Video::Wmv => &[Wma, Mp3],
+            Video::ThreeGp | Video::ThreeG2 => &[Aac],
+            _ => &[],
+        }
+    }
+
+    /// 外挂字幕封装进容器时用的编码器，`None` 表示容器装不下文字字幕，只能烧录。
+    /// MKV 保留 ASS 的样式，其余都转成 SubRip
+    pub fn subtitle_encoder(&self, source: &Subtitle) -> Option<&'static str> {
+        match self {
+            Video::Mp4 | Video::Mov | Video::ThreeGp | Video::ThreeG2 => Some("mov_text"),
+            Video::Mkv => match source {
+                Subtitle::Ass => Some("ass"),
+                _ => Some("subrip"),
+            },
+            Video::Webm => Some("webvtt"),
+            _ => None,
+        }
+    }
+}
+
+impl Image {
+    /// 有损格式才有质量设置
+    pub fn is_lossy(&self) -> bool {
+        matches!(self, Image::Jpg | Image::Webp | Image::Avif)
+    }
+
+    /// 把 1-100 的质量（越大越好）换算成编码器自己的参数，无损格式返回空
+    pub fn quality_args(&self, quality: u32) -> Vec<String> {
+        match self {
+            // mjpeg 的 -q:v 是 2-31，越小越好
+            Image::Jpg => vec!["-q:v".into(), (2 + (100 - quality) * 29 / 99).to_string()],
+            Image::Webp => vec!["-quality".into(), quality.to_string()],
+            // AV1 的 crf 是 0-63，越小越好
+            Image::Avif => vec!["-crf".into(), ((100 - quality) * 63 / 100).to_string()],
+            _ => vec![],
+        }
+    }
+}
+
+impl Audio {
+    /// ffmpeg 给这个格式选的默认编码，不在 [`AudioCodec`] 里的返回 `None`
+    pub fn default_codec(&self) -> Option<AudioCodec> {
+        match self {
+            Audio::Mp3 => Some(AudioCodec::Mp3),
+            Audio::Wav | Audio::Lpcm => Some(AudioCodec::Pcm),
+            Audio::Flac => Some(AudioCodec::Flac),
+            Audio::Aac => Some(AudioCodec::Aac),
+            Audio::Ac3 => Some(AudioCodec::Ac3),
+            Audio::Opus => Some(AudioCodec::Opus),
+            Audio::Vorbis => Some(AudioCodec::Vorbis),
+            Audio::Alac => Some(AudioCodec::Alac),
+            Audio::Wma => Some(AudioCodec::Wma),
+            Audio::Eac3 => Some(AudioCodec::Eac3),
+            _ => None,
+        }
+    }
+
+    /// 能直接装下这种编码的音频格式，提取音轨时默认选它，免得重新编码
+    pub fn for_codec(codec: AudioCodec) -> Audio {
+        match codec {
+            AudioCodec::Aac | AudioCodec::Alac => Audio::Alac,
+            AudioCodec::Mp3 => Audio::Mp3,
+            AudioCodec::Opus => Audio::Opus,
+            AudioCodec::Vorbis => Audio::Vorbis,
+            AudioCodec::Flac => Audio::Flac,
+            AudioCodec::Ac3 => Audio::Ac3,
+            AudioCodec::Eac3 => Audio::Eac3,
+            AudioCodec::Pcm => Audio::Wav,
+            AudioCodec::Wma => Audio::Wma,
+        }
+    }
+
+    /// 音频容器里可换的编码，大多数格式只有一种编码
+    pub fn audio_codecs(&self) -> &'static [AudioCodec] {
+        use AudioCodec::*;
+        match self {
+            Audio::Vorbis => &[Vorbis, Opus, Flac],
+            Audio::Alac => &[Alac, Aac],
+            _ => &[],
+        }
+    }
+}
+
+impl FormatType {
+    pub fn video_codecs(&self) -> &'static [VideoCodec] {
+        match self {
+            FormatType::Audio(_) => &[],
+            FormatType::Video(video) => video.video_codecs(),
+            FormatType::AudioVisual(visual) => visual.container.video_codecs(),
+            FormatType::Subtitle(_) | FormatType::Image(_) => &[],
+        }
+    }
+
+    pub fn audio_codecs(&self) -> &'static [AudioCodec] {
+        match self {
+            FormatType::Audio(audio) => audio.audio_codecs(),
+            FormatType::Video(video) => video.audio_codecs(),
+            FormatType::AudioVisual(visual) => visual.container.audio_codecs(),
+            FormatType::Subtitle(_) | FormatType::Image(_) => &[],
+        }
+    }
+
+    /// 实际使用的视频编码：用户没选时取容器的首选编码
+    pub fn effective_video_codec(&self, chosen: Option<VideoCodec>) -> Option<VideoCodec> {
+        chosen.or_else(|| self.video_codecs().first().copied())
+    }
+
+    /// 实际使用的音频编码：用户没选时取格式的默认编码
+    pub fn effective_audio_codec(&self, chosen: Option<AudioCodec>) -> Option<AudioCodec> {
+        chosen.or_else(|| match self {
+            FormatType::Audio(audio) => audio.default_codec(),
+            _ => self.audio_codecs().first().copied(),
+        })
+    }
+
+    /// 检查编码和容器是否搭配，`None` 表示交给 ffmpeg 选默认编码
+    pub fn check_codecs(
+        &self,
+        video: Option<VideoCodec>,
+        audio: Option<AudioCodec>,
+    ) -> Result<(), String> {
+        if let Some(codec) = video
+            && !self.video_codecs().contains(&codec)
+        {
+            return Err(format!("{codec} video can't be stored in this container"));
+        }
+        if let Some(codec) = audio
+            && !self.audio_codecs().contains(&codec)
+        {
+            return Err(format!("{codec} audio can't be stored in this container"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ids_and_probe_names() {
+        assert_eq!(VideoCodec::from_id("HEVC"), Some(VideoCodec::Hevc));
+        assert_eq!(
+            VideoCodec::from_probe_name("mpeg2video"),
+            Some(VideoCodec::Mpeg2)
+        );
+        assert_eq!(VideoCodec::from_id("x264"), None);
+        assert_eq!(
+            AudioCodec::from_probe_name("pcm_s16le"),
+            Some(AudioCodec::Pcm)
+        );
+        assert_eq!(AudioCodec::from_probe_name("pcm_s24le"), None);
+        assert_eq!(AudioCodec::from_probe_name("wmav2"), Some(AudioCodec::Wma));
+    }
+
+    #[test]
+    fn serializes_as_id() {
+        for codec in VideoCodec::ALL {
+            assert_eq!(
+                serde_json::to_string(&codec).unwrap(),
+                format!("\"{}\"", codec.id())
+            );
+        }
+        for codec in AudioCodec::ALL {
+            assert_eq!(
+                serde_json::to_string(&codec).unwrap(),
+                format!("\"{}\"", codec.id())
+            );
+        }
+    }
+
+    #[test]
+    fn checks_codecs_against_container() {
+        let webm = FormatType::Video(Video::Webm);
+
+        assert!(
+            webm.check_codecs(Some(VideoCodec::Vp9), Some(AudioCodec::Opus))
+                .is_ok()
+        );
+        assert!(webm.check_codecs(None, None).is_ok());
+        assert!(webm.check_codecs(Some(VideoCodec::H264), None).is_err());
+        assert!(webm.check_codecs(None, Some(AudioCodec::Aac)).is_err());
+        assert!(
+            FormatType::Audio(Audio::Mp3)
+                .check_codecs(Some(VideoCodec::H264), None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn falls_back_to_default_codecs() {
+        let mp4 = FormatType::Video(Video::Mp4);
+
+        assert_eq!(mp4.effective_video_codec(None), Some(VideoCodec::H264));
+        assert_eq!(
+            mp4.effective_video_codec(Some(VideoCodec::Av1)),
+            Some(VideoCodec::Av1)
+        );
+        assert_eq!(mp4.effective_audio_codec(None), Some(AudioCodec::Aac));
+        assert_eq!(
+            FormatType::Audio(Audio::Wav).effective_audio_codec(None),
+            Some(AudioCodec::Pcm)
+        );
+    }
+
+    #[test]
+    fn maps_image_quality_to_encoder_scales() {
+        assert_eq!(Image::Jpg.quality_args(100), ["-q:v", "2"]);
+        assert_eq!(Image::Jpg.quality_args(1), ["-q:v", "31"]);
+        assert_eq!(Image::Webp.quality_args(80), ["-quality", "80"]);
+        assert_eq!(Image::Avif.quality_args(100), ["-crf", "0"]);
+        assert!(Image::Png.quality_args(80).is_empty());
+        assert!(!Image::Png.is_lossy());
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use super::{media_format::{Audio, Video}, task::TaskType};
+use super::{media_format::{Audio, AudioVisual, Image, Subtitle, Video}, task::TaskType};
 
 pub trait ConvertibleFormat: Send + Sync {
     fn get_supported_output_formats(&self) -> Vec<Box<dyn ConvertibleFormat>>;
@@ -12,11 +12,19 @@ pub trait ConvertibleFormat: Send + Sync {
     fn get_decs(&self) -> Option<String>;
 
     fn get_format_type(&self) -> FormatType;
+
+    /// ffmpeg 的封装器名（`-f`）
+    fn get_muxer(&self) -> String;
+
+    /// 列表中显示的名字，扩展名相同的选项用它区分
+    fn get_label(&self) -> String {
+        self.get_ext()
+    }
 }
 
 impl Display for dyn ConvertibleFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.get_ext())
+        write!(f, "{}", self.get_label())
     }
 }
 
@@ -29,4 +37,7 @@ impl std::fmt::Debug for dyn ConvertibleFormat {
 pub enum FormatType {
     Audio(Audio),
     Video(Video),
+    AudioVisual(AudioVisual),
+    Subtitle(Subtitle),
+    Image(Image),
 }
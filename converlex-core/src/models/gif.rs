@@ -0,0 +1,285 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+/// GIF 的帧延迟以 1/100 秒计，再高的帧率播放器多半不认
+pub const MAX_GIF_FPS: u32 = 50;
+
+/// paletteuse 的抖动算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum GifDither {
+    /// 不抖动，色带明显但文件最小
+    None,
+    /// 有序抖动，网格纹理，压缩效果好
+    Bayer,
+    FloydSteinberg,
+    Sierra2,
+    /// ffmpeg 的默认值
+    #[default]
+    Sierra2_4a,
+}
+
+impl GifDither {
+    pub const ALL: [GifDither; 5] = [
+        GifDither::None,
+        GifDither::Bayer,
+        GifDither::FloydSteinberg,
+        GifDither::Sierra2,
+        GifDither::Sierra2_4a,
+    ];
+
+    /// paletteuse 的 dither 参数
+    pub fn name(&self) -> &'static str {
+        match self {
+            GifDither::None => "none",
+            GifDither::Bayer => "bayer",
+            GifDither::FloydSteinberg => "floyd_steinberg",
+            GifDither::Sierra2 => "sierra2",
+            GifDither::Sierra2_4a => "sierra2_4a",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.name() == name)
+    }
+
+    /// 估算大小用的每像素字节系数：误差扩散的噪点最难压缩
+    fn size_factor(&self) -> f64 {
+        match self {
+            GifDither::None => 0.25,
+            GifDither::Bayer => 0.3,
+            _ => 0.4,
+        }
+    }
+}
+
+impl Display for GifDither {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            GifDither::None => "None",
+            GifDither::Bayer => "Bayer",
+            GifDither::FloydSteinberg => "Floyd-Steinberg",
+            GifDither::Sierra2 => "Sierra-2",
+            GifDither::Sierra2_4a => "Sierra-2-4A",
+        };
+        write!(f, "{label}")
+    }
+}
+
+/// GIF 输出的参数，先用 palettegen 生成调色板，再用 paletteuse 按调色板编码
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct GifOptions {
+    pub fps: u32,
+    /// 输出宽度，高度按比例；`None` 时保持原尺寸
+    pub width: Option<u32>,
+    /// 播完后再重复几次，`None` 时无限循环，`Some(0)` 只播一次
+    pub repeat: Option<u32>,
+    pub dither: GifDither,
+    /// 调色板的颜色数，2-256
+    pub max_colors: u32,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self {
+            fps: 15,
+            width: Some(480),
+            repeat: None,
+            dither: GifDither::default(),
+            max_colors: 256,
+        }
+    }
+}
+
+impl GifOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_GIF_FPS).contains(&self.fps) {
+            return Err(format!("GIF frame rate must be within 1-{MAX_GIF_FPS} fps"));
+        }
+        if self.width.is_some_and(|w| !(16..=4096).contains(&w)) {
+            return Err("GIF width must be within 16-4096 pixels".into());
+        }
+        if !(2..=256).contains(&self.max_colors) {
+            return Err("GIF colors must be within 2-256".into());
+        }
+        Ok(())
+    }
+
+    /// 两遍共用的抽帧和缩放滤镜
+    pub fn frame_filters(&self) -> Vec<String> {
+        let mut filters = vec![format!("fps={}", self.fps)];
+        if let Some(width) = self.width {
+            filters.push(format!("scale={width}:-1:flags=lanczos"));
+        }
+        filters
+    }
+
+    pub fn palettegen_filter(&self) -> String {
+        format!("palettegen=max_colors={}", self.max_colors)
+    }
+
+    /// 只重绘和上一帧不同的矩形区域，能明显减小文件
+    pub fn paletteuse_filter(&self) -> String {
+        let mut filter = format!("paletteuse=dither={}", self.dither.name());
+        if self.dither == GifDither::Bayer {
+            filter.push_str(":bayer_scale=3");
+        }
+        filter.push_str(":diff_mode=rectangle");
+        filter
+    }
+
+    /// gif 封装器的 `-loop`：0 无限循环，-1 不循环，N 重复 N 次
+    pub fn loop_args(&self) -> Vec<String> {
+        let value = match self.repeat {
+            None => "0".to_string(),
+            Some(0) => "-1".to_string(),
+            Some(n) => n.to_string(),
+        };
+        vec!["-loop".into(), value]
+    }
+
+    /// 输出的画面大小，`source` 是输入的宽高
+    pub fn output_size(&self, source: (u32, u32)) -> (u32, u32) {
+        match self.width {
+            Some(width) if source.0 > 0 => {
+                let height = (source.1 as f64 * width as f64 / source.0 as f64).round() as u32;
+                (width, height)
+            }
+            _ => source,
+        }
+    }
+
+    /// 粗略估算输出大小（字节）：按帧数和像素数乘上经验系数，
+    /// 颜色越少、抖动越弱，压缩得越好
+    pub fn estimate_size(&self, secs: f64, source: (u32, u32)) -> u64 {
+        let (width, height) = self.output_size(source);
+        let frames = (secs * self.fps as f64).ceil().max(1.0);
+        let bytes_per_pixel = (self.max_colors.max(2) as f64).log2() / 8.0;
+        (frames * width as f64 * height as f64 * bytes_per_pixel * self.dither.size_factor()) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_ranges() {
+        assert!(GifOptions::default().validate().is_ok());
+        assert!(
+            GifOptions {
+                fps: 0,
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            GifOptions {
+                fps: MAX_GIF_FPS + 1,
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            GifOptions {
+                width: Some(8),
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            GifOptions {
+                max_colors: 1,
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn builds_filters() {
+        let options = GifOptions {
+            dither: GifDither::Bayer,
+            max_colors: 64,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.frame_filters(),
+            ["fps=15", "scale=480:-1:flags=lanczos"]
+        );
+        assert_eq!(options.palettegen_filter(), "palettegen=max_colors=64");
+        assert_eq!(
+            options.paletteuse_filter(),
+            "paletteuse=dither=bayer:bayer_scale=3:diff_mode=rectangle"
+        );
+
+        let original = GifOptions {
+            width: None,
+            ..Default::default()
+        };
+        assert_eq!(original.frame_filters(), ["fps=15"]);
+        assert_eq!(
+            original.paletteuse_filter(),
+            "paletteuse=dither=sierra2_4a:diff_mode=rectangle"
+        );
+    }
+
+    #[test]
+    fn maps_repeat_to_loop() {
+        let loop_value = |repeat| {
+            GifOptions {
+                repeat,
+                ..Default::default()
+            }
+            .loop_args()[1]
+                .clone()
+        };
+        assert_eq!(loop_value(None), "0");
+        assert_eq!(loop_value(Some(0)), "-1");
+        assert_eq!(loop_value(Some(3)), "3");
+    }
+
+    #[test]
+    fn estimates_size_from_scaled_frames() {
+        let options = GifOptions::default();
+        assert_eq!(options.output_size((1920, 1080)), (480, 270));
+        assert_eq!(
+            GifOptions {
+                width: None,
+                ..options
+            }
+            .output_size((1920, 1080)),
+            (1920, 1080)
+        );
+
+        // 2 秒 30 帧，每像素 1 字节 × 0.4
+        assert_eq!(
+            options.estimate_size(2.0, (1920, 1080)),
+            30 * 480 * 270 * 4 / 10
+        );
+        let fewer = GifOptions {
+            max_colors: 16,
+            dither: GifDither::None,
+            ..options
+        };
+        assert!(fewer.estimate_size(2.0, (1920, 1080)) < options.estimate_size(2.0, (1920, 1080)));
+    }
+
+    #[test]
+    fn round_trips_dither_names() {
+        for dither in GifDither::ALL {
+            assert_eq!(GifDither::from_name(dither.name()), Some(dither));
+        }
+        assert_eq!(GifDither::from_name("ordered"), None);
+    }
+}
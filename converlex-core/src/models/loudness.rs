@@ -0,0 +1,373 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+/// EBU R128 的目标值，默认是播客和网络平台常用的 -16 LUFS
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct LoudnessTarget {
+    /// 综合响度（LUFS），-70 ~ -5
+    pub integrated: f64,
+    /// 真峰值上限（dBTP），-9 ~ 0
+    pub true_peak: f64,
+    /// 响度范围（LU），1 ~ 50
+    pub range: f64,
+}
+
+impl Default for LoudnessTarget {
+    fn default() -> Self {
+        Self {
+            integrated: -16.0,
+            true_peak: -1.5,
+            range: 11.0,
+        }
+    }
+}
+
+/// 音频标准化方式，两种都要先跑一遍测量
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum Normalization {
+    /// 第一遍 loudnorm 测量，第二遍按测量值线性调整到目标响度
+    Loudness(LoudnessTarget),
+    /// 把最高峰值调整到指定电平（dBFS）
+    Peak(f64),
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization::Loudness(LoudnessTarget::default())
+    }
+}
+
+impl Normalization {
+    pub const DEFAULT_PEAK: f64 = -1.0;
+
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Normalization::Loudness(target) => {
+                if !(-70.0..=-5.0).contains(&target.integrated) {
+                    return Err("Target loudness must be within -70 to -5 LUFS".into());
+                }
+                if !(-9.0..=0.0).contains(&target.true_peak) {
+                    return Err("Target true peak must be within -9 to 0 dBTP".into());
+                }
+                if !(1.0..=50.0).contains(&target.range) {
+                    return Err("Target loudness range must be within 1-50 LU".into());
+                }
+            }
+            Normalization::Peak(peak) => {
+                if !(-30.0..=0.0).contains(peak) {
+                    return Err("Target peak must be within -30 to 0 dBFS".into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 测量那一遍的音频滤镜，结果打印在 stderr
+    pub fn analysis_filter(&self) -> String {
+        match self {
+            Normalization::Loudness(target) => {
+                format!("{}:print_format=json", loudnorm_targets(target))
+            }
+            Normalization::Peak(_) => "volumedetect".into(),
+        }
+    }
+
+    /// 从测量那一遍的 stderr 中读出测量值
+    pub fn parse_measurement(&self, stderr: &[String]) -> Result<LoudnessMeasurement, String> {
+        match self {
+            Normalization::Loudness(_) => parse_loudnorm(stderr),
+            Normalization::Peak(_) => parse_volumedetect(stderr),
+        }
+    }
+
+    /// 按测量值调整音量的滤镜
+    pub fn apply_filter(&self, measured: &LoudnessMeasurement) -> Result<String, String> {
+        match (self, measured) {
+            (
+                Normalization::Loudness(target),
+                LoudnessMeasurement::Loudness {
+                    integrated,
+                    true_peak,
+                    range,
+                    threshold,
+                    offset,
+                },
+            ) => {
+                if !integrated.is_finite() {
+                    return Err("The audio is silent, there's nothing to normalize".into());
+                }
+                Ok(format!(
+                    "{}:measured_I={integrated}:measured_TP={true_peak}:measured_LRA={range}\
+                     :measured_thresh={threshold}:offset={offset}:linear=true",
+                    loudnorm_targets(target)
+                ))
+            }
+            (Normalization::Peak(_), LoudnessMeasurement::Peak { .. }) => {
+                let gain = self
+                    .gain(measured)
+                    .ok_or("The audio is silent, there's nothing to normalize")?;
+                Ok(format!("volume={gain:.2}dB"))
+            }
+            _ => Err("The loudness measurement doesn't match the normalization".into()),
+        }
+    }
+
+    /// 要加的增益（dB），测量值无效时为 `None`
+    pub fn gain(&self, measured: &LoudnessMeasurement) -> Option<f64> {
+        let gain = match (self, measured) {
+            (Normalization::Loudness(target), LoudnessMeasurement::Loudness { integrated, .. }) => {
+                target.integrated - integrated
+            }
+            (Normalization::Peak(peak), LoudnessMeasurement::Peak { max_volume, .. }) => {
+                peak - max_volume
+            }
+            _ => return None,
+        };
+        gain.is_finite().then_some(gain)
+    }
+}
+
+fn loudnorm_targets(target: &LoudnessTarget) -> String {
+    format!(
+        "loudnorm=I={}:TP={}:LRA={}",
+        target.integrated, target.true_peak, target.range
+    )
+}
+
+/// 测量那一遍得到的数值，静音时可能是负无穷
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum LoudnessMeasurement {
+    /// loudnorm 的测量值
+    Loudness {
+        /// 综合响度（LUFS）
+        integrated: f64,
+        /// 真峰值（dBTP）
+        true_peak: f64,
+        /// 响度范围（LU）
+        range: f64,
+        threshold: f64,
+        /// 第二遍用来修正的偏移（LU）
+        offset: f64,
+    },
+    /// volumedetect 测得的电平（dBFS）
+    Peak { max_volume: f64, mean_volume: f64 },
+}
+
+/// 如 "-23.4 LUFS, -3.1 dBTP, LRA 7.2 LU"
+impl Display for LoudnessMeasurement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoudnessMeasurement::Loudness {
+                integrated,
+                true_peak,
+                range,
+                ..
+            } => write!(
+                f,
+                "{integrated:.1} LUFS, {true_peak:.1} dBTP, LRA {range:.1} LU"
+            ),
+            LoudnessMeasurement::Peak {
+                max_volume,
+                mean_volume,
+            } => write!(f, "peak {max_volume:.1} dB, mean {mean_volume:.1} dB"),
+        }
+    }
+}
+
+/// loudnorm 在结束时打印的 JSON 块
+#[derive(Deserialize)]
+struct LoudnormStats {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    target_offset: String,
+}
+
+fn parse_loudnorm(stderr: &[String]) -> Result<LoudnessMeasurement, String> {
+    let start = stderr
+        .iter()
+        .rposition(|line| line.contains("Parsed_loudnorm"))
+        .ok_or("ffmpeg didn't report the measured loudness")?;
+    let json: String = stderr[start + 1..]
+        .iter()
+        .skip_while(|line| line.trim() != "{")
+        .scan(false, |closed, line| {
+            (!*closed).then(|| {
+                *closed = line.trim() == "}";
+                line.as_str()
+            })
+        })
+        .collect();
+    let stats: LoudnormStats =
+        serde_json::from_str(&json).map_err(|e| format!("Invalid loudnorm output: {e}"))?;
+    // 静音时是 "-inf"，Rust 能直接解析
+    let number = |value: &str| {
+        value
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid loudnorm value \"{value}\""))
+    };
+    Ok(LoudnessMeasurement::Loudness {
+        integrated: number(&stats.input_i)?,
+        true_peak: number(&stats.input_tp)?,
+        range: number(&stats.input_lra)?,
+        threshold: number(&stats.input_thresh)?,
+        offset: number(&stats.target_offset)?,
+    })
+}
+
+fn parse_volumedetect(stderr: &[String]) -> Result<LoudnessMeasurement, String> {
+    // 如 "[Parsed_volumedetect_0 @ 0x...] max_volume: -5.2 dB"
+    let level = |key: &str| {
+        stderr.iter().rev().find_map(|line| {
+            let value = line.split(key).nth(1)?;
+            value
+                .trim()
+                .trim_end_matches("dB")
+                .trim()
+                .parse::<f64>()
+                .ok()
+        })
+    };
+    match (level("max_volume:"), level("mean_volume:")) {
+        (Some(max_volume), Some(mean_volume)) => Ok(LoudnessMeasurement::Peak {
+            max_volume,
+            mean_volume,
+        }),
+        _ => Err("ffmpeg didn't report the audio level".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    const LOUDNORM_STDERR: &str = r#"size=N/A time=00:00:10.00 bitrate=N/A speed= 500x
+[Parsed_loudnorm_0 @ 0x5581] 
+{
+	"input_i" : "-23.40",
+	"input_tp" : "-3.10",
+	"input_lra" : "7.20",
+	"input_thresh" : "-33.80",
+	"output_i" : "-16.02",
+	"output_tp" : "-1.50",
+	"output_lra" : "6.10",
+	"output_thresh" : "-26.40",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.02"
+}
+[out#0/null @ 0x5582] video:0KiB audio:1875KiB"#;
+
+    fn measured() -> LoudnessMeasurement {
+        LoudnessMeasurement::Loudness {
+            integrated: -23.4,
+            true_peak: -3.1,
+            range: 7.2,
+            threshold: -33.8,
+            offset: 0.02,
+        }
+    }
+
+    #[test]
+    fn parses_loudnorm_stats() {
+        let normalization = Normalization::default();
+        assert_eq!(
+            normalization
+                .parse_measurement(&lines(LOUDNORM_STDERR))
+                .unwrap(),
+            measured()
+        );
+        assert_eq!(measured().to_string(), "-23.4 LUFS, -3.1 dBTP, LRA 7.2 LU");
+        assert!(normalization.parse_measurement(&lines("size=N/A")).is_err());
+    }
+
+    #[test]
+    fn parses_silent_loudnorm_stats() {
+        let stderr = LOUDNORM_STDERR.replace("\"-23.40\"", "\"-inf\"");
+        let normalization = Normalization::default();
+        let silent = normalization.parse_measurement(&lines(&stderr)).unwrap();
+
+        assert_eq!(normalization.gain(&silent), None);
+        assert!(
+            normalization
+                .apply_filter(&silent)
+                .unwrap_err()
+                .contains("silent")
+        );
+    }
+
+    #[test]
+    fn parses_volumedetect_levels() {
+        let stderr = lines(
+            "[Parsed_volumedetect_0 @ 0x55] mean_volume: -20.5 dB\n\
+             [Parsed_volumedetect_0 @ 0x55] max_volume: -4.0 dB\n\
+             [Parsed_volumedetect_0 @ 0x55] histogram_4db: 12",
+        );
+        let normalization = Normalization::Peak(Normalization::DEFAULT_PEAK);
+        let measured = normalization.parse_measurement(&stderr).unwrap();
+
+        assert_eq!(
+            measured,
+            LoudnessMeasurement::Peak {
+                max_volume: -4.0,
+                mean_volume: -20.5
+            }
+        );
+        assert_eq!(
+            normalization.apply_filter(&measured).unwrap(),
+            "volume=3.00dB"
+        );
+    }
+
+    #[test]
+    fn applies_measured_loudness_linearly() {
+        let normalization = Normalization::default();
+        assert_eq!(
+            normalization.analysis_filter(),
+            "loudnorm=I=-16:TP=-1.5:LRA=11:print_format=json"
+        );
+        assert_eq!(
+            normalization.apply_filter(&measured()).unwrap(),
+            "loudnorm=I=-16:TP=-1.5:LRA=11:measured_I=-23.4:measured_TP=-3.1:measured_LRA=7.2\
+             :measured_thresh=-33.8:offset=0.02:linear=true"
+        );
+        assert!((normalization.gain(&measured()).unwrap() - 7.4).abs() < 1e-9);
+
+        let peak = Normalization::Peak(-1.0);
+        assert!(
+            peak.apply_filter(&measured())
+                .unwrap_err()
+                .contains("doesn't match")
+        );
+    }
+
+    #[test]
+    fn validates_targets() {
+        assert!(Normalization::default().validate().is_ok());
+        let loud = LoudnessTarget {
+            integrated: -3.0,
+            ..Default::default()
+        };
+        assert!(Normalization::Loudness(loud).validate().is_err());
+        let clipping = LoudnessTarget {
+            true_peak: 1.0,
+            ..Default::default()
+        };
+        assert!(Normalization::Loudness(clipping).validate().is_err());
+        assert!(Normalization::Peak(-40.0).validate().is_err());
+    }
+}
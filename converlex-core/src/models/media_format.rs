@@ -0,0 +1,549 @@
+use std::fmt::{self, Display};
+use strum::IntoEnumIterator;
+use strum_macros::AsRefStr;
+use strum_macros::EnumIter;
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use crate::def_formats;
+use crate::utils::filter_chain::FilterChain;
+
+use super::convertible_format::ConvertibleFormat;
+use super::convertible_format::FormatType;
+use super::media_info::MediaInfo;
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum MediaFormat {
+    Audio(Audio),
+    Video(Video),
+    Subtitle(Subtitle),
+    Image(Image),
+}
+
+impl MediaFormat {
+    pub fn new(extension: &str) -> Option<Self> {
+        let ext = extension.trim_start_matches('.').to_lowercase();
+
+        if let Some(video) = Video::from_extension(&ext) {
+            return Some(MediaFormat::Video(video));
+        }
+
+        if let Some(audio) = Audio::from_extension(&ext) {
+            return Some(MediaFormat::Audio(audio));
+        }
+
+        if let Some(subtitle) = Subtitle::from_extension(&ext) {
+            return Some(MediaFormat::Subtitle(subtitle));
+        }
+
+        if let Some(image) = Image::from_extension(&ext) {
+            return Some(MediaFormat::Image(image));
+        }
+
+        None
+    }
+}
+
+impl Default for MediaFormat {
+    fn default() -> Self {
+        MediaFormat::Audio(Audio::Mp3)
+    }
+}
+
+impl Display for MediaFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MediaFormat::Audio(audio) => write!(f, "{}", audio),
+            MediaFormat::Video(video) => write!(f, "{}", video),
+            MediaFormat::Subtitle(subtitle) => write!(f, "{}", subtitle),
+            MediaFormat::Image(image) => write!(f, "{}", image),
+        }
+    }
+}
+
+impl ConvertibleFormat for MediaFormat {
+    /// 仅按扩展名推断，拿到 ffprobe 结果后应改用 [`MediaFormat::output_formats_for`]
+    fn get_supported_output_formats(&self) -> Vec<Box<dyn ConvertibleFormat>> {
+        match self {
+            MediaFormat::Audio(_) => audio_input_targets(None),
+            MediaFormat::Video(_) => video_input_targets(),
+            MediaFormat::Subtitle(_) => subtitle_targets(),
+            MediaFormat::Image(_) => image_targets(),
+        }
+    }
+
+    fn as_any(&self) -> &dyn ConvertibleFormat {
+        match self {
+            MediaFormat::Audio(_) => self,
+            MediaFormat::Video(_) => self,
+            MediaFormat::Subtitle(_) => self,
+            MediaFormat::Image(_) => self,
+        }
+    }
+
+    fn get_ext(&self) -> String {
+        match self {
+            MediaFormat::Audio(audio) => audio.to_string(),
+            MediaFormat::Video(video) => video.to_string(),
+            MediaFormat::Subtitle(subtitle) => subtitle.to_string(),
+            MediaFormat::Image(image) => image.to_string(),
+        }
+    }
+    
+    fn get_decs(&self) -> Option<String> {
+        match self{
+            MediaFormat::Audio(audio) => audio.desc().map(|s| s.to_string()),
+            MediaFormat::Video(video) => video.desc().map(|s| s.to_string()),
+            MediaFormat::Subtitle(subtitle) => subtitle.desc().map(|s| s.to_string()),
+            MediaFormat::Image(image) => image.desc().map(|s| s.to_string()),
+        }
+    }
+    
+    fn get_format_type(&self)->FormatType {
+        match self {
+            MediaFormat::Audio(audio) => FormatType::Audio(audio.clone()),
+            MediaFormat::Video(video) => FormatType::Video(video.clone()),
+            MediaFormat::Subtitle(subtitle) => FormatType::Subtitle(subtitle.clone()),
+            MediaFormat::Image(image) => FormatType::Image(image.clone()),
+        }
+    }
+
+    fn get_muxer(&self) -> String {
+        match self {
+            MediaFormat::Audio(audio) => audio.mux().to_string(),
+            MediaFormat::Video(video) => video.mux().to_string(),
+            MediaFormat::Subtitle(subtitle) => subtitle.mux().to_string(),
+            MediaFormat::Image(image) => image.mux().to_string(),
+        }
+    }
+}
+
+impl MediaFormat {
+    /// 根据输入文件实际包含的流给出可选的输出格式：
+    /// 有视频流时给视频和音频（提取音轨），只有音频时给音频和“音频配画面”的视频选项；
+    /// 带文字字幕时再加上字幕格式，用于提取字幕。
+    /// 图片在 ffprobe 里也是视频流，按扩展名认出来后只给图片格式。
+    /// 没有探测结果时退回按扩展名推断。
+    pub fn output_formats_for(
+        input: Option<&MediaFormat>,
+        media_info: Option<&MediaInfo>,
+    ) -> Vec<Box<dyn ConvertibleFormat>> {
+        if let Some(MediaFormat::Image(_)) = input {
+            return image_targets();
+        }
+        match media_info {
+            Some(info) if info.has_video() => {
+                let mut formats = if info.has_audio() {
+                    video_input_targets()
+                } else {
+                    Video::all()
+                        .into_iter()
+                        .map(|fmt| Box::new(MediaFormat::Video(fmt)) as Box<dyn ConvertibleFormat>)
+                        .collect()
+                };
+                if info.has_text_subtitle() {
+                    formats.extend(subtitle_targets());
+                }
+                formats
+            }
+            Some(info) if info.has_audio() => audio_input_targets(Some(info)),
+            Some(info) if info.has_text_subtitle() => subtitle_targets(),
+            _ => input
+                .map(|format| format.get_supported_output_formats())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl MediaFormat {
+    /// 导出帧可选的格式：能写成编号序列的图片（image2 封装）
+    pub fn frame_export_formats() -> Vec<Box<dyn ConvertibleFormat>> {
+        Image::all()
+            .into_iter()
+            .filter(|fmt| fmt.mux() == "image2")
+            .map(|fmt| Box::new(MediaFormat::Image(fmt)) as Box<dyn ConvertibleFormat>)
+            .collect()
+    }
+
+    /// 图片序列可选的输出格式
+    pub fn image_sequence_formats() -> Vec<Box<dyn ConvertibleFormat>> {
+        Video::all()
+            .into_iter()
+            .map(|fmt| Box::new(MediaFormat::Video(fmt)) as Box<dyn ConvertibleFormat>)
+            .collect()
+    }
+}
+
+fn video_input_targets() -> Vec<Box<dyn ConvertibleFormat>> {
+    let video_all = Video::all()
+        .into_iter()
+        .map(|fmt| Box::new(MediaFormat::Video(fmt)) as Box<dyn ConvertibleFormat>);
+    let audio_all = Audio::all()
+        .into_iter()
+        .map(|fmt| Box::new(MediaFormat::Audio(fmt)) as Box<dyn ConvertibleFormat>);
+
+    video_all.chain(audio_all).collect()
+}
+
+/// ffmpeg 能写出的字幕格式
+fn subtitle_targets() -> Vec<Box<dyn ConvertibleFormat>> {
+    Subtitle::all()
+        .into_iter()
+        .filter(|fmt| fmt.encoder().is_some())
+        .map(|fmt| Box::new(MediaFormat::Subtitle(fmt)) as Box<dyn ConvertibleFormat>)
+        .collect()
+}
+
+fn image_targets() -> Vec<Box<dyn ConvertibleFormat>> {
+    Image::all()
+        .into_iter()
+        .map(|fmt| Box::new(MediaFormat::Image(fmt)) as Box<dyn ConvertibleFormat>)
+        .collect()
+}
+
+fn audio_input_targets(media_info: Option<&MediaInfo>) -> Vec<Box<dyn ConvertibleFormat>> {
+    let source = match media_info.and_then(|info| info.cover_art()) {
+        Some(_) => VisualSource::CoverArt,
+        None => VisualSource::Waveform,
+    };
+
+    let audio_all = Audio::all()
+        .into_iter()
+        .map(|fmt| Box::new(MediaFormat::Audio(fmt)) as Box<dyn ConvertibleFormat>);
+    let visual = AudioVisual::CONTAINERS.into_iter().map(|container| {
+        Box::new(AudioVisual { container, source }) as Box<dyn ConvertibleFormat>
+    });
+
+    audio_all.chain(visual).collect()
+}
+
+/// 纯音频输出成视频时的画面来源
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum VisualSource {
+    /// 用文件内嵌的封面做静态画面
+    CoverArt,
+    /// 用波形可视化
+    Waveform,
+}
+
+/// “音频配画面”的视频输出
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct AudioVisual {
+    pub container: Video,
+    pub source: VisualSource,
+}
+
+impl AudioVisual {
+    /// 只给常用的视频容器提供这个选项
+    pub const CONTAINERS: [Video; 4] = [Video::Mp4, Video::Mkv, Video::Mov, Video::Webm];
+
+    /// 生成画面所需的 ffmpeg 参数，放在输出路径之前；
+    /// `filters` 是额外的视频滤镜（如缩放），接在画面生成之后
+    pub fn ffmpeg_args(&self, filters: &FilterChain) -> Vec<String> {
+        let source = match self.source {
+            VisualSource::CoverArt => {
+                "[0:v:0]scale=trunc(iw/2)*2:trunc(ih/2)*2,loop=loop=-1:size=1,fps=25"
+            }
+            VisualSource::Waveform => "[0:a:0]showwaves=s=1280x720:mode=cline:rate=25",
+        };
+        let filter = match filters.join() {
+            Some(extra) => format!("{source},{extra},format=yuv420p[v]"),
+            None => format!("{source},format=yuv420p[v]"),
+        };
+
+        vec![
+            "-filter_complex".into(),
+            filter,
+            "-map".into(),
+            "[v]".into(),
+            "-map".into(),
+            "0:a:0".into(),
+            "-shortest".into(),
+        ]
+    }
+}
+
+impl ConvertibleFormat for AudioVisual {
+    fn get_supported_output_formats(&self) -> Vec<Box<dyn ConvertibleFormat>> {
+        MediaFormat::Video(self.container.clone()).get_supported_output_formats()
+    }
+
+    fn as_any(&self) -> &dyn ConvertibleFormat {
+        self
+    }
+
+    fn get_ext(&self) -> String {
+        self.container.to_string()
+    }
+
+    fn get_decs(&self) -> Option<String> {
+        Some(match self.source {
+            VisualSource::CoverArt => "Audio with the embedded cover art as a still image".into(),
+            VisualSource::Waveform => "Audio with a waveform visualizer".into(),
+        })
+    }
+
+    fn get_format_type(&self) -> FormatType {
+        FormatType::AudioVisual(self.clone())
+    }
+
+    fn get_muxer(&self) -> String {
+        self.container.mux().to_string()
+    }
+
+    fn get_label(&self) -> String {
+        match self.source {
+            VisualSource::CoverArt => format!("{} (cover art)", self.container),
+            VisualSource::Waveform => format!("{} (waveform)", self.container),
+        }
+    }
+}
+
+def_formats! {Video{
+    Mp4(decs = "MPEG-4 Part 14, widely supported video container"),
+    Mkv(decs = "Matroska Multimedia Container")(mux = "matroska"),
+    Avi(decs = "Audio Video Interleave, Microsoft format"),
+    Mov(decs = "Apple QuickTime Movie"),
+    Wmv(decs = "Windows Media Video")(mux = "asf"),
+    Flv(decs = "Flash Video Format"),
+    Webm(decs = "Web-optimized Matroska variant by Google"),
+    Mpegts(ext = "ts")(decs = "MPEG Transport Stream")(mux = "mpegts"),
+    Mpeg(decs = "MPEG-1 or MPEG-2 Video"),
+    Mpg(decs = "Alternative extension for MPEG video")(mux = "mpeg"),
+    Ogv(decs = "Ogg Video, Theora encoded")(mux = "ogg"),
+    Gif(decs = "Graphics Interchange Format, supports animation"),
+
+    ThreeG2(ext = "3g2")(decs = "3GPP2 multimedia format"),
+    ThreeGp(ext = "3gp")(decs = "3GPP multimedia format"),
+    F4v(decs = "Flash MP4 video format"),
+    Nut(decs = "Experimental multimedia container"),
+    Psp(decs = "PlayStation Portable media format"),
+    RealMedia(ext = "rm")(decs = "RealNetworks streaming format"),
+    Swf(decs = "Small Web Format for vector animation"),
+    Vcd(ext = "dat")(decs = "Video CD format")(mux = "vcd"),
+    Hds(ext = "f4m")(decs = "HTTP Dynamic Streaming, Adobe format")(mux = "hds"),
+    Ismv(decs = "Smooth Streaming format from Microsoft")
+}}
+
+
+impl Video {
+    pub fn all() -> Vec<Video> {
+        Video::iter().collect()
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let ext = ext.trim_start_matches('.').to_lowercase();
+        Video::iter().find(|variant| variant.ext() == ext)
+    }
+}
+
+impl fmt::Display for Video {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.ext())
+    }
+}
+
+def_formats! {Audio{
+    Mp3(decs = "MPEG-1 Audio Layer 3"),
+    Wav(decs = "Waveform Audio File Format"),
+    Flac(decs = "Free Lossless Audio Codec"),
+    Aac(decs = "Advanced Audio Coding")(mux = "adts"),
+    Ac3(decs = "Audio Codec 3"),
+    Opus(decs = "Opus Interactive Audio Codec"),
+    Vorbis(ext = "ogg")(decs = "Xiph Vorbis audio")(mux = "ogg"),
+    Alac(ext = "m4a")(decs = "Apple Lossless Audio Codec")(mux = "ipod"),
+    Amr(decs = "Adaptive Multi-Rate Audio Codec"),
+    Wma(decs = "Windows Media Audio")(mux = "asf"),
+    Dts(decs = "DTS Coherent Acoustics"),
+    Lpcm(decs = "Linear PCM")(mux = "s16le"),
+    Eac3(decs = "Enhanced AC-3"),
+    Dsd(ext = "dsf")(decs = "Direct Stream Digital"),
+    Tta(decs = "True Audio"),
+    Wv(decs = "WavPack")
+}}
+
+impl Audio {
+    pub fn all() -> Vec<Audio> {
+        Audio::iter().collect()
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let ext = ext.trim_start_matches('.').to_lowercase();
+        Audio::iter().find(|variant| variant.ext() == ext)
+    }
+}
+
+impl Display for Audio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.ext())
+    }
+}
+
+def_formats! {Subtitle{
+    Srt(decs = "SubRip Subtitle"),
+    Ass(decs = "Advanced SubStation Alpha, supports styling"),
+    Vtt(decs = "WebVTT, subtitles for HTML5 video")(mux = "webvtt"),
+    Sub(decs = "MicroDVD Subtitle, frame based")(mux = "microdvd")
+}}
+
+impl Subtitle {
+    pub fn all() -> Vec<Subtitle> {
+        Subtitle::iter().collect()
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let ext = ext.trim_start_matches('.').to_lowercase();
+        Subtitle::iter().find(|variant| variant.ext() == ext)
+    }
+
+    /// 写成这种格式用的编码器；ffmpeg 没有 MicroDVD 编码器，只能读
+    pub fn encoder(&self) -> Option<&'static str> {
+        match self {
+            Subtitle::Srt => Some("subrip"),
+            Subtitle::Ass => Some("ass"),
+            Subtitle::Vtt => Some("webvtt"),
+            Subtitle::Sub => None,
+        }
+    }
+
+    /// ffprobe 报告的编码名，用于判断能否直接复制
+    pub fn codec_name(&self) -> &'static str {
+        match self {
+            Subtitle::Srt => "subrip",
+            Subtitle::Ass => "ass",
+            Subtitle::Vtt => "webvtt",
+            Subtitle::Sub => "microdvd",
+        }
+    }
+}
+
+impl Display for Subtitle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.ext())
+    }
+}
+
+// ffmpeg 的 image2 按扩展名选编码器；它不认识 avif 和 ico，这两种用各自的封装器
+def_formats! {Image{
+    Png(decs = "Portable Network Graphics, lossless")(mux = "image2"),
+    Jpg(decs = "JPEG, lossy photo format")(mux = "image2"),
+    Webp(decs = "WebP image by Google")(mux = "image2"),
+    Bmp(decs = "Windows Bitmap, uncompressed")(mux = "image2"),
+    Tiff(decs = "Tagged Image File Format")(mux = "image2"),
+    Avif(decs = "AV1 Image File Format"),
+    Ico(decs = "Windows icon, at most 256x256")
+}}
+
+impl Image {
+    pub fn all() -> Vec<Image> {
+        Image::iter().collect()
+    }
+
+    /// "jpeg" 和 "tif" 也认
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let ext = ext.trim_start_matches('.').to_lowercase();
+        match ext.as_str() {
+            "jpeg" => Some(Image::Jpg),
+            "tif" => Some(Image::Tiff),
+            _ => Image::iter().find(|variant| variant.ext() == ext),
+        }
+    }
+}
+
+impl Display for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.ext())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::media_info::{StreamInfo, StreamKind};
+
+    fn media_info(streams: &[(StreamKind, bool)]) -> MediaInfo {
+        MediaInfo {
+            streams: streams
+                .iter()
+                .map(|&(kind, is_attached_pic)| StreamInfo {
+                    kind,
+                    is_attached_pic,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn exts(formats: &[Box<dyn ConvertibleFormat>]) -> Vec<String> {
+        formats.iter().map(|f| f.get_ext()).collect()
+    }
+
+    #[test]
+    fn video_with_audio_offers_video_and_audio_targets() {
+        let info = media_info(&[(StreamKind::Video, false), (StreamKind::Audio, false)]);
+        let formats = MediaFormat::output_formats_for(None, Some(&info));
+
+        assert!(exts(&formats).contains(&"mkv".to_string()));
+        assert!(exts(&formats).contains(&"mp3".to_string()));
+        assert_eq!(formats.len(), Video::all().len() + Audio::all().len());
+    }
+
+    #[test]
+    fn silent_video_offers_only_video_targets() {
+        let info = media_info(&[(StreamKind::Video, false)]);
+        let formats = MediaFormat::output_formats_for(None, Some(&info));
+
+        assert!(!formats.is_empty());
+        assert!(
+            formats
+                .iter()
+                .all(|f| matches!(f.get_format_type(), FormatType::Video(_)))
+        );
+    }
+
+    #[test]
+    fn audio_offers_visualized_video_targets() {
+        let waveform = media_info(&[(StreamKind::Audio, false)]);
+        let cover = media_info(&[(StreamKind::Audio, false), (StreamKind::Video, true)]);
+
+        for (info, expected) in [
+            (waveform, VisualSource::Waveform),
+            (cover, VisualSource::CoverArt),
+        ] {
+            let formats = MediaFormat::output_formats_for(None, Some(&info));
+            let visual: Vec<_> = formats
+                .iter()
+                .filter_map(|f| match f.get_format_type() {
+                    FormatType::AudioVisual(visual) => Some(visual),
+                    _ => None,
+                })
+                .collect();
+
+            assert_eq!(visual.len(), AudioVisual::CONTAINERS.len());
+            assert!(visual.iter().all(|v| v.source == expected));
+        }
+    }
+
+    #[test]
+    fn falls_back_to_extension_without_probe() {
+        let input = MediaFormat::Audio(Audio::Flac);
+        let formats = MediaFormat::output_formats_for(Some(&input), None);
+
+        assert_eq!(exts(&formats), exts(&input.get_supported_output_formats()));
+    }
+
+    #[test]
+    fn images_convert_to_images() {
+        let input = MediaFormat::Image(Image::Png);
+        let formats = MediaFormat::output_formats_for(Some(&input), None);
+
+        assert_eq!(formats.len(), Image::all().len());
+        assert_eq!(Image::from_extension(".JPEG"), Some(Image::Jpg));
+        assert_eq!(Image::from_extension("tif"), Some(Image::Tiff));
+        assert_eq!(Image::from_extension("mp4"), None);
+    }
+}
@@ -0,0 +1,208 @@
+use std::fmt::{self, Display};
+
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use crate::utils::utils::format_timestamp;
+
+/// 图形字幕的编码，不能转成文字格式，也不能烧录成文字
+const IMAGE_SUBTITLE_CODECS: [&str; 5] = [
+    "hdmv_pgs_subtitle",
+    "dvd_subtitle",
+    "dvb_subtitle",
+    "dvb_teletext",
+    "xsub",
+];
+
+/// 字幕编码是否是文字字幕，未知编码按文字处理，交给 ffmpeg 报错
+pub fn is_text_subtitle_codec(codec: &str) -> bool {
+    !IMAGE_SUBTITLE_CODECS.contains(&codec)
+}
+
+/// ffprobe 探测到的媒体信息
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct MediaInfo {
+    /// ffprobe 的 format_name，如 "mov,mp4,m4a,3gp,3g2,mj2"
+    pub container: String,
+    pub container_long_name: Option<String>,
+    /// 秒
+    pub duration: Option<f64>,
+    /// bit/s
+    pub bit_rate: Option<u64>,
+    pub streams: Vec<StreamInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct StreamInfo {
+    /// 在输入文件中的绝对索引
+    pub index: u32,
+    pub kind: StreamKind,
+    pub codec: Option<String>,
+    pub codec_long_name: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub frame_count: Option<u64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// bit/s
+    pub bit_rate: Option<u64>,
+    pub duration: Option<f64>,
+    pub is_default: bool,
+    /// 强制显示，多用于只翻译对白的字幕
+    pub is_forced: bool,
+    /// 音频文件里的封面图也会以视频流的形式出现
+    pub is_attached_pic: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    #[default]
+    Unknown,
+}
+
+impl Display for StreamKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            StreamKind::Video => "Video",
+            StreamKind::Audio => "Audio",
+            StreamKind::Subtitle => "Subtitle",
+            StreamKind::Data => "Data",
+            StreamKind::Attachment => "Attachment",
+            StreamKind::Unknown => "Unknown",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl StreamKind {
+    pub fn from_codec_type(codec_type: &str) -> Self {
+        match codec_type {
+            "video" => StreamKind::Video,
+            "audio" => StreamKind::Audio,
+            "subtitle" => StreamKind::Subtitle,
+            "data" => StreamKind::Data,
+            "attachment" => StreamKind::Attachment,
+            _ => StreamKind::Unknown,
+        }
+    }
+}
+
+impl StreamInfo {
+    /// 语言，ffprobe 的 "und" 视为未标注
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref().filter(|lang| *lang != "und")
+    }
+
+    /// 文字字幕（SubRip、ASS 等），图形字幕返回 false
+    pub fn is_text_subtitle(&self) -> bool {
+        self.kind == StreamKind::Subtitle
+            && self.codec.as_deref().is_none_or(is_text_subtitle_codec)
+    }
+
+    /// 选择音轨等场合显示的说明，如 "eng · Commentary · aac 48000Hz"
+    pub fn label(&self) -> String {
+        let mut parts: Vec<String> = vec![];
+        parts.extend(self.language().map(str::to_string));
+        parts.extend(self.title.clone());
+        let mut codec = self.codec.clone().unwrap_or_default();
+        if let (Some(w), Some(h)) = (self.width, self.height) {
+            codec.push_str(&format!(" {}x{}", w, h));
+        }
+        if let Some(sr) = self.sample_rate {
+            codec.push_str(&format!(" {}Hz", sr));
+        }
+        parts.push(codec);
+        parts.join(" · ")
+    }
+}
+
+impl MediaInfo {
+    pub fn streams_of(&self, kind: StreamKind) -> impl Iterator<Item = &StreamInfo> {
+        self.streams.iter().filter(move |s| s.kind == kind)
+    }
+
+    /// 真正的视频流，不含封面图
+    pub fn video_streams(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams_of(StreamKind::Video)
+            .filter(|s| !s.is_attached_pic)
+    }
+
+    pub fn audio_streams(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams_of(StreamKind::Audio)
+    }
+
+    pub fn subtitle_streams(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams_of(StreamKind::Subtitle)
+    }
+
+    pub fn has_text_subtitle(&self) -> bool {
+        self.subtitle_streams().any(StreamInfo::is_text_subtitle)
+    }
+
+    /// 内嵌的封面图（attached_pic）
+    pub fn cover_art(&self) -> Option<&StreamInfo> {
+        self.streams_of(StreamKind::Video).find(|s| s.is_attached_pic)
+    }
+
+    pub fn has_video(&self) -> bool {
+        self.video_streams().next().is_some()
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.audio_streams().next().is_some()
+    }
+
+    /// 是否有可转换的音视频或文字字幕
+    pub fn is_convertible(&self) -> bool {
+        self.has_video() || self.has_audio() || self.has_text_subtitle()
+    }
+
+    /// 第一个视频流的分辨率
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        let video = self.video_streams().next()?;
+        Some((video.width?, video.height?))
+    }
+
+    /// 第一个音频流的采样率
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.audio_streams().next()?.sample_rate
+    }
+
+    /// 任务列表里显示的一行摘要，如 "h264 1920x1080 · aac · 00:03:12"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+
+        if let Some(video) = self.video_streams().next() {
+            let mut s = video.codec.clone().unwrap_or_default();
+            if let (Some(w), Some(h)) = (video.width, video.height) {
+                s.push_str(&format!(" {}x{}", w, h));
+            }
+            parts.push(s);
+        }
+
+        if let Some(audio) = self.audio_streams().next() {
+            let mut s = audio.codec.clone().unwrap_or_default();
+            if let Some(sr) = audio.sample_rate {
+                s.push_str(&format!(" {}Hz", sr));
+            }
+            parts.push(s);
+        }
+
+        if let Some(duration) = self.duration {
+            parts.push(format_timestamp(duration));
+        }
+
+        parts.join(" · ")
+    }
+}
@@ -0,0 +1,14 @@
+pub mod media_info;
+pub mod media_format;
+pub mod task;
+pub mod convertible_format;
+pub mod codec;
+pub mod rate_control;
+pub mod output_params;
+pub mod preset;
+pub mod trim;
+pub mod stream_copy;
+pub mod stream_map;
+pub mod subtitle;
+pub mod gif;
+pub mod loudness;
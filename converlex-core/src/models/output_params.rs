@@ -0,0 +1,173 @@
+use std::fmt::{self, Display};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+/// 输出分辨率
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum Resolution {
+    /// 只指定高度，宽度按比例缩放（`scale=-2:H`）
+    Height(u32),
+    /// 强制指定宽高
+    Exact(u32, u32),
+}
+
+impl Resolution {
+    /// 常用的高度预设
+    pub const PRESETS: [Resolution; 4] = [
+        Resolution::Height(2160),
+        Resolution::Height(1080),
+        Resolution::Height(720),
+        Resolution::Height(480),
+    ];
+
+    const MIN_SIZE: u32 = 16;
+    const MAX_SIZE: u32 = 8640;
+
+    /// 解析 "720p"、"1080" 或 "1280x720"
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let resolution = match s.split_once(['x', 'X']) {
+            Some((w, h)) => {
+                let w = w.trim().parse().map_err(|_| format!("invalid width \"{w}\""))?;
+                let h = h.trim().parse().map_err(|_| format!("invalid height \"{h}\""))?;
+                Resolution::Exact(w, h)
+            }
+            None => {
+                let h = s.trim_end_matches(['p', 'P']);
+                Resolution::Height(h.parse().map_err(|_| {
+                    format!("expected a height like 720p or WIDTHxHEIGHT, got \"{s}\"")
+                })?)
+            }
+        };
+        resolution.validate()?;
+        Ok(resolution)
+    }
+
+    /// 大多数编码器要求偶数尺寸
+    pub fn validate(&self) -> Result<(), String> {
+        let sizes = match self {
+            Resolution::Height(h) => vec![*h],
+            Resolution::Exact(w, h) => vec![*w, *h],
+        };
+        for size in sizes {
+            if !(Self::MIN_SIZE..=Self::MAX_SIZE).contains(&size) {
+                return Err(format!(
+                    "{size} is outside {}-{} pixels",
+                    Self::MIN_SIZE,
+                    Self::MAX_SIZE
+                ));
+            }
+            if size % 2 != 0 {
+                return Err(format!("{size} is odd, most encoders need even sizes"));
+            }
+        }
+        Ok(())
+    }
+
+    /// `scale` 滤镜
+    pub fn scale_filter(&self) -> String {
+        match self {
+            Resolution::Height(h) => format!("scale=-2:{h}"),
+            Resolution::Exact(w, h) => format!("scale={w}:{h}"),
+        }
+    }
+}
+
+impl Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resolution::Height(h) => write!(f, "{h}p"),
+            Resolution::Exact(w, h) => write!(f, "{w}x{h}"),
+        }
+    }
+}
+
+/// 帧率预设
+pub const FRAME_RATE_PRESETS: [u32; 6] = [24, 25, 30, 48, 50, 60];
+
+/// 采样率预设（Hz）
+pub const SAMPLE_RATE_PRESETS: [u32; 7] = [8000, 16000, 22050, 32000, 44100, 48000, 96000];
+
+/// 不知道输入的采样率时用的值（Hz）
+pub const DEFAULT_SAMPLE_RATE: u32 = 48000;
+
+/// 帧率上限（fps）
+pub const MAX_FRAME_RATE: u32 = 240;
+
+pub fn validate_frame_rate(fps: u32) -> Result<(), String> {
+    if (1..=MAX_FRAME_RATE).contains(&fps) {
+        Ok(())
+    } else {
+        Err(format!("Frame rate {fps} is outside 1-{MAX_FRAME_RATE} fps"))
+    }
+}
+
+/// 图片质量，1-100，越大越好
+pub fn validate_image_quality(quality: u32) -> Result<(), String> {
+    if (1..=100).contains(&quality) {
+        Ok(())
+    } else {
+        Err(format!("Image quality {quality} is outside 1-100"))
+    }
+}
+
+pub fn validate_sample_rate(rate: u32) -> Result<(), String> {
+    if (8000..=192000).contains(&rate) {
+        Ok(())
+    } else {
+        Err(format!("Sample rate {rate} Hz is outside 8000-192000 Hz"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_resolutions() {
+        assert_eq!(Resolution::parse("720p"), Ok(Resolution::Height(720)));
+        assert_eq!(Resolution::parse(" 1080 "), Ok(Resolution::Height(1080)));
+        assert_eq!(
+            Resolution::parse("1280X720"),
+            Ok(Resolution::Exact(1280, 720))
+        );
+        assert!(Resolution::parse("hd").is_err());
+        assert!(Resolution::parse("1280x").is_err());
+    }
+
+    #[test]
+    fn rejects_odd_and_out_of_range_sizes() {
+        assert!(Resolution::parse("721p").unwrap_err().contains("odd"));
+        assert!(Resolution::parse("8x8").is_err());
+        assert!(Resolution::Exact(1920, 9000).validate().is_err());
+    }
+
+    #[test]
+    fn formats_filter_and_label() {
+        assert_eq!(Resolution::Height(720).scale_filter(), "scale=-2:720");
+        assert_eq!(Resolution::Exact(640, 480).scale_filter(), "scale=640:480");
+        assert_eq!(Resolution::Height(720).to_string(), "720p");
+        assert_eq!(Resolution::Exact(640, 480).to_string(), "640x480");
+    }
+
+    #[test]
+    fn validates_frame_and_sample_rates() {
+        assert!(validate_frame_rate(60).is_ok());
+        assert!(validate_frame_rate(0).is_err());
+        assert!(validate_frame_rate(MAX_FRAME_RATE + 1).is_err());
+        assert!(validate_sample_rate(44100).is_ok());
+        assert!(validate_sample_rate(4000).is_err());
+    }
+
+    #[test]
+    fn validates_image_quality() {
+        assert!(validate_image_quality(1).is_ok());
+        assert!(validate_image_quality(100).is_ok());
+        assert!(validate_image_quality(0).is_err());
+        assert!(validate_image_quality(101).is_err());
+    }
+}
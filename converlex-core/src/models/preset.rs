@@ -0,0 +1,298 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use super::{
+    codec::{AudioCodec, VideoCodec},
+    convertible_format::FormatType,
+    gif::GifOptions,
+    loudness::Normalization,
+    media_format::Video,
+    output_params::{validate_frame_rate, validate_image_quality, validate_sample_rate, Resolution},
+    rate_control::{AudioRateControl, VideoRateControl},
+    task::Task,
+};
+
+/// 命名的转换配置：输出格式加上 [`FfmpegTask`](crate::utils::ffmpeg_wrapper::FfmpegTask) 的各项参数
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "vizia", derive(Lens, Data))]
+pub struct Preset {
+    pub name: String,
+    /// 输出格式的 [`get_label`](super::convertible_format::ConvertibleFormat::get_label)，如 "mp4"
+    pub format: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video_codec: Option<VideoCodec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_codec: Option<AudioCodec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video_rate: Option<VideoRateControl>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_rate: Option<AudioRateControl>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<Resolution>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_rate: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_rate: Option<u32>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    #[serde(default)]
+    pub two_pass: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_quality: Option<u32>,
+    #[serde(default)]
+    pub strip_metadata: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalization: Option<Normalization>,
+    /// 只有 GIF 格式的预设才保存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gif: Option<GifOptions>,
+    /// 内置预设不写入文件，也不能删除
+    #[serde(skip)]
+    pub builtin: bool,
+}
+
+impl Preset {
+    /// 只有格式，其余参数交给 ffmpeg 默认值
+    pub fn new(name: impl Into<String>, format: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            format: format.into(),
+            video_codec: None,
+            audio_codec: None,
+            video_rate: None,
+            audio_rate: None,
+            resolution: None,
+            frame_rate: None,
+            sample_rate: None,
+            extra_args: vec![],
+            two_pass: false,
+            image_quality: None,
+            strip_metadata: false,
+            normalization: None,
+            gif: None,
+            builtin: false,
+        }
+    }
+
+    /// 随程序提供的预设
+    pub fn builtins() -> Vec<Preset> {
+        let mut presets = vec![
+            Preset {
+                video_codec: Some(VideoCodec::H264),
+                audio_codec: Some(AudioCodec::Aac),
+                video_rate: Some(VideoRateControl::Quality(23)),
+                audio_rate: Some(AudioRateControl::Bitrate(128)),
+                resolution: Some(Resolution::Height(720)),
+                ..Preset::new("Web 720p H.264", "mp4")
+            },
+            Preset {
+                video_codec: Some(VideoCodec::Vp9),
+                audio_codec: Some(AudioCodec::Opus),
+                video_rate: Some(VideoRateControl::Quality(31)),
+                audio_rate: Some(AudioRateControl::Bitrate(128)),
+                resolution: Some(Resolution::Height(1080)),
+                ..Preset::new("WebM VP9 1080p", "webm")
+            },
+            Preset {
+                video_codec: Some(VideoCodec::H264),
+                audio_codec: Some(AudioCodec::Aac),
+                video_rate: Some(VideoRateControl::TargetSize(25)),
+                audio_rate: Some(AudioRateControl::Bitrate(96)),
+                two_pass: true,
+                ..Preset::new("Share 25 MB MP4", "mp4")
+            },
+            Preset {
+                audio_rate: Some(AudioRateControl::Bitrate(96)),
+                sample_rate: Some(44100),
+                ..Preset::new("Podcast MP3 96k", "mp3")
+            },
+            Preset::new("Archive FLAC", "flac"),
+            Preset {
+                gif: Some(GifOptions::default()),
+                ..Preset::new("Web GIF 480px", "gif")
+            },
+        ];
+        for preset in &mut presets {
+            preset.builtin = true;
+        }
+        presets
+    }
+
+    /// 从任务当前的配置生成预设
+    pub fn from_task(name: impl Into<String>, task: &Task) -> Self {
+        let output_format = &task.supported_output_formats[task.selected_output_format];
+        let format = output_format.get_label();
+        let is_gif = matches!(
+            output_format.get_format_type(),
+            FormatType::Video(Video::Gif)
+        );
+        let ffmpeg_task = task.task_type.ffmpeg();
+        Self {
+            video_codec: ffmpeg_task.video_codec,
+            audio_codec: ffmpeg_task.audio_codec,
+            video_rate: ffmpeg_task.video_rate,
+            audio_rate: ffmpeg_task.audio_rate,
+            resolution: ffmpeg_task.resolution,
+            frame_rate: ffmpeg_task.frame_rate,
+            sample_rate: ffmpeg_task.sample_rate,
+            extra_args: ffmpeg_task.extra_args.clone(),
+            two_pass: ffmpeg_task.two_pass,
+            image_quality: ffmpeg_task.image_quality,
+            strip_metadata: ffmpeg_task.strip_metadata,
+            normalization: ffmpeg_task.normalization,
+            gif: is_gif.then_some(ffmpeg_task.gif),
+            ..Self::new(name, format)
+        }
+    }
+
+    /// 检查预设本身的参数，和输入文件无关
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Preset name can't be empty".into());
+        }
+        if let Some(resolution) = &self.resolution {
+            resolution.validate()?;
+        }
+        if let Some(fps) = self.frame_rate {
+            validate_frame_rate(fps)?;
+        }
+        if let Some(rate) = self.sample_rate {
+            validate_sample_rate(rate)?;
+        }
+        if let Some(quality) = self.image_quality {
+            validate_image_quality(quality)?;
+        }
+        if let Some(normalization) = &self.normalization {
+            normalization.validate()?;
+        }
+        if let Some(gif) = &self.gif {
+            gif.validate()?;
+        }
+        Ok(())
+    }
+
+    /// 把预设套到任务上，任务的输入不能输出为预设格式时返回错误且不修改任务
+    pub fn apply_to(&self, task: &mut Task) -> Result<(), String> {
+        let index = task
+            .supported_output_formats
+            .iter()
+            .position(|f| f.get_label() == self.format)
+            .ok_or_else(|| format!("{} can't be converted to {}", task.input_path, self.format))?;
+        let format_type = task.supported_output_formats[index].get_format_type();
+        format_type.check_codecs(self.video_codec, self.audio_codec)?;
+
+        task.selected_output_format = index;
+        if task.auto_rename {
+            task.output_path = task.default_output_path(false);
+        }
+        let ffmpeg_task = task.task_type.ffmpeg_mut();
+        ffmpeg_task.video_codec = self.video_codec;
+        ffmpeg_task.audio_codec = self.audio_codec;
+        ffmpeg_task.video_rate = self.video_rate;
+        ffmpeg_task.audio_rate = self.audio_rate;
+        ffmpeg_task.resolution = self.resolution;
+        ffmpeg_task.frame_rate = self.frame_rate;
+        ffmpeg_task.sample_rate = self.sample_rate;
+        ffmpeg_task.extra_args = self.extra_args.clone();
+        ffmpeg_task.two_pass = self.two_pass;
+        ffmpeg_task.image_quality = self.image_quality;
+        ffmpeg_task.strip_metadata = self.strip_metadata;
+        ffmpeg_task.normalization = self.normalization;
+        if let Some(gif) = self.gif {
+            ffmpeg_task.gif = gif;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::models::convertible_format::ConvertibleFormat;
+    use crate::models::media_format::{Audio, MediaFormat};
+    use crate::utils::ffmpeg_wrapper::FfmpegEntry;
+
+    fn task(formats: &[MediaFormat]) -> Task {
+        let formats: Vec<Arc<dyn ConvertibleFormat>> = formats
+            .iter()
+            .map(|f| Arc::new(f.clone()) as Arc<dyn ConvertibleFormat>)
+            .collect();
+        let mut task = Task::new("in.mkv".into(), "out".into(), formats, 0, FfmpegEntry::Env);
+        task.auto_rename = false;
+        task
+    }
+
+    #[test]
+    fn builtins_are_valid() {
+        for preset in Preset::builtins() {
+            assert!(preset.builtin);
+            assert_eq!(preset.validate(), Ok(()), "{}", preset.name);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_presets() {
+        assert!(Preset::new(" ", "mp4").validate().is_err());
+        let preset = Preset {
+            frame_rate: Some(0),
+            ..Preset::new("Broken", "mp4")
+        };
+        assert!(preset.validate().is_err());
+    }
+
+    #[test]
+    fn applies_format_and_settings() {
+        let mut task = task(&[
+            MediaFormat::Video(Video::Mkv),
+            MediaFormat::Video(Video::Mp4),
+        ]);
+        let preset = &Preset::builtins()[0];
+        preset.apply_to(&mut task).unwrap();
+
+        assert_eq!(task.selected_output_format, 1);
+        let ffmpeg_task = task.task_type.ffmpeg();
+        assert_eq!(ffmpeg_task.video_codec, Some(VideoCodec::H264));
+        assert_eq!(ffmpeg_task.resolution, Some(Resolution::Height(720)));
+        assert_eq!(
+            Preset::from_task(&preset.name, &task),
+            Preset {
+                builtin: false,
+                ..preset.clone()
+            }
+        );
+    }
+
+    #[test]
+    fn leaves_task_untouched_when_format_is_unavailable() {
+        let mut task = task(&[
+            MediaFormat::Audio(Audio::Mp3),
+            MediaFormat::Audio(Audio::Flac),
+        ]);
+        let preset = Preset {
+            sample_rate: Some(44100),
+            ..Preset::new("Video", "mp4")
+        };
+
+        assert!(preset.apply_to(&mut task).is_err());
+        assert_eq!(task.selected_output_format, 0);
+        assert_eq!(task.task_type.ffmpeg().sample_rate, None);
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        for preset in Preset::builtins() {
+            let json = serde_json::to_string(&preset).unwrap();
+            let loaded: Preset = serde_json::from_str(&json).unwrap();
+            assert_eq!(
+                loaded,
+                Preset {
+                    builtin: false,
+                    ..preset
+                }
+            );
+        }
+    }
+}
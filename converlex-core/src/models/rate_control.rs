@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use super::codec::{AudioCodec, VideoCodec};
+
+/// 视频码率控制
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum VideoRateControl {
+    /// 固定码率（kbps）
+    Bitrate(u32),
+    /// 恒定质量（CRF/CQ），取值范围见 [`VideoCodec::quality_scale`]
+    Quality(u32),
+    /// 目标文件大小（MB），按输入时长换算成码率
+    TargetSize(u32),
+}
+
+impl VideoRateControl {
+    /// 新建任务或切换模式时的默认值
+    pub const DEFAULT_BITRATE: u32 = 2500;
+    pub const DEFAULT_TARGET_SIZE: u32 = 50;
+
+    pub fn value(&self) -> u32 {
+        match self {
+            VideoRateControl::Bitrate(v)
+            | VideoRateControl::Quality(v)
+            | VideoRateControl::TargetSize(v) => *v,
+        }
+    }
+
+    /// 保持模式，换一个数值
+    pub fn with_value(&self, value: u32) -> Self {
+        match self {
+            VideoRateControl::Bitrate(_) => VideoRateControl::Bitrate(value),
+            VideoRateControl::Quality(_) => VideoRateControl::Quality(value),
+            VideoRateControl::TargetSize(_) => VideoRateControl::TargetSize(value),
+        }
+    }
+}
+
+/// 音频码率控制
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum AudioRateControl {
+    /// 固定码率（kbps）
+    Bitrate(u32),
+    /// VBR 质量等级（`-q:a`），取值范围见 [`AudioCodec::quality_scale`]
+    Quality(u32),
+}
+
+impl AudioRateControl {
+    pub const DEFAULT_BITRATE: u32 = 128;
+
+    pub fn value(&self) -> u32 {
+        match self {
+            AudioRateControl::Bitrate(v) | AudioRateControl::Quality(v) => *v,
+        }
+    }
+
+    pub fn with_value(&self, value: u32) -> Self {
+        match self {
+            AudioRateControl::Bitrate(_) => AudioRateControl::Bitrate(value),
+            AudioRateControl::Quality(_) => AudioRateControl::Quality(value),
+        }
+    }
+}
+
+/// 编码器的质量参数及取值范围
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QualityScale {
+    /// 如 `-crf`、`-q:v`、`-q:a`
+    pub arg: &'static str,
+    pub min: u32,
+    pub max: u32,
+    pub default: u32,
+    /// 数值越小质量越高
+    pub lower_is_better: bool,
+    /// 需要同时给 `-b:v 0` 才是真正的恒定质量（libvpx-vp9、libaom-av1）
+    pub zero_bitrate: bool,
+}
+
+impl QualityScale {
+    const fn new(arg: &'static str, min: u32, max: u32, default: u32) -> Self {
+        Self {
+            arg,
+            min,
+            max,
+            default,
+            lower_is_better: true,
+            zero_bitrate: false,
+        }
+    }
+
+    const fn higher_is_better(mut self) -> Self {
+        self.lower_is_better = false;
+        self
+    }
+
+    const fn zero_bitrate(mut self) -> Self {
+        self.zero_bitrate = true;
+        self
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+
+    /// 界面上的范围提示，如 "0-51, lower is better"
+    pub fn hint(&self) -> String {
+        let direction = if self.lower_is_better { "lower" } else { "higher" };
+        format!("{}-{}, {direction} is better", self.min, self.max)
+    }
+}
+
+impl VideoCodec {
+    pub fn quality_scale(&self) -> QualityScale {
+        match self {
+            VideoCodec::H264 => QualityScale::new("-crf", 0, 51, 23),
+            VideoCodec::Hevc => QualityScale::new("-crf", 0, 51, 28),
+            VideoCodec::Av1 => QualityScale::new("-crf", 0, 63, 30).zero_bitrate(),
+            VideoCodec::Vp9 => QualityScale::new("-crf", 0, 63, 31).zero_bitrate(),
+            VideoCodec::Vp8 => QualityScale::new("-crf", 4, 63, 10),
+            VideoCodec::Theora => QualityScale::new("-q:v", 0, 10, 7).higher_is_better(),
+            VideoCodec::ProRes => QualityScale::new("-q:v", 0, 32, 9),
+            VideoCodec::Mpeg4 | VideoCodec::Mpeg2 | VideoCodec::Wmv2 | VideoCodec::Flv1 => {
+                QualityScale::new("-q:v", 1, 31, 4)
+            }
+        }
+    }
+}
+
+impl AudioCodec {
+    /// 只有支持 `-q:a` VBR 的编码器才有
+    pub fn quality_scale(&self) -> Option<QualityScale> {
+        match self {
+            AudioCodec::Mp3 => Some(QualityScale::new("-q:a", 0, 9, 2)),
+            AudioCodec::Vorbis => Some(QualityScale::new("-q:a", 0, 10, 5).higher_is_better()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_mode_when_changing_value() {
+        let rate = VideoRateControl::TargetSize(50).with_value(8);
+
+        assert_eq!(rate, VideoRateControl::TargetSize(8));
+        assert_eq!(rate.value(), 8);
+        assert_eq!(
+            AudioRateControl::Quality(2).with_value(5),
+            AudioRateControl::Quality(5)
+        );
+    }
+
+    #[test]
+    fn describes_quality_scales() {
+        let crf = VideoCodec::H264.quality_scale();
+        assert!(crf.contains(0) && crf.contains(51) && !crf.contains(52));
+        assert_eq!(crf.hint(), "0-51, lower is better");
+        assert_eq!(
+            VideoCodec::Theora.quality_scale().hint(),
+            "0-10, higher is better"
+        );
+        assert!(AudioCodec::Aac.quality_scale().is_none());
+    }
+
+    #[test]
+    fn serializes_in_snake_case() {
+        let json = serde_json::to_string(&VideoRateControl::TargetSize(50)).unwrap();
+
+        assert_eq!(json, r#"{"target_size":50}"#);
+        assert_eq!(
+            serde_json::from_str::<VideoRateControl>(&json).unwrap(),
+            VideoRateControl::TargetSize(50)
+        );
+    }
+}
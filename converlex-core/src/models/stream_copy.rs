@@ -0,0 +1,151 @@
+use std::fmt::{self, Display};
+
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use super::codec::{AudioCodec, VideoCodec};
+use super::convertible_format::FormatType;
+use super::media_info::MediaInfo;
+
+/// 输入里各路流的绝对索引和编码，不认识的编码记为 `None`
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct SourceCodecs {
+    /// 不含封面图
+    pub video: Vec<(u32, Option<VideoCodec>)>,
+    pub audio: Vec<(u32, Option<AudioCodec>)>,
+    /// 字幕编码没有对应的枚举，记 ffprobe 的编码名，如 "subrip"
+    pub subtitle: Vec<(u32, Option<String>)>,
+}
+
+impl SourceCodecs {
+    pub fn from_media_info(info: &MediaInfo) -> Self {
+        Self {
+            video: info
+                .video_streams()
+                .map(|v| (v.index, v.codec.as_deref().and_then(VideoCodec::from_probe_name)))
+                .collect(),
+            audio: info
+                .audio_streams()
+                .map(|a| (a.index, a.codec.as_deref().and_then(AudioCodec::from_probe_name)))
+                .collect(),
+            subtitle: info
+                .subtitle_streams()
+                .map(|s| (s.index, s.codec.clone()))
+                .collect(),
+        }
+    }
+
+    /// 只保留会输出的那些流
+    pub fn only(&self, indices: &[u32]) -> Self {
+        Self {
+            video: self.video.iter().filter(|(i, _)| indices.contains(i)).copied().collect(),
+            audio: self.audio.iter().filter(|(i, _)| indices.contains(i)).copied().collect(),
+            subtitle: self
+                .subtitle
+                .iter()
+                .filter(|(i, _)| indices.contains(i))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// 所有视频流都能原样放进目标容器，指定了编码时还要和它一致
+    pub fn video_fits(&self, format_type: &FormatType, chosen: Option<VideoCodec>) -> bool {
+        !self.video.is_empty()
+            && self.video.iter().all(|(_, codec)| {
+                codec.is_some_and(|c| {
+                    format_type.video_codecs().contains(&c) && chosen.is_none_or(|x| x == c)
+                })
+            })
+    }
+
+    /// 同 [`SourceCodecs::video_fits`]，音频格式只有默认编码时也算能装
+    pub fn audio_fits(&self, format_type: &FormatType, chosen: Option<AudioCodec>) -> bool {
+        !self.audio.is_empty()
+            && self.audio.iter().all(|(_, codec)| {
+                codec.is_some_and(|c| {
+                    (format_type.audio_codecs().contains(&c)
+                        || format_type.effective_audio_codec(None) == Some(c))
+                        && chosen.is_none_or(|x| x == c)
+                })
+            })
+    }
+}
+
+/// 任务列表里显示的任务类型
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JobKind {
+    /// 所有流都直接复制，只换容器
+    Remux,
+    /// 至少有一路流要重新编码
+    Transcode,
+}
+
+impl Display for JobKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobKind::Remux => write!(f, "remux"),
+            JobKind::Transcode => write!(f, "transcode"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::media_format::{Audio, Video};
+    use crate::models::media_info::{StreamInfo, StreamKind};
+
+    fn stream(index: u32, kind: StreamKind, codec: &str) -> StreamInfo {
+        StreamInfo {
+            index,
+            kind,
+            codec: Some(codec.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn collects_codecs_by_kind() {
+        let cover = StreamInfo {
+            is_attached_pic: true,
+            ..stream(3, StreamKind::Video, "mjpeg")
+        };
+        let info = MediaInfo {
+            streams: vec![
+                stream(0, StreamKind::Video, "hevc"),
+                stream(1, StreamKind::Audio, "pcm_s24le"),
+                stream(2, StreamKind::Subtitle, "subrip"),
+                cover,
+            ],
+            ..Default::default()
+        };
+        let codecs = SourceCodecs::from_media_info(&info);
+
+        assert_eq!(codecs.video, [(0, Some(VideoCodec::Hevc))]);
+        assert_eq!(codecs.audio, [(1, None)]);
+        assert_eq!(codecs.subtitle, [(2, Some("subrip".to_string()))]);
+        assert_eq!(codecs.only(&[1]).video, []);
+    }
+
+    #[test]
+    fn checks_whether_streams_fit_the_container() {
+        let codecs = SourceCodecs {
+            video: vec![(0, Some(VideoCodec::H264))],
+            audio: vec![(1, Some(AudioCodec::Aac))],
+            ..Default::default()
+        };
+        let mp4 = FormatType::Video(Video::Mp4);
+        let webm = FormatType::Video(Video::Webm);
+
+        assert!(codecs.video_fits(&mp4, None));
+        assert!(!codecs.video_fits(&mp4, Some(VideoCodec::Hevc)));
+        assert!(!codecs.video_fits(&webm, None));
+        assert!(codecs.audio_fits(&mp4, Some(AudioCodec::Aac)));
+        // 只有默认编码的音频格式
+        assert!(codecs.audio_fits(&FormatType::Audio(Audio::Aac), None));
+        assert!(!codecs.audio_fits(&FormatType::Audio(Audio::Mp3), None));
+        assert!(!SourceCodecs::default().video_fits(&mp4, None));
+    }
+}
@@ -0,0 +1,189 @@
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use super::convertible_format::FormatType;
+use super::media_info::{MediaInfo, StreamKind};
+
+/// 可以手动设置的流标记
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Disposition {
+    Default,
+    Forced,
+}
+
+/// 手动选择的一路流，列表顺序就是输出里的顺序
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct StreamSelection {
+    /// 在输入文件中的绝对索引，对应 `-map 0:N`
+    pub index: u32,
+    pub kind: StreamKind,
+    /// 封面图要保留 attached_pic 标记，否则会变成普通视频流
+    pub attached_pic: bool,
+    pub included: bool,
+    pub default: bool,
+    pub forced: bool,
+}
+
+impl StreamSelection {
+    /// 按输入顺序列出所有流，音视频和字幕默认都保留，标记沿用输入的
+    pub fn from_media_info(info: &MediaInfo) -> Vec<Self> {
+        info.streams
+            .iter()
+            .map(|stream| Self {
+                index: stream.index,
+                kind: stream.kind,
+                attached_pic: stream.is_attached_pic,
+                included: matches!(
+                    stream.kind,
+                    StreamKind::Video | StreamKind::Audio | StreamKind::Subtitle
+                ),
+                default: stream.is_default,
+                forced: stream.is_forced,
+            })
+            .collect()
+    }
+
+    pub fn toggle(&mut self, disposition: Disposition) {
+        match disposition {
+            Disposition::Default => self.default = !self.default,
+            Disposition::Forced => self.forced = !self.forced,
+        }
+    }
+
+    /// `-disposition` 的值，都不选时用 "0" 清掉输入带过来的标记
+    fn disposition(&self) -> String {
+        let mut flags = vec![];
+        if self.attached_pic {
+            flags.push("attached_pic");
+        }
+        if self.default {
+            flags.push("default");
+        }
+        if self.forced {
+            flags.push("forced");
+        }
+        if flags.is_empty() {
+            "0".into()
+        } else {
+            flags.join("+")
+        }
+    }
+
+    /// 输出格式能不能装下这类流，纯音频格式只保留音频
+    fn fits(&self, format_type: &FormatType) -> bool {
+        match format_type {
+            FormatType::Audio(_) => self.kind == StreamKind::Audio,
+            _ => true,
+        }
+    }
+}
+
+/// 被选中且输出格式能装下的流，按输出顺序
+pub fn selected_streams<'a>(
+    streams: &'a [StreamSelection],
+    format_type: &'a FormatType,
+) -> impl Iterator<Item = &'a StreamSelection> {
+    streams.iter().filter(|s| s.included && s.fits(format_type))
+}
+
+/// 生成 `-map` 和 `-disposition` 参数，`-disposition` 按输出流的序号指定
+pub fn stream_map_args(
+    streams: &[StreamSelection],
+    format_type: &FormatType,
+) -> Result<Vec<String>, String> {
+    let selected: Vec<_> = selected_streams(streams, format_type).collect();
+    if selected.is_empty() {
+        return Err("No streams selected for this output format".into());
+    }
+
+    let mut args = vec![];
+    for stream in &selected {
+        args.extend(["-map".into(), format!("0:{}", stream.index)]);
+    }
+    for (output_index, stream) in selected.iter().enumerate() {
+        args.extend([format!("-disposition:{output_index}"), stream.disposition()]);
+    }
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::media_format::{Audio, Video};
+    use crate::models::media_info::StreamInfo;
+
+    fn streams() -> Vec<StreamSelection> {
+        let stream = |index, kind| StreamInfo {
+            index,
+            kind,
+            ..Default::default()
+        };
+        let info = MediaInfo {
+            streams: vec![
+                StreamInfo {
+                    is_default: true,
+                    ..stream(0, StreamKind::Video)
+                },
+                stream(1, StreamKind::Audio),
+                StreamInfo {
+                    is_forced: true,
+                    ..stream(2, StreamKind::Subtitle)
+                },
+                stream(3, StreamKind::Attachment),
+            ],
+            ..Default::default()
+        };
+        StreamSelection::from_media_info(&info)
+    }
+
+    #[test]
+    fn includes_media_streams_with_their_flags() {
+        let streams = streams();
+
+        assert_eq!(streams.iter().filter(|s| s.included).count(), 3);
+        assert!(!streams[3].included);
+        assert!(streams[0].default && streams[2].forced);
+    }
+
+    #[test]
+    fn maps_streams_in_list_order() {
+        let mut streams = streams();
+        streams.swap(1, 2);
+        streams[0].toggle(Disposition::Default);
+        streams[2].toggle(Disposition::Default);
+        let args = stream_map_args(&streams, &FormatType::Video(Video::Mkv)).unwrap();
+
+        assert_eq!(
+            args.join(" "),
+            "-map 0:0 -map 0:2 -map 0:1 -disposition:0 0 -disposition:1 forced -disposition:2 default"
+        );
+    }
+
+    #[test]
+    fn keeps_only_audio_for_audio_outputs() {
+        let mut streams = streams();
+        let mp3 = FormatType::Audio(Audio::Mp3);
+        assert_eq!(
+            stream_map_args(&streams, &mp3).unwrap(),
+            ["-map", "0:1", "-disposition:0", "0"]
+        );
+
+        streams[1].included = false;
+        assert!(stream_map_args(&streams, &mp3).is_err());
+    }
+
+    #[test]
+    fn keeps_cover_art_marked() {
+        let cover = StreamSelection {
+            index: 0,
+            kind: StreamKind::Video,
+            attached_pic: true,
+            included: true,
+            default: true,
+            forced: false,
+        };
+
+        assert_eq!(cover.disposition(), "attached_pic+default");
+    }
+}
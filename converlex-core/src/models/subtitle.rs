@@ -0,0 +1,143 @@
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use super::media_format::{Subtitle, Video};
+
+/// 外挂字幕文件的用法
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum SubtitleMode {
+    /// 作为字幕轨封装进输出，播放时可以开关
+    #[default]
+    Embed,
+    /// 用 subtitles 滤镜画进画面，视频要重新编码
+    BurnIn,
+}
+
+impl SubtitleMode {
+    pub const ALL: [SubtitleMode; 2] = [SubtitleMode::Embed, SubtitleMode::BurnIn];
+}
+
+impl Display for SubtitleMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubtitleMode::Embed => write!(f, "Embed"),
+            SubtitleMode::BurnIn => write!(f, "Burn In"),
+        }
+    }
+}
+
+/// 加到视频输出里的外挂字幕文件
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct SubtitleFile {
+    pub path: PathBuf,
+    pub mode: SubtitleMode,
+}
+
+impl SubtitleFile {
+    pub fn new(path: impl Into<PathBuf>, mode: SubtitleMode) -> Self {
+        Self {
+            path: path.into(),
+            mode,
+        }
+    }
+
+    /// 按扩展名判断的字幕格式
+    pub fn format(&self) -> Result<Subtitle, String> {
+        self.path
+            .extension()
+            .and_then(|ext| Subtitle::from_extension(&ext.to_string_lossy()))
+            .ok_or_else(|| format!("{} isn't a supported subtitle file", self.path.display()))
+    }
+
+    /// 封装进 `container` 的参数：字幕文件是第 `input` 个输入，
+    /// `output_index` 是它在输出字幕流中的序号，编码只对这一路生效
+    pub fn embed_args(
+        &self,
+        container: &Video,
+        input: usize,
+        output_index: usize,
+    ) -> Result<Vec<String>, String> {
+        let encoder = container.subtitle_encoder(&self.format()?).ok_or_else(|| {
+            format!("{container} can't hold text subtitles, burn them in instead")
+        })?;
+        Ok(vec![
+            "-map".into(),
+            format!("{input}:s:0"),
+            format!("-c:s:{output_index}"),
+            encoder.into(),
+        ])
+    }
+
+    /// 烧录用的滤镜。快速定位后画面的时间戳从 0 开始，
+    /// 要先加回 `seek` 秒再叠字幕，叠完再归零
+    pub fn burn_in_filter(&self, seek: Option<f64>) -> Result<String, String> {
+        self.format()?;
+        let filter = format!("subtitles={}", escape_filter_path(&self.path));
+        Ok(match seek {
+            Some(secs) => format!("setpts=PTS+{secs}/TB,{filter},setpts=PTS-STARTPTS"),
+            None => filter,
+        })
+    }
+}
+
+/// 滤镜参数里的路径要转义两层：先是选项值里的 `\ ' :`，再是滤镜图里的 `\ ' [ ] , ;`
+fn escape_filter_path(path: &Path) -> String {
+    let escape = |text: &str, special: &[char]| {
+        text.chars().fold(String::new(), |mut out, c| {
+            if special.contains(&c) {
+                out.push('\\');
+            }
+            out.push(c);
+            out
+        })
+    };
+    let value = escape(&path.to_string_lossy(), &['\\', '\'', ':']);
+    escape(&value, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_filter_paths_twice() {
+        assert_eq!(escape_filter_path(Path::new("/subs/a.srt")), "/subs/a.srt");
+        assert_eq!(
+            escape_filter_path(Path::new(r"C:\subs\it's [1].ass")),
+            r"C\\:\\\\subs\\\\it\\\'s \[1\].ass"
+        );
+    }
+
+    #[test]
+    fn embeds_with_the_container_encoder() {
+        let ass = SubtitleFile::new("a.ass", SubtitleMode::Embed);
+
+        assert_eq!(
+            ass.embed_args(&Video::Mkv, 1, 2).unwrap(),
+            ["-map", "1:s:0", "-c:s:2", "ass"]
+        );
+        assert_eq!(ass.embed_args(&Video::Mp4, 1, 0).unwrap()[3], "mov_text");
+        assert!(ass.embed_args(&Video::Avi, 1, 0).is_err());
+    }
+
+    #[test]
+    fn shifts_timestamps_around_burned_subtitles_after_fast_seek() {
+        let srt = SubtitleFile::new("a.srt", SubtitleMode::BurnIn);
+
+        assert_eq!(srt.burn_in_filter(None).unwrap(), "subtitles=a.srt");
+        assert_eq!(
+            srt.burn_in_filter(Some(5.0)).unwrap(),
+            "setpts=PTS+5/TB,subtitles=a.srt,setpts=PTS-STARTPTS"
+        );
+        assert!(
+            SubtitleFile::new("a.txt", SubtitleMode::BurnIn)
+                .burn_in_filter(None)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,384 @@
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use crate::utils::{
+    ffmpeg_concat::{ConcatInput, ConcatTask},
+    ffmpeg_sequence::{FrameExportTask, FrameSampling, ImageSequence, ImageSequenceTask},
+    utils::{file_name_part, get_output_dir, get_output_path, get_tagged_output_path},
+    ffmpeg_progress::FfmpegProgress,
+    ffmpeg_wrapper::{FfmpegEntry, FfmpegTask, ProgressMsg},
+    task_control::ControlMsg,
+};
+
+use super::{
+    convertible_format::{ConvertibleFormat, FormatType},
+    loudness::LoudnessMeasurement,
+    media_format::Video,
+    media_info::MediaInfo,
+    stream_copy::{JobKind, SourceCodecs},
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "vizia", derive(Lens, Data))]
+pub struct Task {
+    pub input_path: String,
+    pub output_path: String,
+    // pub config: ConvertConfig,
+    pub supported_output_formats: Vec<Arc<dyn ConvertibleFormat>>,
+    pub selected_output_format: usize,
+    pub auto_rename: bool,
+    /// 列表中勾选，用于“开始选中”
+    pub selected: bool,
+    pub progress: f32,
+    pub progress_info: Option<FfmpegProgress>,
+    pub media_info: Option<MediaInfo>,
+    pub task_type: TaskType,
+    pub status: TaskStatus,
+    /// 标准化之前测得的响度，运行时填入，显示在界面上
+    pub loudness: Option<LoudnessMeasurement>,
+}
+
+impl Task {
+    pub fn new(
+        input_path: String,
+        output_path: String,
+        supported_output_formats: Vec<Arc<dyn ConvertibleFormat>>,
+        selected_output_format: usize,
+        ffmpeg_entry: FfmpegEntry,
+    ) -> Self {
+        let output_format = supported_output_formats
+            .get(selected_output_format)
+            .unwrap()
+            .clone();
+        Self {
+            input_path,
+            output_path,
+            supported_output_formats,
+            selected_output_format,
+            auto_rename: true,
+            selected: false,
+            progress: 0.0,
+            progress_info: None,
+            media_info: None,
+            task_type: TaskType::Ffmpeg(FfmpegTask::new(ffmpeg_entry, output_format)),
+            status: TaskStatus::Queued,
+            loudness: None,
+        }
+    }
+
+    /// 拼接任务，`input_path` 记第一个输入，用于生成输出文件名
+    pub fn new_concat(
+        inputs: Vec<String>,
+        output_path: String,
+        supported_output_formats: Vec<Arc<dyn ConvertibleFormat>>,
+        selected_output_format: usize,
+        ffmpeg_entry: FfmpegEntry,
+    ) -> Self {
+        let mut task = Self::new(
+            inputs.first().cloned().unwrap_or_default(),
+            output_path,
+            supported_output_formats,
+            selected_output_format,
+            ffmpeg_entry,
+        );
+        let ffmpeg_task = task.task_type.ffmpeg().clone();
+        task.task_type = TaskType::Concat(ConcatTask::new(
+            ffmpeg_task,
+            inputs.into_iter().map(ConcatInput::new).collect(),
+        ));
+        task
+    }
+
+    /// 把视频导出成编号图片，`output_dir` 是存放图片的文件夹
+    pub fn new_frame_export(
+        input_path: String,
+        output_dir: String,
+        supported_output_formats: Vec<Arc<dyn ConvertibleFormat>>,
+        selected_output_format: usize,
+        ffmpeg_entry: FfmpegEntry,
+    ) -> Self {
+        let mut task = Self::new(
+            input_path,
+            output_dir,
+            supported_output_formats,
+            selected_output_format,
+            ffmpeg_entry,
+        );
+        let ffmpeg_task = task.task_type.ffmpeg().clone();
+        task.task_type =
+            TaskType::FrameExport(FrameExportTask::new(ffmpeg_task, FrameSampling::default()));
+        task
+    }
+
+    /// 把编号图片序列编码成视频，`input_path` 记序列所在的文件夹
+    pub fn new_image_sequence(
+        sequence: ImageSequence,
+        output_path: String,
+        supported_output_formats: Vec<Arc<dyn ConvertibleFormat>>,
+        selected_output_format: usize,
+        ffmpeg_entry: FfmpegEntry,
+    ) -> Self {
+        let mut task = Self::new(
+            sequence.dir.to_string_lossy().to_string(),
+            output_path,
+            supported_output_formats,
+            selected_output_format,
+            ffmpeg_entry,
+        );
+        let ffmpeg_task = task.task_type.ffmpeg().clone();
+        task.task_type = TaskType::ImageSequence(ImageSequenceTask::new(ffmpeg_task, sequence));
+        task
+    }
+
+    /// 按当前选择的格式和探测结果，判断任务是直接复制流还是重新编码
+    pub fn job_kind(&self) -> JobKind {
+        let output_format = Arc::clone(&self.supported_output_formats[self.selected_output_format]);
+        match &self.task_type {
+            TaskType::Ffmpeg(ffmpeg_task) => ffmpeg_task
+                .clone()
+                .output_format(output_format)
+                .source_codecs(self.media_info.as_ref().map(SourceCodecs::from_media_info))
+                .job_kind(),
+            TaskType::Concat(concat_task) => ConcatTask {
+                ffmpeg: concat_task.ffmpeg.clone().output_format(output_format),
+                inputs: concat_task.inputs.clone(),
+            }
+            .job_kind(),
+            // 图片和视频之间总要重新编码
+            TaskType::FrameExport(_) | TaskType::ImageSequence(_) => JobKind::Transcode,
+        }
+    }
+
+    /// 自动命名的输出路径；提取音轨或字幕时文件名带上轨道序号、语言和标题，
+    /// 导出帧时是输入旁边的文件夹
+    pub fn default_output_path(&self, overwrite: bool) -> String {
+        if let TaskType::FrameExport(_) = self.task_type {
+            return get_output_dir(&self.input_path, "frames", overwrite);
+        }
+        let format = self.supported_output_formats[self.selected_output_format].as_any();
+        match self.track_tag() {
+            Some(tag) => get_tagged_output_path(&self.input_path, &tag, format, overwrite),
+            None => get_output_path(&self.input_path, format, overwrite),
+        }
+    }
+
+    /// 如 "audio2_eng_Commentary"、"sub1_chi"
+    fn track_tag(&self) -> Option<String> {
+        let ffmpeg_task = self.task_type.ffmpeg();
+        let info = self.media_info.as_ref();
+        let (prefix, track, stream) = match (ffmpeg_task.audio_track, ffmpeg_task.subtitle_track) {
+            (Some(track), _) => (
+                "audio",
+                track,
+                info.and_then(|i| i.audio_streams().nth(track)),
+            ),
+            (None, Some(track)) => (
+                "sub",
+                track,
+                info.and_then(|i| i.subtitle_streams().nth(track)),
+            ),
+            (None, None) => return None,
+        };
+        let mut parts = vec![format!("{prefix}{}", track + 1)];
+        if let Some(stream) = stream {
+            parts.extend(stream.language().map(file_name_part));
+            parts.extend(stream.title.as_deref().map(file_name_part));
+        }
+        parts.retain(|part| !part.is_empty());
+        Some(parts.join("_"))
+    }
+
+    /// 清除上次运行的状态，重新排队
+    pub fn reset(&mut self) {
+        self.progress = 0.0;
+        self.progress_info = None;
+        self.status = TaskStatus::Queued;
+        self.loudness = None;
+    }
+
+    /// 选择 GIF 输出时按时长和画面大小估算的文件大小（字节），信息不全时为 `None`
+    pub fn gif_size_estimate(&self) -> Option<u64> {
+        let output_format = &self.supported_output_formats[self.selected_output_format];
+        if !matches!(
+            output_format.get_format_type(),
+            FormatType::Video(Video::Gif)
+        ) {
+            return None;
+        }
+        let media_info = self.media_info.as_ref()?;
+        let ffmpeg = self.task_type.ffmpeg();
+        let secs = ffmpeg.trim.output_duration(media_info.duration)?;
+        Some(ffmpeg.gif.estimate_size(secs, media_info.resolution()?))
+    }
+}
+
+impl Into<FfmpegTask> for Task {
+    fn into(self) -> FfmpegTask {
+        match self.task_type {
+            TaskType::Ffmpeg(ffmpeg_task) => ffmpeg_task,
+            TaskType::Concat(concat_task) => concat_task.ffmpeg,
+            TaskType::FrameExport(export_task) => export_task.ffmpeg,
+            TaskType::ImageSequence(sequence_task) => sequence_task.ffmpeg,
+        }
+    } 
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum TaskType {
+    Ffmpeg(FfmpegTask),
+    /// 多个输入按顺序拼成一个输出
+    Concat(ConcatTask),
+    /// 视频导出成编号图片
+    FrameExport(FrameExportTask),
+    /// 编号图片序列编码成视频
+    ImageSequence(ImageSequenceTask),
+}
+
+impl TaskType {
+    /// 编码参数，拼接和图片序列任务也用同一套
+    pub fn ffmpeg(&self) -> &FfmpegTask {
+        match self {
+            TaskType::Ffmpeg(ffmpeg_task) => ffmpeg_task,
+            TaskType::Concat(concat_task) => &concat_task.ffmpeg,
+            TaskType::FrameExport(export_task) => &export_task.ffmpeg,
+            TaskType::ImageSequence(sequence_task) => &sequence_task.ffmpeg,
+        }
+    }
+
+    pub fn ffmpeg_mut(&mut self) -> &mut FfmpegTask {
+        match self {
+            TaskType::Ffmpeg(ffmpeg_task) => ffmpeg_task,
+            TaskType::Concat(concat_task) => &mut concat_task.ffmpeg,
+            TaskType::FrameExport(export_task) => &mut export_task.ffmpeg,
+            TaskType::ImageSequence(sequence_task) => &mut sequence_task.ffmpeg,
+        }
+    }
+
+    /// 用 [`FfmpegTask`] 的 builder 修改编码参数
+    pub fn map_ffmpeg(&mut self, f: impl FnOnce(FfmpegTask) -> FfmpegTask) {
+        let ffmpeg_task = self.ffmpeg_mut();
+        *ffmpeg_task = f(ffmpeg_task.clone());
+    }
+
+    pub fn build(&self) -> Result<(PathBuf, Vec<Vec<String>>), String> {
+        match self {
+            TaskType::Ffmpeg(ffmpeg_task) => ffmpeg_task.clone().build(),
+            TaskType::Concat(concat_task) => concat_task.build(),
+            TaskType::FrameExport(export_task) => export_task.build(),
+            TaskType::ImageSequence(sequence_task) => sequence_task.build(),
+        }
+    }
+
+    pub async fn run_with_progress(
+        &self,
+        task_id: String,
+        tx: mpsc::UnboundedSender<ProgressMsg>,
+        control: mpsc::UnboundedReceiver<ControlMsg>,
+    ) {
+        match self {
+            TaskType::Ffmpeg(ffmpeg_task) => {
+                ffmpeg_task.run_with_progress(task_id, tx, control).await
+            }
+            TaskType::Concat(concat_task) => {
+                concat_task.run_with_progress(task_id, tx, control).await
+            }
+            TaskType::FrameExport(export_task) => {
+                export_task.run_with_progress(task_id, tx, control).await
+            }
+            TaskType::ImageSequence(sequence_task) => {
+                sequence_task.run_with_progress(task_id, tx, control).await
+            }
+        }
+    }
+}
+
+impl From<FfmpegTask> for TaskType {
+    fn from(ffmpeg_task: FfmpegTask) -> Self {
+        TaskType::Ffmpeg(ffmpeg_task)
+    }
+}
+
+impl From<ConcatTask> for TaskType {
+    fn from(concat_task: ConcatTask) -> Self {
+        TaskType::Concat(concat_task)
+    }
+}
+
+impl From<FrameExportTask> for TaskType {
+    fn from(export_task: FrameExportTask) -> Self {
+        TaskType::FrameExport(export_task)
+    }
+}
+
+impl From<ImageSequenceTask> for TaskType {
+    fn from(sequence_task: ImageSequenceTask) -> Self {
+        TaskType::ImageSequence(sequence_task)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum TaskStatus {
+    Queued,
+    /// 已提交给调度器，等待空位
+    Pending,
+    Running,
+    Paused,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::models::media_format::{Audio, MediaFormat};
+    use crate::models::media_info::{StreamInfo, StreamKind};
+
+    fn audio_track_task(track: usize) -> Task {
+        let audio = |index, language: &str, title: Option<&str>| StreamInfo {
+            index,
+            kind: StreamKind::Audio,
+            language: Some(language.into()),
+            title: title.map(String::from),
+            ..Default::default()
+        };
+        let formats: Vec<Arc<dyn ConvertibleFormat>> =
+            vec![Arc::new(MediaFormat::Audio(Audio::Mp3))];
+        let mut task = Task::new(
+            "/nonexistent/movie.mkv".into(),
+            String::new(),
+            formats,
+            0,
+            FfmpegEntry::Env,
+        );
+        task.media_info = Some(MediaInfo {
+            streams: vec![
+                audio(1, "und", None),
+                audio(2, "eng", Some("Director's Cut")),
+            ],
+            ..Default::default()
+        });
+        task.task_type.map_ffmpeg(|t| t.audio_track(Some(track)));
+        task
+    }
+
+    #[test]
+    fn names_track_outputs_after_the_track() {
+        assert_eq!(
+            audio_track_task(1).default_output_path(false),
+            "/nonexistent/movie_audio2_eng_Director-s-Cut.mp3"
+        );
+        // und 不算语言
+        assert_eq!(
+            audio_track_task(0).default_output_path(false),
+            "/nonexistent/movie_audio1.mp3"
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::fmt::{self, Display};
+
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+/// 起点的定位方式
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum SeekMode {
+    /// `-ss` 放在 `-i` 之后：从头解码再丢弃，起点精确到帧，但起点越靠后越慢
+    #[default]
+    Accurate,
+    /// `-ss` 放在 `-i` 之前：直接跳到附近的关键帧，速度快，复制流时起点可能提前
+    Fast,
+}
+
+impl SeekMode {
+    pub const ALL: [SeekMode; 2] = [SeekMode::Accurate, SeekMode::Fast];
+}
+
+impl Display for SeekMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeekMode::Accurate => write!(f, "Accurate"),
+            SeekMode::Fast => write!(f, "Fast"),
+        }
+    }
+}
+
+/// 界面上分别编辑的三个时间点
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrimPoint {
+    Start,
+    End,
+    Duration,
+}
+
+/// 截取片段，时间都以输入文件的秒数计。`end` 和 `duration` 只能给一个
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct Trim {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+    pub duration: Option<f64>,
+    pub seek: SeekMode,
+}
+
+impl Trim {
+    pub fn is_empty(&self) -> bool {
+        self.start.is_none() && self.end.is_none() && self.duration.is_none()
+    }
+
+    pub fn get(&self, point: TrimPoint) -> Option<f64> {
+        match point {
+            TrimPoint::Start => self.start,
+            TrimPoint::End => self.end,
+            TrimPoint::Duration => self.duration,
+        }
+    }
+
+    /// 设置结束时间或时长时清掉另一个，两者只保留最后编辑的
+    pub fn with(mut self, point: TrimPoint, value: Option<f64>) -> Self {
+        match point {
+            TrimPoint::Start => self.start = value,
+            TrimPoint::End => {
+                self.end = value;
+                if value.is_some() {
+                    self.duration = None;
+                }
+            }
+            TrimPoint::Duration => {
+                self.duration = value;
+                if value.is_some() {
+                    self.end = None;
+                }
+            }
+        }
+        self
+    }
+
+    /// `input_duration` 未知时只检查时间点之间的关系
+    pub fn validate(&self, input_duration: Option<f64>) -> Result<(), String> {
+        if self.end.is_some() && self.duration.is_some() {
+            return Err("Trim end and duration can't both be set".into());
+        }
+        let start = self.start.unwrap_or(0.0);
+        if start < 0.0 {
+            return Err("Trim start can't be negative".into());
+        }
+        if let Some(end) = self.end
+            && end <= start
+        {
+            return Err(format!(
+                "Trim end {} must be after start {}",
+                format_timestamp_ms(end),
+                format_timestamp_ms(start)
+            ));
+        }
+        if self.duration.is_some_and(|d| d <= 0.0) {
+            return Err("Trim duration must be positive".into());
+        }
+        if let Some(total) = input_duration
+            && start >= total
+        {
+            return Err(format!(
+                "Trim start {} is past the end of the input ({})",
+                format_timestamp_ms(start),
+                format_timestamp_ms(total)
+            ));
+        }
+        Ok(())
+    }
+
+    /// 用户要的片段长度，只给了起点时为 None
+    fn length(&self) -> Option<f64> {
+        self.duration
+            .or(self.end.map(|end| end - self.start.unwrap_or(0.0)))
+    }
+
+    /// 截取后的时长，用于进度和目标大小的换算；超出输入的部分不计
+    pub fn output_duration(&self, input_duration: Option<f64>) -> Option<f64> {
+        let start = self.start.unwrap_or(0.0);
+        let remaining = input_duration.map(|total| (total - start).max(0.0));
+        match (self.length(), remaining) {
+            (Some(wanted), Some(remaining)) => Some(wanted.min(remaining)),
+            (wanted, remaining) => wanted.or(remaining),
+        }
+    }
+
+    /// 快速定位时输入端跳过的秒数，输出的时间戳从这里开始算 0
+    pub fn input_seek(&self) -> Option<f64> {
+        self.start.filter(|_| self.seek == SeekMode::Fast)
+    }
+
+    /// 放在 `-i` 之前的参数
+    pub fn input_args(&self) -> Vec<String> {
+        match self.input_seek() {
+            Some(start) => vec!["-ss".into(), format_seconds(start)],
+            None => vec![],
+        }
+    }
+
+    /// 放在 `-i` 之后的参数，结束点统一换算成 `-t`
+    pub fn output_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let (SeekMode::Accurate, Some(start)) = (self.seek, self.start) {
+            args.extend(["-ss".into(), format_seconds(start)]);
+        }
+        if let Some(length) = self.length() {
+            args.extend(["-t".into(), format_seconds(length)]);
+        }
+        args
+    }
+}
+
+/// 解析 "hh:mm:ss.ms"、"mm:ss.ms" 或秒数 "ss.ms"
+pub fn parse_timestamp(s: &str) -> Result<f64, String> {
+    let s = s.trim();
+    let invalid = || format!("invalid timestamp \"{s}\", expected hh:mm:ss.ms");
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() > 3 || parts.iter().any(|p| p.is_empty()) {
+        return Err(invalid());
+    }
+
+    let (last, rest) = parts.split_last().ok_or_else(invalid)?;
+    let seconds: f64 = last.parse().map_err(|_| invalid())?;
+    if !seconds.is_finite() || seconds < 0.0 || (!rest.is_empty() && seconds >= 60.0) {
+        return Err(invalid());
+    }
+
+    let mut total = 0.0;
+    for (i, part) in rest.iter().enumerate() {
+        let value: u32 = part.parse().map_err(|_| invalid())?;
+        // 小时不限，分钟必须小于 60
+        if i == rest.len() - 1 && rest.len() == 2 && value >= 60 {
+            return Err(invalid());
+        }
+        total = total * 60.0 + value as f64;
+    }
+    Ok(total * 60.0 + seconds)
+}
+
+/// 秒数格式化为 hh:mm:ss.mmm
+pub fn format_timestamp_ms(secs: f64) -> String {
+    let total_ms = (secs.max(0.0) * 1000.0).round() as u64;
+    let ms = total_ms % 1000;
+    let total = total_ms / 1000;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        total / 3600,
+        total % 3600 / 60,
+        total % 60,
+        ms
+    )
+}
+
+/// 传给 ffmpeg 的秒数，保留到毫秒
+fn format_seconds(secs: f64) -> String {
+    format!("{:.3}", secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trim(start: Option<f64>, end: Option<f64>, duration: Option<f64>, seek: SeekMode) -> Trim {
+        Trim {
+            start,
+            end,
+            duration,
+            seek,
+        }
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("90.5"), Ok(90.5));
+        assert_eq!(parse_timestamp("01:30"), Ok(90.0));
+        assert_eq!(parse_timestamp("1:02:03.250"), Ok(3723.25));
+        assert_eq!(parse_timestamp(" 100:00:00 "), Ok(360000.0));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for s in [
+            "", "abc", "1:60", "1:60:00", "1::00", "1:2:3:4", "-5", "inf",
+        ] {
+            assert!(parse_timestamp(s).is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn formats_timestamps_with_milliseconds() {
+        assert_eq!(format_timestamp_ms(3723.25), "01:02:03.250");
+        assert_eq!(format_timestamp_ms(-1.0), "00:00:00.000");
+    }
+
+    #[test]
+    fn keeps_only_the_last_edited_end_point() {
+        let trim = Trim::default()
+            .with(TrimPoint::End, Some(10.0))
+            .with(TrimPoint::Duration, Some(5.0));
+
+        assert_eq!(trim.end, None);
+        assert_eq!(trim.get(TrimPoint::Duration), Some(5.0));
+        assert_eq!(trim.with(TrimPoint::End, Some(8.0)).duration, None);
+    }
+
+    #[test]
+    fn places_seek_by_mode() {
+        let accurate = trim(Some(5.0), Some(15.0), None, SeekMode::Accurate);
+        assert!(accurate.input_args().is_empty());
+        assert_eq!(accurate.output_args(), ["-ss", "5.000", "-t", "10.000"]);
+
+        let fast = Trim {
+            seek: SeekMode::Fast,
+            ..accurate
+        };
+        assert_eq!(fast.input_args(), ["-ss", "5.000"]);
+        assert_eq!(fast.output_args(), ["-t", "10.000"]);
+    }
+
+    #[test]
+    fn validates_time_points() {
+        assert!(
+            trim(Some(5.0), Some(5.0), None, SeekMode::Accurate)
+                .validate(None)
+                .is_err()
+        );
+        assert!(
+            trim(None, None, Some(0.0), SeekMode::Accurate)
+                .validate(None)
+                .is_err()
+        );
+        assert!(
+            trim(Some(-1.0), None, None, SeekMode::Accurate)
+                .validate(None)
+                .is_err()
+        );
+        assert!(
+            trim(Some(60.0), None, None, SeekMode::Accurate)
+                .validate(Some(30.0))
+                .is_err()
+        );
+        assert!(
+            trim(Some(10.0), None, Some(5.0), SeekMode::Fast)
+                .validate(Some(30.0))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn output_duration_is_capped_by_input() {
+        assert_eq!(
+            trim(Some(10.0), None, None, SeekMode::Accurate).output_duration(Some(30.0)),
+            Some(20.0)
+        );
+        assert_eq!(
+            trim(Some(10.0), None, Some(60.0), SeekMode::Accurate).output_duration(Some(30.0)),
+            Some(20.0)
+        );
+        assert_eq!(
+            trim(None, Some(5.0), None, SeekMode::Accurate).output_duration(None),
+            Some(5.0)
+        );
+        assert_eq!(Trim::default().output_duration(None), None);
+    }
+}
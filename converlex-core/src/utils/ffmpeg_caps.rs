@@ -0,0 +1,426 @@
+use std::collections::{HashMap, HashSet};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use tokio::process::Command;
+
+use crate::models::codec::{AudioCodec, VideoCodec};
+use crate::models::convertible_format::{ConvertibleFormat, FormatType};
+use crate::models::media_format::{Audio, Image, MediaFormat, Subtitle, Video};
+use crate::models::media_info::StreamKind;
+
+use super::ffmpeg_wrapper::FfmpegEntry;
+
+/// 每个 FfmpegEntry 只探测一次
+static CACHE: OnceLock<Mutex<HashMap<FfmpegEntry, Arc<FfmpegCaps>>>> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncoderInfo {
+    pub name: String,
+    pub kind: StreamKind,
+    pub description: String,
+}
+
+/// ffmpeg 构建支持的功能
+#[derive(Debug, Clone, Default)]
+pub struct FfmpegCaps {
+    /// 如 "7.0.2"，发行版构建可能带后缀
+    pub version: String,
+    /// configure 时打开的许可相关开关：gpl / version3 / nonfree
+    pub license_flags: Vec<String>,
+    pub encoders: Vec<EncoderInfo>,
+    pub muxers: HashSet<String>,
+    pub hwaccels: Vec<String>,
+}
+
+impl FfmpegCaps {
+    /// 有缓存时直接返回，否则探测并缓存
+    pub async fn get_or_probe(entry: &FfmpegEntry) -> anyhow::Result<Arc<Self>> {
+        let cache = CACHE.get_or_init(Default::default);
+        if let Some(caps) = cache.lock().unwrap().get(entry) {
+            return Ok(Arc::clone(caps));
+        }
+
+        let caps = Arc::new(Self::probe(entry).await?);
+        cache
+            .lock()
+            .unwrap()
+            .insert(entry.clone(), Arc::clone(&caps));
+        Ok(caps)
+    }
+
+    /// 丢掉缓存，例如用户替换了同一路径下的 ffmpeg
+    pub fn invalidate(entry: &FfmpegEntry) {
+        if let Some(cache) = CACHE.get() {
+            cache.lock().unwrap().remove(entry);
+        }
+    }
+
+    pub async fn probe(entry: &FfmpegEntry) -> anyhow::Result<Self> {
+        let version = run_ffmpeg(entry, &["-hide_banner", "-version"]).await?;
+        let encoders = run_ffmpeg(entry, &["-hide_banner", "-encoders"]).await?;
+        let muxers = run_ffmpeg(entry, &["-hide_banner", "-muxers"]).await?;
+        let hwaccels = run_ffmpeg(entry, &["-hide_banner", "-hwaccels"]).await?;
+
+        let (version, license_flags) = parse_version(&version);
+        Ok(Self {
+            version,
+            license_flags,
+            encoders: parse_encoders(&encoders),
+            muxers: parse_muxers(&muxers),
+            hwaccels: parse_hwaccels(&hwaccels),
+        })
+    }
+
+    /// 只确认 ffmpeg 能正常运行，返回版本号
+    pub async fn probe_version(entry: &FfmpegEntry) -> anyhow::Result<String> {
+        let output = run_ffmpeg(entry, &["-hide_banner", "-version"]).await?;
+        Ok(parse_version(&output).0)
+    }
+
+    /// 许可证的简要说明
+    pub fn license(&self) -> &'static str {
+        let has = |flag: &str| self.license_flags.iter().any(|f| f == flag);
+        match (has("nonfree"), has("gpl"), has("version3")) {
+            (true, _, _) => "nonfree (unredistributable)",
+            (false, true, true) => "GPL v3",
+            (false, true, false) => "GPL v2+",
+            (false, false, true) => "LGPL v3",
+            (false, false, false) => "LGPL v2.1+",
+        }
+    }
+
+    pub fn can_mux(&self, muxer: &str) -> bool {
+        self.muxers.contains(muxer)
+    }
+
+    pub fn has_encoder(&self, name: &str) -> bool {
+        self.encoders.iter().any(|e| e.name == name)
+    }
+
+    /// 当前 ffmpeg 不能输出该格式时返回原因
+    pub fn check_format(&self, format: &dyn ConvertibleFormat) -> Result<(), String> {
+        let muxer = format.get_muxer();
+        if !self.can_mux(&muxer) {
+            return Err(format!("This ffmpeg build has no \"{muxer}\" muxer"));
+        }
+
+        let candidates = default_encoders(&format.get_format_type());
+        if !candidates.is_empty() && !candidates.iter().any(|e| self.has_encoder(e)) {
+            return Err(format!(
+                "This ffmpeg build has none of the encoders {}",
+                candidates.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// 检查所选编码对应的编码器是否编译进了 ffmpeg
+    pub fn check_codecs(
+        &self,
+        video: Option<VideoCodec>,
+        audio: Option<AudioCodec>,
+    ) -> Result<(), String> {
+        let encoders = video
+            .map(|c| (c.to_string(), c.encoder()))
+            .into_iter()
+            .chain(audio.map(|c| (c.to_string(), c.encoder())));
+        for (name, encoder) in encoders {
+            if !self.has_encoder(encoder) {
+                return Err(format!("This ffmpeg build has no {name} encoder ({encoder})"));
+            }
+        }
+        Ok(())
+    }
+
+    /// 当前 ffmpeg 无法输出的视频/音频/字幕/图片格式及原因
+    pub fn unavailable_formats(&self) -> Vec<(String, String)> {
+        let video = Video::all().into_iter().map(MediaFormat::Video);
+        let audio = Audio::all().into_iter().map(MediaFormat::Audio);
+        let subtitle = Subtitle::all().into_iter().map(MediaFormat::Subtitle);
+        let image = Image::all().into_iter().map(MediaFormat::Image);
+
+        video
+            .chain(audio)
+            .chain(subtitle)
+            .chain(image)
+            .filter_map(|format| {
+                self.check_format(&format)
+                    .err()
+                    .map(|reason| (format.get_ext(), reason))
+            })
+            .collect()
+    }
+}
+
+/// 容器默认编码器缺失时 ffmpeg 不会自动换一个，这里列出可用的候选；
+/// 空列表表示 ffmpeg 自带的编码器即可
+fn default_encoders(format_type: &FormatType) -> &'static [&'static str] {
+    match format_type {
+        FormatType::Audio(audio) => match audio {
+            Audio::Mp3 => &["libmp3lame", "libshine"],
+            Audio::Opus => &["libopus", "opus"],
+            Audio::Vorbis => &["libvorbis", "vorbis"],
+            Audio::Amr => &["libopencore_amrnb"],
+            Audio::Dts => &["dca"],
+            _ => &[],
+        },
+        FormatType::Video(video) => video_default_encoders(video),
+        FormatType::AudioVisual(visual) => video_default_encoders(&visual.container),
+        FormatType::Image(image) => match image {
+            Image::Webp => &["libwebp"],
+            Image::Avif => &["libaom-av1", "libsvtav1"],
+            _ => &[],
+        },
+        // 字幕编码器都是 ffmpeg 自带的
+        FormatType::Subtitle(_) => &[],
+    }
+}
+
+fn video_default_encoders(video: &Video) -> &'static [&'static str] {
+    match video {
+        Video::Webm => &["libvpx-vp9", "libvpx", "libaom-av1", "libsvtav1"],
+        Video::Ogv => &["libtheora"],
+        _ => &[],
+    }
+}
+
+async fn run_ffmpeg(entry: &FfmpegEntry, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new(entry.to_string())
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to spawn ffmpeg ({}): {}", entry.to_string(), e))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "ffmpeg {} exited with status {}",
+            args.join(" "),
+            output.status
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// 解析 `ffmpeg -version`：
+/// ```text
+/// ffmpeg version 7.0.2 Copyright (c) 2000-2024 the FFmpeg developers
+/// configuration: --prefix=/usr --enable-gpl --enable-libx264 ...
+/// ```
+pub fn parse_version(text: &str) -> (String, Vec<String>) {
+    let version = text
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("ffmpeg version "))
+        .and_then(|rest| rest.split_whitespace().next())
+        .unwrap_or_default()
+        .to_string();
+
+    let license_flags = text
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("configuration:"))
+        .map(|config| {
+            ["gpl", "version3", "nonfree"]
+                .into_iter()
+                .filter(|flag| {
+                    config
+                        .split_whitespace()
+                        .any(|opt| opt == format!("--enable-{flag}"))
+                })
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    (version, license_flags)
+}
+
+/// 解析 `ffmpeg -encoders` 的输出：
+/// ```text
+///  ------
+///  V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)
+///  A....D aac                  AAC (Advanced Audio Coding)
+/// ```
+pub fn parse_encoders(text: &str) -> Vec<EncoderInfo> {
+    text.lines()
+        .skip_while(|line| !line.trim().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| {
+            let line = line.trim();
+            let (flags, rest) = line.split_once(char::is_whitespace)?;
+            let rest = rest.trim_start();
+            let (name, description) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            let kind = match flags.chars().next()? {
+                'V' => StreamKind::Video,
+                'A' => StreamKind::Audio,
+                'S' => StreamKind::Subtitle,
+                _ => StreamKind::Unknown,
+            };
+            Some(EncoderInfo {
+                name: name.to_string(),
+                kind,
+                description: description.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// 解析 `ffmpeg -muxers` 的输出：
+/// ```text
+///  --
+///   E 3g2             3GP2 (3GPP2 file format)
+///   E matroska        Matroska
+/// ```
+pub fn parse_muxers(text: &str) -> HashSet<String> {
+    text.lines()
+        .skip_while(|line| line.trim() != "--")
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let flags = parts.next()?;
+            if !flags.contains('E') {
+                return None;
+            }
+            parts.next()
+        })
+        .flat_map(|names| names.split(','))
+        .map(str::to_string)
+        .collect()
+}
+
+/// 解析 `ffmpeg -hwaccels`：首行标题，之后每行一个名字
+pub fn parse_hwaccels(text: &str) -> Vec<String> {
+    text.lines()
+        .skip(1)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caps(muxers: &[&str], encoders: &[&str]) -> FfmpegCaps {
+        FfmpegCaps {
+            muxers: muxers.iter().map(|m| m.to_string()).collect(),
+            encoders: encoders
+                .iter()
+                .map(|name| EncoderInfo {
+                    name: name.to_string(),
+                    kind: StreamKind::Unknown,
+                    description: String::new(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rejects_formats_without_muxer() {
+        let caps = caps(&["mp4"], &[]);
+
+        assert!(caps.check_format(&MediaFormat::Video(Video::Mp4)).is_ok());
+        let err = caps
+            .check_format(&MediaFormat::Video(Video::Mkv))
+            .unwrap_err();
+        assert!(err.contains("\"matroska\""), "{err}");
+    }
+
+    #[test]
+    fn accepts_any_default_encoder_candidate() {
+        let mp3 = MediaFormat::Audio(Audio::Mp3);
+
+        assert!(caps(&["mp3"], &["libshine"]).check_format(&mp3).is_ok());
+        let err = caps(&["mp3"], &["aac"]).check_format(&mp3).unwrap_err();
+        assert!(err.contains("libmp3lame, libshine"), "{err}");
+    }
+
+    #[test]
+    fn lists_unavailable_formats() {
+        let caps = caps(&["mp4", "matroska"], &[]);
+        let unavailable: Vec<String> = caps
+            .unavailable_formats()
+            .into_iter()
+            .map(|(ext, _)| ext)
+            .collect();
+
+        assert!(!unavailable.contains(&"mp4".to_string()));
+        assert!(!unavailable.contains(&"mkv".to_string()));
+        assert!(unavailable.contains(&"mp3".to_string()));
+        assert!(unavailable.contains(&"webm".to_string()));
+    }
+
+    #[test]
+    fn parses_version_and_license_flags() {
+        let text = "ffmpeg version 7.0.2-static https://johnvansickle.com/ffmpeg/ Copyright (c) 2000-2024\n\
+                    built with gcc 8 (Debian 8.3.0-6)\n\
+                    configuration: --enable-gpl --enable-version3 --enable-libx264 --disable-debug\n";
+        let (version, flags) = parse_version(text);
+
+        assert_eq!(version, "7.0.2-static");
+        assert_eq!(flags, ["gpl", "version3"]);
+    }
+
+    #[test]
+    fn describes_license() {
+        let license = |flags: &[&str]| FfmpegCaps {
+            license_flags: flags.iter().map(|f| f.to_string()).collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(license(&[]).license(), "LGPL v2.1+");
+        assert_eq!(license(&["gpl"]).license(), "GPL v2+");
+        assert_eq!(license(&["gpl", "version3"]).license(), "GPL v3");
+        assert_eq!(
+            license(&["gpl", "nonfree"]).license(),
+            "nonfree (unredistributable)"
+        );
+    }
+
+    #[test]
+    fn parses_encoders() {
+        let text = "Encoders:\n V..... = Video\n A..... = Audio\n ------\n \
+                    V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)\n \
+                    A....D aac                  AAC (Advanced Audio Coding)\n \
+                    S..... ass                  ASS (Advanced SubStation Alpha) subtitle\n";
+        let encoders = parse_encoders(text);
+
+        assert_eq!(encoders.len(), 3);
+        assert_eq!(
+            encoders[0],
+            EncoderInfo {
+                name: "libx264".into(),
+                kind: StreamKind::Video,
+                description: "libx264 H.264 / AVC / MPEG-4 AVC (codec h264)".into(),
+            }
+        );
+        assert_eq!(encoders[1].kind, StreamKind::Audio);
+        assert_eq!(encoders[2].kind, StreamKind::Subtitle);
+    }
+
+    #[test]
+    fn parses_muxers_and_skips_demuxers() {
+        let text = "File formats:\n D. = Demuxing supported\n .E = Muxing supported\n --\n \
+                    E 3g2             3GP2 (3GPP2 file format)\n \
+                    D  aac             raw ADTS AAC (Advanced Audio Coding)\n \
+                    DE matroska,webm   Matroska / WebM\n";
+        let muxers = parse_muxers(text);
+
+        assert_eq!(
+            muxers,
+            HashSet::from(["3g2", "matroska", "webm"].map(String::from))
+        );
+    }
+
+    #[test]
+    fn parses_hwaccels() {
+        let text = "Hardware acceleration methods:\nvdpau\ncuda\n\nvaapi\n";
+
+        assert_eq!(parse_hwaccels(text), ["vdpau", "cuda", "vaapi"]);
+    }
+}
@@ -0,0 +1,531 @@
+use std::path::{Path, PathBuf};
+
+use tokio::sync::mpsc;
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use crate::models::convertible_format::FormatType;
+use crate::models::media_format::Video;
+use crate::models::media_info::MediaInfo;
+use crate::models::output_params::{Resolution, DEFAULT_SAMPLE_RATE};
+use crate::models::stream_copy::{JobKind, SourceCodecs};
+
+use super::ffmpeg_progress::ProgressTotal;
+use super::ffmpeg_wrapper::{send_invalid_config, FfmpegTask, PassPlan, ProgressMsg};
+use super::filter_chain::FilterChain;
+use super::task_control::ControlMsg;
+use super::utils::format_timestamp;
+
+/// 拼接的一个输入，`media_info` 由 ffprobe 探测后填入
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct ConcatInput {
+    pub path: PathBuf,
+    pub media_info: Option<MediaInfo>,
+}
+
+impl ConcatInput {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            media_info: None,
+        }
+    }
+}
+
+/// 拼接方式，由输入的流是否一致决定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConcatMethod {
+    /// concat 分离器：各输入的编码参数一致，直接顺序读取
+    Demuxer,
+    /// concat 滤镜：先统一分辨率、帧率和采样率再拼接
+    Filter,
+}
+
+/// 把多个输入按顺序拼成一个输出
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct ConcatTask {
+    /// 编码参数沿用单文件任务，其中的 `input` 不使用
+    pub ffmpeg: FfmpegTask,
+    pub inputs: Vec<ConcatInput>,
+}
+
+impl ConcatTask {
+    pub fn new(ffmpeg: FfmpegTask, inputs: Vec<ConcatInput>) -> Self {
+        Self { ffmpeg, inputs }
+    }
+
+    /// 各输入时长之和，有任何一个未知时为 None
+    pub fn total_duration(&self) -> Option<f64> {
+        self.inputs
+            .iter()
+            .map(|input| input.media_info.as_ref().and_then(|m| m.duration))
+            .sum()
+    }
+
+    /// 任务列表里显示的输入文件名，按拼接顺序
+    pub fn input_names(&self) -> String {
+        self.inputs
+            .iter()
+            .map(|input| input.path.file_name().unwrap_or_default().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" + ")
+    }
+
+    /// 例如 "3 inputs · 00:12:30"，时长要等全部探测完才显示
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} inputs", self.inputs.len());
+        if let Some(duration) = self.total_duration() {
+            summary.push_str(&format!(" · {}", format_timestamp(duration)));
+        }
+        summary
+    }
+
+    /// 所有输入都探测完才能决定拼接方式
+    pub fn method(&self) -> Result<ConcatMethod, String> {
+        let infos = self.media_infos()?;
+        let first = stream_signature(infos[0]);
+        if infos[1..].iter().all(|info| stream_signature(info) == first) {
+            Ok(ConcatMethod::Demuxer)
+        } else {
+            Ok(ConcatMethod::Filter)
+        }
+    }
+
+    /// 只有 concat 分离器能直接复制流，各输入编码一致，取第一个的即可
+    pub fn source_codecs(&self) -> Option<SourceCodecs> {
+        match self.method() {
+            Ok(ConcatMethod::Demuxer) => self.inputs[0]
+                .media_info
+                .as_ref()
+                .map(SourceCodecs::from_media_info),
+            _ => None,
+        }
+    }
+
+    pub fn job_kind(&self) -> JobKind {
+        self.ffmpeg.clone().source_codecs(self.source_codecs()).job_kind()
+    }
+
+    /// concat 分离器用的列表文件，和两遍编码日志放在同一目录
+    pub fn list_path(output: &Path) -> PathBuf {
+        let prefix = FfmpegTask::passlog_prefix(output);
+        let name = prefix.file_name().unwrap_or_default().to_string_lossy();
+        // 不以日志前缀开头，免得被两遍编码的清理一起删掉
+        prefix.with_file_name(format!("concat-{name}.txt"))
+    }
+
+    /// 列表文件内容，路径里的单引号按 ffconcat 的规则转义
+    pub fn list_content(&self) -> String {
+        self.inputs
+            .iter()
+            .map(|input| {
+                let path = input.path.to_string_lossy().replace('\'', r"'\''");
+                format!("file '{path}'\n")
+            })
+            .collect()
+    }
+
+    pub fn build(&self) -> Result<(PathBuf, Vec<Vec<String>>), String> {
+        if !self.ffmpeg.trim.is_empty() {
+            return Err("Trimming isn't supported when joining files".into());
+        }
+        let output = self.ffmpeg.output.clone().ok_or("Missing output path")?;
+        let ffmpeg = self.joined_ffmpeg();
+
+        match self.method()? {
+            ConcatMethod::Demuxer => {
+                ffmpeg.build_with_inputs(Self::demuxer_inputs(&Self::list_path(&output)))
+            }
+            ConcatMethod::Filter => {
+                if ffmpeg.two_pass {
+                    return Err(
+                        "Two-pass encoding isn't supported when the inputs need normalizing"
+                            .into(),
+                    );
+                }
+                // 音频从滤镜图输出，不能再加 -af
+                if ffmpeg.normalization.is_some() {
+                    return Err(
+                        "Audio normalization isn't supported when the inputs need normalizing"
+                            .into(),
+                    );
+                }
+                let inputs = self.filter_inputs()?;
+                // 分辨率和帧率已经在滤镜里统一，不再生成 -vf/-r；输出流由滤镜决定
+                ffmpeg
+                    .resolution(None)
+                    .frame_rate(None)
+                    .stream_map(None)
+                    .build_with_inputs(inputs)
+            }
+        }
+    }
+
+    /// 编码参数加上所有输入合起来的时长和编码
+    fn joined_ffmpeg(&self) -> FfmpegTask {
+        self.ffmpeg
+            .clone()
+            .input_duration(self.total_duration())
+            .source_codecs(self.source_codecs())
+            .source_sample_rate(self.inputs.first().and_then(|input| {
+                input.media_info.as_ref().and_then(MediaInfo::sample_rate)
+            }))
+            // 外挂字幕只对单个输入有意义
+            .subtitle_file(None)
+    }
+
+    /// concat 分离器读列表文件的输入参数
+    fn demuxer_inputs(list: &Path) -> Vec<String> {
+        vec![
+            "-f".into(),
+            "concat".into(),
+            "-safe".into(),
+            "0".into(),
+            "-i".into(),
+            list.to_string_lossy().into_owned(),
+        ]
+    }
+
+    /// concat 滤镜的 `-i` 和 `-filter_complex` 参数
+    fn filter_inputs(&self) -> Result<Vec<String>, String> {
+        let infos = self.media_infos()?;
+        let format_type = self.ffmpeg.output_format.get_format_type();
+        if matches!(format_type, FormatType::AudioVisual(_)) {
+            return Err("Joined files can't be rendered as audio visuals".into());
+        }
+        // GIF 的调色板也要用 -filter_complex，两张滤镜图合不到一起
+        if matches!(format_type, FormatType::Video(Video::Gif)) {
+            return Err("Joined files that need normalizing can't be made into a GIF".into());
+        }
+
+        // 有任何一个输入缺少某类流时，输出就不含这类流
+        let with_video = !matches!(format_type, FormatType::Audio(_))
+            && infos.iter().all(|info| info.has_video());
+        let with_audio = infos.iter().all(|info| info.has_audio());
+        if !with_video && !with_audio {
+            return Err("The inputs have no video or audio in common".into());
+        }
+
+        let mut video_chain = FilterChain::new();
+        if with_video {
+            let (width, height) = self.target_size(infos[0])?;
+            video_chain
+                .push(format!(
+                    "scale={width}:{height}:force_original_aspect_ratio=decrease"
+                ))
+                .push(format!("pad={width}:{height}:(ow-iw)/2:(oh-ih)/2"))
+                .push("setsar=1");
+            let fps = self
+                .ffmpeg
+                .frame_rate
+                .map(|fps| fps as f64)
+                .or_else(|| infos[0].video_streams().next().and_then(|v| v.frame_rate));
+            if let Some(fps) = fps {
+                video_chain.push(format!("fps={fps}"));
+            }
+        }
+
+        let mut audio_chain = FilterChain::new();
+        if with_audio {
+            let sample_rate = self
+                .ffmpeg
+                .sample_rate
+                .or_else(|| infos[0].sample_rate())
+                .unwrap_or(DEFAULT_SAMPLE_RATE);
+            audio_chain
+                .push(format!("aresample={sample_rate}"))
+                .push("aformat=channel_layouts=stereo");
+        }
+
+        let mut args = vec![];
+        let mut graph = vec![];
+        let mut concat_inputs = String::new();
+        for (i, input) in self.inputs.iter().enumerate() {
+            args.extend(["-i".into(), input.path.to_string_lossy().into_owned()]);
+            if let Some(chain) = video_chain.join() {
+                graph.push(format!("[{i}:v:0]{chain}[v{i}]"));
+                concat_inputs.push_str(&format!("[v{i}]"));
+            }
+            if let Some(chain) = audio_chain.join() {
+                graph.push(format!("[{i}:a:0]{chain}[a{i}]"));
+                concat_inputs.push_str(&format!("[a{i}]"));
+            }
+        }
+
+        let mut outputs = String::new();
+        let mut maps = vec![];
+        if with_video {
+            outputs.push_str("[v]");
+            maps.extend(["-map".into(), "[v]".into()]);
+        }
+        if with_audio {
+            outputs.push_str("[a]");
+            maps.extend(["-map".into(), "[a]".into()]);
+        }
+        graph.push(format!(
+            "{concat_inputs}concat=n={}:v={}:a={}{outputs}",
+            self.inputs.len(),
+            with_video as u8,
+            with_audio as u8
+        ));
+
+        args.extend(["-filter_complex".into(), graph.join(";")]);
+        args.extend(maps);
+        Ok(args)
+    }
+
+    /// 统一后的画面大小：用户指定的分辨率，否则取第一个输入的
+    fn target_size(&self, first: &MediaInfo) -> Result<(u32, u32), String> {
+        let source = first.resolution();
+        let (width, height) = match (self.ffmpeg.resolution, source) {
+            (Some(Resolution::Exact(w, h)), _) => (w, h),
+            (Some(Resolution::Height(h)), Some((sw, sh))) if sh > 0 => {
+                ((sw as f64 * h as f64 / sh as f64).round() as u32, h)
+            }
+            (_, Some(size)) => size,
+            (_, None) => return Err("The first input's resolution is unknown".into()),
+        };
+        // 大多数编码器要求偶数尺寸
+        Ok((width & !1, height & !1))
+    }
+
+    fn media_infos(&self) -> Result<Vec<&MediaInfo>, String> {
+        if self.inputs.len() < 2 {
+            return Err("Joining needs at least two inputs".into());
+        }
+        self.inputs
+            .iter()
+            .map(|input| {
+                input.media_info.as_ref().ok_or_else(|| {
+                    format!("{} hasn't been probed yet", input.path.display())
+                })
+            })
+            .collect()
+    }
+
+    pub async fn run_with_progress(
+        &self,
+        task_id: String,
+        tx: mpsc::UnboundedSender<ProgressMsg>,
+        mut control: mpsc::UnboundedReceiver<ControlMsg>,
+    ) {
+        let (output, passes) = match self.build() {
+            Ok(built) => built,
+            Err(e) => return send_invalid_config(task_id, &tx, e),
+        };
+
+        let list = Self::list_path(&output);
+        let uses_list = matches!(self.method(), Ok(ConcatMethod::Demuxer));
+        if uses_list {
+            let written = async {
+                if let Some(dir) = list.parent() {
+                    tokio::fs::create_dir_all(dir).await?;
+                }
+                tokio::fs::write(&list, self.list_content()).await
+            }
+            .await;
+            if let Err(e) = written {
+                let _ = tx.send(ProgressMsg::Error {
+                    task_id,
+                    error: format!("Failed to write concat list: {e}"),
+                });
+                return;
+            }
+        }
+
+        let total = self.total_duration().map(ProgressTotal::Duration);
+        let ffmpeg = self.joined_ffmpeg();
+        if ffmpeg.normalizes_audio() {
+            // 只有分离器能标准化，测量时同样读列表文件
+            let inputs = Self::demuxer_inputs(&list);
+            let pass_count = passes.len() as u32;
+            let measured = ffmpeg
+                .measure_loudness(&task_id, inputs, pass_count, &tx, &mut control)
+                .await;
+            let built = measured.map(|measurement| {
+                let mut task = self.clone();
+                task.ffmpeg.loudness_measurement = Some(measurement);
+                task.build()
+            });
+            match built {
+                Some(Ok(built)) => {
+                    let plan = PassPlan::new(built, total).after_measuring();
+                    self.ffmpeg.run_passes(task_id, plan, tx, control).await;
+                }
+                Some(Err(e)) => send_invalid_config(task_id, &tx, e),
+                None => {}
+            }
+        } else {
+            let plan = PassPlan::new((output, passes), total);
+            self.ffmpeg.run_passes(task_id, plan, tx, control).await;
+        }
+
+        if uses_list && let Err(e) = tokio::fs::remove_file(&list).await {
+            log::warn!("⚠ Failed to remove concat list {}: {e}", list.display());
+        }
+    }
+}
+
+/// 用于判断能否直接用 concat 分离器：各路流的编码、尺寸、帧率、采样率和声道数
+fn stream_signature(info: &MediaInfo) -> Vec<String> {
+    let video = info.video_streams().map(|v| {
+        format!(
+            "v:{}:{}x{}:{:.2}",
+            v.codec.as_deref().unwrap_or_default(),
+            v.width.unwrap_or_default(),
+            v.height.unwrap_or_default(),
+            v.frame_rate.unwrap_or_default()
+        )
+    });
+    let audio = info.audio_streams().map(|a| {
+        format!(
+            "a:{}:{}:{}",
+            a.codec.as_deref().unwrap_or_default(),
+            a.sample_rate.unwrap_or_default(),
+            a.channels.unwrap_or_default()
+        )
+    });
+    video.chain(audio).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::models::media_format::MediaFormat;
+    use crate::models::media_info::{StreamInfo, StreamKind};
+    use crate::models::trim::Trim;
+    use crate::utils::ffmpeg_wrapper::FfmpegEntry;
+
+    /// h264 + aac 的视频
+    fn probed(path: &str, width: u32, duration: f64) -> ConcatInput {
+        let video = StreamInfo {
+            kind: StreamKind::Video,
+            codec: Some("h264".into()),
+            width: Some(width),
+            height: Some(width * 9 / 16),
+            frame_rate: Some(30.0),
+            ..Default::default()
+        };
+        let audio = StreamInfo {
+            index: 1,
+            kind: StreamKind::Audio,
+            codec: Some("aac".into()),
+            sample_rate: Some(44100),
+            channels: Some(2),
+            ..Default::default()
+        };
+        ConcatInput {
+            path: path.into(),
+            media_info: Some(MediaInfo {
+                duration: Some(duration),
+                streams: vec![video, audio],
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn concat(inputs: Vec<ConcatInput>) -> ConcatTask {
+        let ffmpeg = FfmpegTask::new(FfmpegEntry::Env, Arc::new(MediaFormat::Video(Video::Mp4)))
+            .output("joined.mp4");
+        ConcatTask::new(ffmpeg, inputs)
+    }
+
+    #[test]
+    fn escapes_quotes_in_the_list_file() {
+        let task = concat(vec![
+            ConcatInput::new("/v/a.mp4"),
+            ConcatInput::new("/v/it's.mp4"),
+        ]);
+
+        assert_eq!(
+            task.list_content(),
+            "file '/v/a.mp4'\nfile '/v/it'\\''s.mp4'\n"
+        );
+    }
+
+    #[test]
+    fn sums_durations_once_all_inputs_are_probed() {
+        let mut task = concat(vec![probed("a.mp4", 1920, 60.0), ConcatInput::new("b.mp4")]);
+        assert_eq!(task.total_duration(), None);
+        assert_eq!(task.summary(), "2 inputs");
+        assert!(task.method().is_err());
+
+        task.inputs[1] = probed("b.mp4", 1920, 30.0);
+        assert_eq!(task.total_duration(), Some(90.0));
+        assert_eq!(task.input_names(), "a.mp4 + b.mp4");
+    }
+
+    #[test]
+    fn uses_the_demuxer_for_matching_inputs() {
+        let task = concat(vec![
+            probed("a.mp4", 1920, 60.0),
+            probed("b.mp4", 1920, 30.0),
+        ]);
+        assert_eq!(task.method(), Ok(ConcatMethod::Demuxer));
+
+        let list = ConcatTask::list_path(Path::new("joined.mp4"));
+        let (_, passes) = task.build().unwrap();
+        let args = passes[0].join(" ");
+        assert!(
+            args.starts_with(&format!("-y -f concat -safe 0 -i {}", list.display())),
+            "{args}"
+        );
+        assert!(
+            args.contains("-c:v copy") && args.contains("-c:a copy"),
+            "{args}"
+        );
+    }
+
+    #[test]
+    fn normalizes_mismatched_inputs_with_the_filter() {
+        let task = concat(vec![
+            probed("a.mp4", 1920, 60.0),
+            probed("b.mp4", 1280, 30.0),
+        ]);
+        assert_eq!(task.method(), Ok(ConcatMethod::Filter));
+
+        let (_, passes) = task.build().unwrap();
+        let args = passes[0].join(" ");
+        assert!(
+            args.starts_with("-y -i a.mp4 -i b.mp4 -filter_complex "),
+            "{args}"
+        );
+        assert!(args.contains(
+            "[1:v:0]scale=1920:1080:force_original_aspect_ratio=decrease,pad=1920:1080:(ow-iw)/2:(oh-ih)/2,setsar=1,fps=30[v1]"
+        ), "{args}");
+        assert!(
+            args.contains("[1:a:0]aresample=44100,aformat=channel_layouts=stereo[a1]"),
+            "{args}"
+        );
+        assert!(
+            args.contains("[v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a] -map [v] -map [a]"),
+            "{args}"
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_joins() {
+        assert!(concat(vec![probed("a.mp4", 1920, 60.0)]).build().is_err());
+
+        let mut trimmed = concat(vec![
+            probed("a.mp4", 1920, 60.0),
+            probed("b.mp4", 1920, 30.0),
+        ]);
+        trimmed.ffmpeg = trimmed.ffmpeg.trim(Trim {
+            start: Some(1.0),
+            ..Default::default()
+        });
+        assert!(trimmed.build().is_err());
+
+        let mut two_pass = concat(vec![
+            probed("a.mp4", 1920, 60.0),
+            probed("b.mp4", 1280, 30.0),
+        ]);
+        two_pass.ffmpeg = two_pass.ffmpeg.two_pass(true);
+        assert!(two_pass.build().is_err());
+    }
+}
@@ -0,0 +1,230 @@
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+/// ffmpeg `-progress` 输出的一个完整块
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct FfmpegProgress {
+    pub frame: Option<u64>,
+    pub fps: Option<f32>,
+    /// kbit/s
+    pub bitrate: Option<f32>,
+    /// bytes
+    pub total_size: Option<u64>,
+    pub out_time_us: Option<i64>,
+    pub speed: Option<f32>,
+    /// 输入总时长（微秒），未知时为 None
+    pub duration_us: Option<i64>,
+    /// 预计输出的总帧数，图片序列没有时长可用，按帧数算进度
+    pub total_frames: Option<u64>,
+    pub is_end: bool,
+    /// 两遍编码时的当前遍（从 1 开始）和总遍数，单遍编码时为 0
+    pub pass: u32,
+    pub pass_count: u32,
+}
+
+impl FfmpegProgress {
+    pub fn out_time_secs(&self) -> Option<f64> {
+        self.out_time_us.map(|us| us as f64 / 1_000_000.0)
+    }
+
+    /// 0.0 ~ 1.0，时长和总帧数都未知时返回 None。多遍编码时按遍数均分，
+    /// 例如两遍编码的第一遍占 0 ~ 0.5
+    pub fn ratio(&self) -> Option<f32> {
+        let pass_ratio = if self.is_end {
+            1.0
+        } else if let Some(total) = self.total_frames.filter(|n| *n > 0) {
+            (self.frame? as f64 / total as f64).min(1.0) as f32
+        } else {
+            let total = self.duration_us.filter(|d| *d > 0)?;
+            let current = self.out_time_us?.max(0);
+            (current as f64 / total as f64).min(1.0) as f32
+        };
+
+        if self.pass_count > 1 {
+            let done = self.pass.saturating_sub(1) as f32;
+            Some((done + pass_ratio) / self.pass_count as f32)
+        } else {
+            Some(pass_ratio)
+        }
+    }
+
+    /// 如 "frame 120/300"，只在按帧数算进度时有
+    pub fn frame_label(&self) -> Option<String> {
+        let total = self.total_frames?;
+        Some(format!("frame {}/{total}", self.frame.unwrap_or_default()))
+    }
+
+    /// 如 "pass 1/2"，单遍编码时为 None
+    pub fn pass_label(&self) -> Option<String> {
+        (self.pass_count > 1).then(|| format!("pass {}/{}", self.pass, self.pass_count))
+    }
+}
+
+/// 计算进度用的总量
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressTotal {
+    /// 输出时长（秒）
+    Duration(f64),
+    /// 输出帧数
+    Frames(u64),
+}
+
+/// 逐行解析 `-progress pipe:1` 的 key=value 输出，
+/// 每遇到 `progress=continue|end` 产出一个完整的 [`FfmpegProgress`]
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    current: FfmpegProgress,
+}
+
+impl ProgressParser {
+    pub fn new(total: Option<ProgressTotal>) -> Self {
+        let (duration_us, total_frames) = match total {
+            Some(ProgressTotal::Duration(secs)) => (Some((secs * 1_000_000.0) as i64), None),
+            Some(ProgressTotal::Frames(frames)) => (None, Some(frames)),
+            None => (None, None),
+        };
+        Self {
+            current: FfmpegProgress {
+                duration_us,
+                total_frames,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn set_duration_us(&mut self, duration_us: Option<i64>) {
+        self.current.duration_us = duration_us;
+    }
+
+    pub fn feed_line(&mut self, line: &str) -> Option<FfmpegProgress> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+
+        match key.trim() {
+            "frame" => self.current.frame = value.parse().ok(),
+            "fps" => self.current.fps = value.parse().ok(),
+            "bitrate" => {
+                self.current.bitrate = value.trim_end_matches("kbits/s").parse().ok();
+            }
+            "total_size" => self.current.total_size = value.parse().ok(),
+            // 旧版本 ffmpeg 的 out_time_ms 实际上也是微秒
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse() {
+                    self.current.out_time_us = Some(us);
+                }
+            }
+            "speed" => self.current.speed = value.trim_end_matches('x').parse().ok(),
+            "progress" => {
+                self.current.is_end = value == "end";
+                return Some(self.current.clone());
+            }
+            _ => {}
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(parser: &mut ProgressParser, block: &str) -> Option<FfmpegProgress> {
+        block
+            .lines()
+            .filter_map(|line| parser.feed_line(line))
+            .last()
+    }
+
+    #[test]
+    fn parses_a_progress_block() {
+        let mut parser = ProgressParser::new(Some(ProgressTotal::Duration(10.0)));
+        let progress = feed(
+            &mut parser,
+            "frame=120\nfps=29.97\nbitrate= 1024.5kbits/s\ntotal_size=524288\n\
+             out_time_us=2500000\nspeed=1.5x\nprogress=continue",
+        )
+        .unwrap();
+
+        assert_eq!(progress.frame, Some(120));
+        assert_eq!(progress.fps, Some(29.97));
+        assert_eq!(progress.bitrate, Some(1024.5));
+        assert_eq!(progress.total_size, Some(524288));
+        assert_eq!(progress.out_time_secs(), Some(2.5));
+        assert_eq!(progress.speed, Some(1.5));
+        assert!(!progress.is_end);
+        assert_eq!(progress.ratio(), Some(0.25));
+    }
+
+    #[test]
+    fn emits_only_on_progress_lines() {
+        let mut parser = ProgressParser::new(None);
+        assert_eq!(parser.feed_line("frame=1"), None);
+        assert_eq!(parser.feed_line("not a key value line"), None);
+        assert!(parser.feed_line("progress=continue").is_some());
+    }
+
+    #[test]
+    fn keeps_values_across_blocks_and_marks_the_end() {
+        let mut parser = ProgressParser::new(Some(ProgressTotal::Duration(4.0)));
+        feed(&mut parser, "out_time_us=1000000\nprogress=continue");
+        // N/A 不覆盖上一块的时间
+        let progress = feed(&mut parser, "out_time_us=N/A\nbitrate=N/A\nprogress=end").unwrap();
+
+        assert_eq!(progress.out_time_us, Some(1_000_000));
+        assert_eq!(progress.bitrate, None);
+        assert!(progress.is_end);
+        assert_eq!(progress.ratio(), Some(1.0));
+    }
+
+    #[test]
+    fn reads_out_time_ms_as_microseconds() {
+        let mut parser = ProgressParser::new(None);
+        let progress = feed(&mut parser, "out_time_ms=3000000\nprogress=continue").unwrap();
+        assert_eq!(progress.out_time_secs(), Some(3.0));
+    }
+
+    #[test]
+    fn ratio_needs_a_duration_and_is_capped() {
+        let mut parser = ProgressParser::new(None);
+        let progress = feed(&mut parser, "out_time_us=1000000\nprogress=continue").unwrap();
+        assert_eq!(progress.ratio(), None);
+
+        parser.set_duration_us(Some(500_000));
+        let progress = feed(&mut parser, "progress=continue").unwrap();
+        assert_eq!(progress.ratio(), Some(1.0));
+    }
+
+    #[test]
+    fn splits_ratio_between_passes() {
+        let progress = |pass, is_end| FfmpegProgress {
+            out_time_us: Some(500_000),
+            duration_us: Some(1_000_000),
+            is_end,
+            pass,
+            pass_count: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(progress(1, false).ratio(), Some(0.25));
+        assert_eq!(progress(1, true).ratio(), Some(0.5));
+        assert_eq!(progress(2, false).ratio(), Some(0.75));
+        assert_eq!(progress(2, false).pass_label().as_deref(), Some("pass 2/2"));
+        assert_eq!(FfmpegProgress::default().pass_label(), None);
+    }
+
+    #[test]
+    fn counts_frames_when_a_frame_total_is_known() {
+        let progress = FfmpegProgress {
+            frame: Some(30),
+            out_time_us: Some(0),
+            duration_us: Some(1_000_000),
+            total_frames: Some(120),
+            ..Default::default()
+        };
+
+        assert_eq!(progress.ratio(), Some(0.25));
+        assert_eq!(progress.frame_label().as_deref(), Some("frame 30/120"));
+    }
+}
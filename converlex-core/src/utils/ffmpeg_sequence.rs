@@ -0,0 +1,478 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::mpsc;
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use crate::models::codec::VideoCodec;
+use crate::models::convertible_format::FormatType;
+use crate::models::media_format::Image;
+use crate::models::output_params;
+
+use super::ffmpeg_progress::ProgressTotal;
+use super::ffmpeg_wrapper::{send_invalid_config, FfmpegTask, PassPlan, ProgressMsg};
+use super::task_control::ControlMsg;
+use super::utils::format_timestamp;
+
+/// 导出帧的文件名，编号从 1 开始
+pub const FRAME_PATTERN: &str = "frame_%05d";
+
+/// 图片序列没有指定帧率时用的帧率
+pub const DEFAULT_SEQUENCE_FPS: u32 = 25;
+
+/// 导出帧的取样方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum FrameSampling {
+    /// 每秒取几帧，可以小于 1，如 0.5 是每两秒一帧
+    Fps(f64),
+    /// 每 N 帧取一帧
+    EveryNth(u32),
+}
+
+impl Default for FrameSampling {
+    fn default() -> Self {
+        FrameSampling::Fps(1.0)
+    }
+}
+
+impl Display for FrameSampling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameSampling::Fps(fps) => write!(f, "{fps} fps"),
+            FrameSampling::EveryNth(n) => write!(f, "every {n} frames"),
+        }
+    }
+}
+
+impl FrameSampling {
+    /// 放在缩放之前的取样滤镜
+    pub fn filter(&self) -> Result<String, String> {
+        match *self {
+            FrameSampling::Fps(fps) if fps > 0.0 && fps <= output_params::MAX_FRAME_RATE as f64 => {
+                Ok(format!("fps={fps}"))
+            }
+            FrameSampling::Fps(_) => Err(format!(
+                "Frame export rate must be within 0-{} fps",
+                output_params::MAX_FRAME_RATE
+            )),
+            FrameSampling::EveryNth(n) if n >= 1 => Ok(format!("select='not(mod(n\\,{n}))'")),
+            FrameSampling::EveryNth(_) => Err("Frame interval must be at least 1".into()),
+        }
+    }
+
+    /// select 丢掉的帧不能再被补回来，输出要按可变帧率写
+    pub fn output_args(&self) -> Vec<String> {
+        match self {
+            FrameSampling::Fps(_) => vec![],
+            FrameSampling::EveryNth(_) => vec!["-fps_mode".into(), "vfr".into()],
+        }
+    }
+}
+
+/// 把视频导出成编号图片，`ffmpeg.output` 是输出文件夹
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct FrameExportTask {
+    pub ffmpeg: FfmpegTask,
+    pub sampling: FrameSampling,
+    /// 输入的帧率，来自 ffprobe，用于估算按帧间隔导出的帧数
+    pub source_frame_rate: Option<f64>,
+}
+
+impl FrameExportTask {
+    pub fn new(ffmpeg: FfmpegTask, sampling: FrameSampling) -> Self {
+        Self {
+            ffmpeg,
+            sampling,
+            source_frame_rate: None,
+        }
+    }
+
+    /// 输出文件夹里的文件名模式，如 `frame_%05d.png`
+    pub fn pattern_path(&self, dir: &Path) -> PathBuf {
+        dir.join(format!(
+            "{FRAME_PATTERN}.{}",
+            self.ffmpeg.output_format.get_ext()
+        ))
+    }
+
+    /// 预计导出的帧数，时长或帧率未知时为 None
+    pub fn expected_frames(&self) -> Option<u64> {
+        let secs = self.ffmpeg.output_duration()?;
+        let frames = match self.sampling {
+            FrameSampling::Fps(fps) => secs * fps,
+            FrameSampling::EveryNth(n) => secs * self.source_frame_rate? / n.max(1) as f64,
+        };
+        Some(frames.ceil().max(1.0) as u64)
+    }
+
+    pub fn build(&self) -> Result<(PathBuf, Vec<Vec<String>>), String> {
+        if !matches!(
+            self.ffmpeg.output_format.get_format_type(),
+            FormatType::Image(_)
+        ) {
+            return Err("Frames can only be exported as images".into());
+        }
+        let dir = self.ffmpeg.output.clone().ok_or("Missing output folder")?;
+        self.ffmpeg
+            .clone()
+            .output(self.pattern_path(&dir))
+            .frame_sampling(Some(self.sampling))
+            .subtitle_file(None)
+            .build()
+    }
+
+    pub async fn run_with_progress(
+        &self,
+        task_id: String,
+        tx: mpsc::UnboundedSender<ProgressMsg>,
+        control: mpsc::UnboundedReceiver<ControlMsg>,
+    ) {
+        let built = self.build().and_then(|built| {
+            let dir = self
+                .ffmpeg
+                .output
+                .as_deref()
+                .ok_or("Missing output folder")?;
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+            Ok(built)
+        });
+        let built = match built {
+            Ok(built) => built,
+            Err(e) => return send_invalid_config(task_id, &tx, e),
+        };
+
+        let total = self.expected_frames().map(ProgressTotal::Frames);
+        let plan = PassPlan::new(built, total);
+        self.ffmpeg.run_passes(task_id, plan, tx, control).await;
+    }
+}
+
+/// 文件夹里编号连续的一组图片
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct ImageSequence {
+    pub dir: PathBuf,
+    /// ffmpeg 的文件名模式，如 `render_%04d.png`
+    pub pattern: String,
+    pub start_number: u64,
+    pub frame_count: u64,
+}
+
+impl ImageSequence {
+    /// 在文件夹里找编号的图片，文件名除编号外相同的归为一组，取文件最多的一组。
+    /// 编号必须连续，否则 ffmpeg 读到缺口就会停下
+    pub fn detect(dir: &Path) -> Result<Self, String> {
+        let entries =
+            std::fs::read_dir(dir).map_err(|e| format!("Can't read {}: {e}", dir.display()))?;
+
+        let mut groups: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let is_image = Path::new(&name)
+                .extension()
+                .and_then(|ext| Image::from_extension(&ext.to_string_lossy().to_lowercase()))
+                .is_some();
+            if !is_image {
+                continue;
+            }
+            if let Some((prefix, digits, suffix)) = split_frame_number(&name) {
+                groups
+                    .entry((prefix.to_string(), suffix.to_string()))
+                    .or_default()
+                    .push(digits.to_string());
+            }
+        }
+
+        let ((prefix, suffix), digits) = groups
+            .into_iter()
+            .filter(|(_, digits)| digits.len() >= 2)
+            .max_by_key(|(_, digits)| digits.len())
+            .ok_or_else(|| format!("No numbered image sequence found in {}", dir.display()))?;
+
+        // 位数都一样时按补零处理，否则编号不能带前导零
+        let width = digits[0].len();
+        let number = if digits.iter().all(|d| d.len() == width) {
+            format!("%0{width}d")
+        } else if digits.iter().any(|d| d.len() > 1 && d.starts_with('0')) {
+            return Err(format!(
+                "{prefix}*{suffix} mixes padded and unpadded numbers"
+            ));
+        } else {
+            "%d".into()
+        };
+        // 文件名里的 % 要写成 %%
+        let pattern = format!(
+            "{}{number}{}",
+            prefix.replace('%', "%%"),
+            suffix.replace('%', "%%")
+        );
+
+        let mut numbers = digits
+            .iter()
+            .map(|d| d.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Bad frame number in {pattern}: {e}"))?;
+        numbers.sort_unstable();
+        let start_number = numbers[0];
+        if let Some(missing) = (start_number..)
+            .zip(&numbers)
+            .find(|(want, got)| want != *got)
+        {
+            return Err(format!("Frame {} is missing from {pattern}", missing.0));
+        }
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            pattern,
+            start_number,
+            frame_count: numbers.len() as u64,
+        })
+    }
+
+    pub fn input_args(&self, fps: u32) -> Vec<String> {
+        vec![
+            "-framerate".into(),
+            fps.to_string(),
+            "-start_number".into(),
+            self.start_number.to_string(),
+            "-i".into(),
+            self.dir.join(&self.pattern).to_string_lossy().into_owned(),
+        ]
+    }
+}
+
+/// 拆出文件名主干里最后一段数字：(前缀, 数字, 后缀含扩展名)
+fn split_frame_number(name: &str) -> Option<(&str, &str, &str)> {
+    let (stem, _) = name.rsplit_once('.')?;
+    let end = stem.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let start = end
+        - stem[..end]
+            .chars()
+            .rev()
+            .take_while(char::is_ascii_digit)
+            .count();
+    Some((&name[..start], &name[start..end], &name[end..]))
+}
+
+/// 把编号图片序列编码成视频，`ffmpeg.frame_rate` 是序列的帧率
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct ImageSequenceTask {
+    pub ffmpeg: FfmpegTask,
+    pub sequence: ImageSequence,
+}
+
+impl ImageSequenceTask {
+    pub fn new(ffmpeg: FfmpegTask, sequence: ImageSequence) -> Self {
+        Self { ffmpeg, sequence }
+    }
+
+    pub fn frame_rate(&self) -> u32 {
+        self.ffmpeg.frame_rate.unwrap_or(DEFAULT_SEQUENCE_FPS)
+    }
+
+    /// 按所选帧率算出的视频时长（秒）
+    pub fn duration(&self) -> f64 {
+        self.sequence.frame_count as f64 / self.frame_rate().max(1) as f64
+    }
+
+    /// 例如 "240 frames · 00:00:10"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} frames · {}",
+            self.sequence.frame_count,
+            format_timestamp(self.duration())
+        )
+    }
+
+    pub fn build(&self) -> Result<(PathBuf, Vec<Vec<String>>), String> {
+        if !self.ffmpeg.trim.is_empty() {
+            return Err("Trimming isn't supported for image sequences".into());
+        }
+        let format_type = self.ffmpeg.output_format.get_format_type();
+        if !matches!(format_type, FormatType::Video(_)) {
+            return Err("Image sequences can only be encoded as video".into());
+        }
+        let fps = self.frame_rate();
+        output_params::validate_frame_rate(fps)?;
+
+        // 帧率已经在输入端给出，不再生成 -r；序列没有可复制的流，也没有字幕可封装
+        let mut ffmpeg = self
+            .ffmpeg
+            .clone()
+            .frame_rate(None)
+            .input_duration(Some(self.duration()))
+            .source_codecs(None)
+            .stream_map(None)
+            .subtitle_file(None);
+        // PNG 等 RGB 图片默认会编成 4:4:4，很多播放器放不了
+        let codec = format_type.effective_video_codec(ffmpeg.video_codec);
+        if matches!(codec, Some(VideoCodec::H264 | VideoCodec::Hevc)) {
+            ffmpeg = ffmpeg.extra_arg("-pix_fmt").extra_arg("yuv420p");
+        }
+        ffmpeg.build_with_inputs(self.sequence.input_args(fps))
+    }
+
+    pub async fn run_with_progress(
+        &self,
+        task_id: String,
+        tx: mpsc::UnboundedSender<ProgressMsg>,
+        control: mpsc::UnboundedReceiver<ControlMsg>,
+    ) {
+        let built = match self.build() {
+            Ok(built) => built,
+            Err(e) => return send_invalid_config(task_id, &tx, e),
+        };
+
+        // 图片序列的 Duration: 行不可靠，按帧数算进度
+        let total = Some(ProgressTotal::Frames(self.sequence.frame_count));
+        let plan = PassPlan::new(built, total);
+        self.ffmpeg.run_passes(task_id, plan, tx, control).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::models::media_format::{MediaFormat, Video};
+    use crate::utils::ffmpeg_wrapper::FfmpegEntry;
+
+    /// 在独立的临时目录中创建空文件
+    fn dir_with(names: &[&str]) -> PathBuf {
+        static NEXT: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+        let n = NEXT.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let dir =
+            std::env::temp_dir().join(format!("converlex-sequence-{}-{n}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in names {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        dir
+    }
+
+    fn ffmpeg(format: MediaFormat, output: &str) -> FfmpegTask {
+        FfmpegTask::new(FfmpegEntry::Env, Arc::new(format))
+            .input("in.mp4")
+            .output(output)
+    }
+
+    #[test]
+    fn splits_the_last_number_in_the_stem() {
+        assert_eq!(
+            split_frame_number("shot2_0042.png"),
+            Some(("shot2_", "0042", ".png"))
+        );
+        assert_eq!(split_frame_number("7.jpg"), Some(("", "7", ".jpg")));
+        assert_eq!(split_frame_number("cover.png"), None);
+        assert_eq!(split_frame_number("noext"), None);
+    }
+
+    #[test]
+    fn detects_padded_sequences() {
+        let dir = dir_with(&[
+            "render_0009.png",
+            "render_0010.png",
+            "render_0011.png",
+            "notes.txt",
+            "logo.png",
+        ]);
+        let sequence = ImageSequence::detect(&dir).unwrap();
+
+        assert_eq!(sequence.pattern, "render_%04d.png");
+        assert_eq!((sequence.start_number, sequence.frame_count), (9, 3));
+        assert_eq!(
+            sequence.input_args(30),
+            [
+                "-framerate",
+                "30",
+                "-start_number",
+                "9",
+                "-i",
+                &*dir.join("render_%04d.png").to_string_lossy()
+            ]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn detects_unpadded_sequences_and_escapes_percent() {
+        let dir = dir_with(&["100%_8.png", "100%_9.png", "100%_10.png"]);
+        let sequence = ImageSequence::detect(&dir).unwrap();
+
+        assert_eq!(sequence.pattern, "100%%_%d.png");
+        assert_eq!(sequence.start_number, 8);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_gaps_and_mixed_padding() {
+        let gap = dir_with(&["f1.png", "f2.png", "f4.png"]);
+        assert!(ImageSequence::detect(&gap).unwrap_err().contains("Frame 3"));
+
+        let mixed = dir_with(&["f08.png", "f09.png", "f100.png"]);
+        assert!(ImageSequence::detect(&mixed).unwrap_err().contains("mixes"));
+
+        let single = dir_with(&["f1.png"]);
+        assert!(ImageSequence::detect(&single).is_err());
+        for dir in [gap, mixed, single] {
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn builds_sampling_filters() {
+        assert_eq!(FrameSampling::Fps(0.5).filter().unwrap(), "fps=0.5");
+        assert_eq!(
+            FrameSampling::EveryNth(10).filter().unwrap(),
+            r"select='not(mod(n\,10))'"
+        );
+        assert_eq!(
+            FrameSampling::EveryNth(10).output_args(),
+            ["-fps_mode", "vfr"]
+        );
+        assert!(FrameSampling::Fps(0.0).filter().is_err());
+        assert!(FrameSampling::EveryNth(0).filter().is_err());
+    }
+
+    #[test]
+    fn exports_numbered_frames() {
+        let ffmpeg = ffmpeg(MediaFormat::Image(Image::Png), "frames").input_duration(Some(10.0));
+        let mut task = FrameExportTask::new(ffmpeg, FrameSampling::EveryNth(30));
+        assert_eq!(task.expected_frames(), None);
+        task.source_frame_rate = Some(30.0);
+        assert_eq!(task.expected_frames(), Some(10));
+
+        let (output, passes) = task.build().unwrap();
+        assert_eq!(output, Path::new("frames/frame_%05d.png"));
+        assert_eq!(
+            passes[0].join(" "),
+            r"-y -i in.mp4 -vf select='not(mod(n\,30))' -fps_mode vfr -f image2 frames/frame_%05d.png"
+        );
+    }
+
+    #[test]
+    fn encodes_sequences_as_yuv420p_video() {
+        let sequence = ImageSequence {
+            dir: "seq".into(),
+            pattern: "f_%03d.png".into(),
+            start_number: 1,
+            frame_count: 250,
+        };
+        let task =
+            ImageSequenceTask::new(ffmpeg(MediaFormat::Video(Video::Mp4), "out.mp4"), sequence);
+        assert_eq!(task.summary(), "250 frames · 00:00:10");
+
+        let (_, passes) = task.build().unwrap();
+        assert_eq!(
+            passes[0].join(" "),
+            "-y -framerate 25 -start_number 1 -i seq/f_%03d.png -pix_fmt yuv420p -f mp4 out.mp4"
+        );
+    }
+}
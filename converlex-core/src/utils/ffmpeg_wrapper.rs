@@ -0,0 +1,1991 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use crate::models::codec::{AudioCodec, VideoCodec};
+use crate::models::output_params::{self, Resolution, DEFAULT_SAMPLE_RATE};
+use crate::models::rate_control::{AudioRateControl, VideoRateControl};
+use crate::models::stream_copy::{JobKind, SourceCodecs};
+use crate::models::gif::GifOptions;
+use crate::models::loudness::{LoudnessMeasurement, Normalization};
+use crate::models::media_format::{Image, Subtitle, Video};
+use crate::models::media_info::{is_text_subtitle_codec, StreamKind};
+use crate::models::stream_map::{self, StreamSelection};
+use crate::models::subtitle::{SubtitleFile, SubtitleMode};
+use crate::models::trim::{SeekMode, Trim};
+use crate::models::convertible_format::{ConvertibleFormat, FormatType};
+
+use super::ffmpeg_caps::FfmpegCaps;
+use super::ffmpeg_progress::{FfmpegProgress, ProgressParser, ProgressTotal};
+use super::ffmpeg_sequence::FrameSampling;
+use super::filter_chain::FilterChain;
+use super::scheduler::Scheduler;
+use super::task_control::{self, ControlMsg, TaskControls};
+
+/// 失败时附带的 stderr 末尾行数
+const STDERR_TAIL_LINES: usize = 20;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub struct FfmpegTask {
+    pub ffmpeg_entry: FfmpegEntry,
+    pub input: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub output_format: Arc<dyn ConvertibleFormat>,
+    /// 输入时长（秒），来自 ffprobe，用于计算进度
+    pub input_duration: Option<f64>,
+    /// `None` 时由 ffmpeg 按容器选择默认编码
+    pub video_codec: Option<VideoCodec>,
+    pub audio_codec: Option<AudioCodec>,
+    /// `None` 时交给编码器的默认码率
+    pub video_rate: Option<VideoRateControl>,
+    pub audio_rate: Option<AudioRateControl>,
+    pub resolution: Option<Resolution>,
+    pub frame_rate: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub extra_args: Vec<String>,
+    /// 两遍编码，只用于固定码率和目标大小
+    pub two_pass: bool,
+    /// 只转换其中一段，默认转换整个文件
+    pub trim: Trim,
+    /// 输入流的编码，来自 ffprobe，用于判断能否直接复制
+    pub source_codecs: Option<SourceCodecs>,
+    /// 输入第一路音频的采样率，来自 ffprobe，标准化响度后按它重采样
+    pub source_sample_rate: Option<u32>,
+    /// 允许直接复制目标容器能装下的流，关闭时总是重新编码
+    pub stream_copy: bool,
+    /// 只输出第 N 路音频（从 0 开始），用于提取音轨
+    pub audio_track: Option<usize>,
+    /// 手动选择的输出流，`None` 时由 ffmpeg 按默认规则各选一路；提取音轨时不使用
+    pub stream_map: Option<Vec<StreamSelection>>,
+    /// 字幕输出时取第 N 路字幕（从 0 开始），`None` 时取第一路
+    pub subtitle_track: Option<usize>,
+    /// 封装或烧录进视频输出的外挂字幕
+    pub subtitle_file: Option<SubtitleFile>,
+    /// 有损图片格式的质量，1-100，`None` 时用编码器默认值
+    pub image_quality: Option<u32>,
+    /// 不写入输入的元数据（EXIF、标题等）
+    pub strip_metadata: bool,
+    /// 图片输出为编号序列时的取样方式，`None` 时只输出一帧
+    pub frame_sampling: Option<FrameSampling>,
+    /// GIF 输出的帧率、尺寸和调色板参数，其他格式不使用
+    pub gif: GifOptions,
+    /// 音频标准化，运行时先跑一遍测量
+    pub normalization: Option<Normalization>,
+    /// 测量那一遍的结果，运行时填入；为空时生成的参数不含标准化滤镜
+    pub loudness_measurement: Option<LoudnessMeasurement>,
+}
+
+impl PartialEq for FfmpegTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.ffmpeg_entry == other.ffmpeg_entry
+            && self.input == other.input
+            && self.output == other.output
+            && self.output_format.get_ext() == other.output_format.get_ext()
+            && self.input_duration == other.input_duration
+            && self.video_codec == other.video_codec
+            && self.audio_codec == other.audio_codec
+            && self.video_rate == other.video_rate
+            && self.audio_rate == other.audio_rate
+            && self.resolution == other.resolution
+            && self.frame_rate == other.frame_rate
+            && self.sample_rate == other.sample_rate
+            && self.extra_args == other.extra_args
+            && self.two_pass == other.two_pass
+            && self.trim == other.trim
+            && self.source_codecs == other.source_codecs
+            && self.source_sample_rate == other.source_sample_rate
+            && self.stream_copy == other.stream_copy
+            && self.audio_track == other.audio_track
+            && self.stream_map == other.stream_map
+            && self.subtitle_track == other.subtitle_track
+            && self.subtitle_file == other.subtitle_file
+            && self.image_quality == other.image_quality
+            && self.strip_metadata == other.strip_metadata
+            && self.frame_sampling == other.frame_sampling
+            && self.gif == other.gif
+            && self.normalization == other.normalization
+            && self.loudness_measurement == other.loudness_measurement
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "vizia", derive(Data))]
+pub enum FfmpegEntry {
+    Path(PathBuf),
+    Env,
+}
+
+impl ToString for FfmpegEntry {
+    fn to_string(&self) -> String {
+        match self {
+            FfmpegEntry::Path(path) => path.to_string_lossy().to_string(),
+            FfmpegEntry::Env => "ffmpeg".to_string(),
+        }
+    }
+}
+
+impl FfmpegEntry {
+    /// 与 ffmpeg 同目录的 ffprobe，找不到时退回 PATH 中的 ffprobe
+    pub fn ffprobe_path(&self) -> PathBuf {
+        match self {
+            FfmpegEntry::Path(path) => {
+                let name = match path.extension() {
+                    Some(ext) => format!("ffprobe.{}", ext.to_string_lossy()),
+                    None => "ffprobe".to_string(),
+                };
+                let sibling = path.with_file_name(name);
+                if sibling.exists() {
+                    sibling
+                } else {
+                    PathBuf::from("ffprobe")
+                }
+            }
+            FfmpegEntry::Env => PathBuf::from("ffprobe"),
+        }
+    }
+}
+
+impl Into<PathBuf> for FfmpegEntry {
+    fn into(self) -> PathBuf {
+        match self {
+            FfmpegEntry::Path(path) => path,
+            FfmpegEntry::Env => PathBuf::from("ffmpeg"),
+        }
+    }
+}
+
+impl FromStr for FfmpegEntry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("Empty string".to_string());
+        }
+
+        let path = PathBuf::from(s);
+        if path.exists() {
+            Ok(FfmpegEntry::Path(path))
+        } else {
+            Ok(FfmpegEntry::Env)
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl FfmpegTask {
+    pub fn new(ffmpeg_entry: FfmpegEntry, output_format: Arc<dyn ConvertibleFormat>) -> Self {
+        Self {
+            ffmpeg_entry,
+            input: None,
+            output: None,
+            output_format: output_format,
+            input_duration: None,
+            video_codec: None,
+            audio_codec: None,
+            video_rate: None,
+            audio_rate: None,
+            resolution: None,
+            frame_rate: None,
+            sample_rate: None,
+            extra_args: vec![],
+            two_pass: false,
+            trim: Trim::default(),
+            source_codecs: None,
+            source_sample_rate: None,
+            stream_copy: true,
+            audio_track: None,
+            stream_map: None,
+            subtitle_track: None,
+            subtitle_file: None,
+            image_quality: None,
+            strip_metadata: false,
+            frame_sampling: None,
+            gif: GifOptions::default(),
+            normalization: None,
+            loudness_measurement: None,
+        }
+    }
+
+    pub fn ffmpeg_entry(mut self, entry: FfmpegEntry) -> Self {
+        self.ffmpeg_entry = entry;
+        self
+    }
+
+    pub fn input(mut self, path: impl Into<PathBuf>) -> Self {
+        self.input = Some(path.into());
+        self
+    }
+
+    pub fn output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output = Some(path.into());
+        self
+    }
+
+    pub fn output_format(mut self, format: Arc<dyn ConvertibleFormat>) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    pub fn input_duration(mut self, secs: Option<f64>) -> Self {
+        self.input_duration = secs;
+        self
+    }
+
+    pub fn video_codec(mut self, codec: Option<VideoCodec>) -> Self {
+        self.video_codec = codec;
+        self
+    }
+
+    pub fn audio_codec(mut self, codec: Option<AudioCodec>) -> Self {
+        self.audio_codec = codec;
+        self
+    }
+
+    pub fn video_rate(mut self, rate: Option<VideoRateControl>) -> Self {
+        self.video_rate = rate;
+        self
+    }
+
+    pub fn audio_rate(mut self, rate: Option<AudioRateControl>) -> Self {
+        self.audio_rate = rate;
+        self
+    }
+
+    pub fn trim(mut self, trim: Trim) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    pub fn source_codecs(mut self, codecs: Option<SourceCodecs>) -> Self {
+        self.source_codecs = codecs;
+        self
+    }
+
+    pub fn source_sample_rate(mut self, rate: Option<u32>) -> Self {
+        self.source_sample_rate = rate;
+        self
+    }
+
+    pub fn stream_copy(mut self, enabled: bool) -> Self {
+        self.stream_copy = enabled;
+        self
+    }
+
+    pub fn audio_track(mut self, track: Option<usize>) -> Self {
+        self.audio_track = track;
+        self
+    }
+
+    pub fn stream_map(mut self, streams: Option<Vec<StreamSelection>>) -> Self {
+        self.stream_map = streams;
+        self
+    }
+
+    pub fn subtitle_track(mut self, track: Option<usize>) -> Self {
+        self.subtitle_track = track;
+        self
+    }
+
+    pub fn subtitle_file(mut self, file: Option<SubtitleFile>) -> Self {
+        self.subtitle_file = file;
+        self
+    }
+
+    pub fn image_quality(mut self, quality: Option<u32>) -> Self {
+        self.image_quality = quality;
+        self
+    }
+
+    pub fn strip_metadata(mut self, enabled: bool) -> Self {
+        self.strip_metadata = enabled;
+        self
+    }
+
+    pub fn frame_sampling(mut self, sampling: Option<FrameSampling>) -> Self {
+        self.frame_sampling = sampling;
+        self
+    }
+
+    pub fn gif(mut self, options: GifOptions) -> Self {
+        self.gif = options;
+        self
+    }
+
+    pub fn normalization(mut self, normalization: Option<Normalization>) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    pub fn loudness_measurement(mut self, measurement: Option<LoudnessMeasurement>) -> Self {
+        self.loudness_measurement = measurement;
+        self
+    }
+
+    fn embedded_subtitle(&self) -> Option<&SubtitleFile> {
+        self.subtitle_file
+            .as_ref()
+            .filter(|file| file.mode == SubtitleMode::Embed)
+    }
+
+    fn burned_subtitle(&self) -> Option<&SubtitleFile> {
+        self.subtitle_file
+            .as_ref()
+            .filter(|file| file.mode == SubtitleMode::BurnIn)
+    }
+
+    /// 实际输出的那些流的编码：提取音轨或字幕时只有选中的那一路
+    fn output_sources(&self) -> Option<SourceCodecs> {
+        let source = self.source_codecs.as_ref()?;
+        if let FormatType::Subtitle(_) = self.output_format.get_format_type() {
+            let track = self.subtitle_track.unwrap_or(0);
+            return Some(SourceCodecs {
+                subtitle: vec![source.subtitle.get(track)?.clone()],
+                ..Default::default()
+            });
+        }
+        match (self.audio_track, &self.stream_map) {
+            (Some(track), _) => Some(SourceCodecs {
+                audio: vec![*source.audio.get(track)?],
+                ..Default::default()
+            }),
+            (None, Some(streams)) => {
+                let format_type = self.output_format.get_format_type();
+                let indices: Vec<u32> = stream_map::selected_streams(streams, &format_type)
+                    .map(|s| s.index)
+                    .collect();
+                Some(source.only(&indices))
+            }
+            (None, None) => Some(source.clone()),
+        }
+    }
+
+    /// 视频流能否直接复制：源编码容器能装，且没有要求改动画面或码率
+    fn copies_video(&self, format_type: &FormatType) -> bool {
+        let Some(source) = self.output_sources().filter(|_| self.stream_copy) else {
+            return false;
+        };
+        // 精确定位要从起点重新编码，复制只能从关键帧开始
+        let accurate_trim = self.trim.start.is_some() && self.trim.seek == SeekMode::Accurate;
+        matches!(format_type, FormatType::Video(_))
+            && self.video_rate.is_none()
+            && self.resolution.is_none()
+            && self.frame_rate.is_none()
+            && !self.two_pass
+            && self.extra_args.is_empty()
+            && !accurate_trim
+            && self.burned_subtitle().is_none()
+            // GIF 总要重新生成调色板
+            && !matches!(format_type, FormatType::Video(Video::Gif))
+            && source.video_fits(format_type, self.video_codec)
+    }
+
+    /// 音频流能否直接复制，条件同 [`FfmpegTask::copies_video`]
+    fn copies_audio(&self, format_type: &FormatType) -> bool {
+        let Some(source) = self.output_sources().filter(|_| self.stream_copy) else {
+            return false;
+        };
+        !matches!(format_type, FormatType::AudioVisual(_))
+            && self.audio_rate.is_none()
+            && self.sample_rate.is_none()
+            && self.normalization.is_none()
+            && self.extra_args.is_empty()
+            && source.audio_fits(format_type, self.audio_codec)
+    }
+
+    /// 字幕输出能否直接复制：源字幕已经是目标格式的编码
+    fn copies_subtitle(&self, subtitle: &Subtitle) -> bool {
+        self.stream_copy
+            && self.output_sources().is_some_and(|source| {
+                source
+                    .subtitle
+                    .iter()
+                    .all(|(_, codec)| codec.as_deref() == Some(subtitle.codec_name()))
+            })
+    }
+
+    /// 按当前配置，所有输出的流都能直接复制时为 remux
+    pub fn job_kind(&self) -> JobKind {
+        let Some(source) = self.output_sources() else {
+            return JobKind::Transcode;
+        };
+        let format_type = self.output_format.get_format_type();
+        if let FormatType::Subtitle(subtitle) = &format_type {
+            return match self.copies_subtitle(subtitle) {
+                true => JobKind::Remux,
+                false => JobKind::Transcode,
+            };
+        }
+        // 纯音频输出用 -vn 去掉了画面，只看音频能否复制
+        let has_video = !matches!(format_type, FormatType::Audio(_)) && !source.video.is_empty();
+        let has_audio = !source.audio.is_empty();
+        if (has_video || has_audio)
+            && (!has_video || self.copies_video(&format_type))
+            && (!has_audio || self.copies_audio(&format_type))
+        {
+            JobKind::Remux
+        } else {
+            JobKind::Transcode
+        }
+    }
+
+    /// 实际输出的时长（秒），截取时按片段计算
+    pub fn output_duration(&self) -> Option<f64> {
+        self.trim.output_duration(self.input_duration)
+    }
+
+    /// 目标文件大小换算出的视频码率（kbps），扣除音频和 2% 的封装开销
+    pub fn target_video_kbps(&self, size_mb: u32) -> Result<u32, String> {
+        let secs = self
+            .output_duration()
+            .filter(|d| *d > 0.0)
+            .ok_or("Target size needs a known input duration")?;
+        let total_kbps = size_mb as f64 * 8192.0 / secs * 0.98;
+        let audio_kbps = match self.audio_rate {
+            Some(AudioRateControl::Bitrate(kbps)) => kbps,
+            _ => AudioRateControl::DEFAULT_BITRATE,
+        };
+        let video_kbps = (total_kbps - audio_kbps as f64).floor();
+        if video_kbps < 16.0 {
+            return Err(format!(
+                "Target size {size_mb} MB is too small for {secs:.0}s of video"
+            ));
+        }
+        Ok(video_kbps as u32)
+    }
+
+    fn video_rate_args(&self, codec: Option<VideoCodec>) -> Result<Vec<String>, String> {
+        let Some(rate) = self.video_rate else {
+            return Ok(vec![]);
+        };
+
+        match rate {
+            VideoRateControl::Bitrate(kbps) => Ok(vec!["-b:v".into(), format!("{kbps}k")]),
+            VideoRateControl::Quality(quality) => {
+                let codec = codec.ok_or("Constant quality isn't available for this format")?;
+                let scale = codec.quality_scale();
+                if !scale.contains(quality) {
+                    return Err(format!("{codec} quality must be within {}", scale.hint()));
+                }
+                let mut args = vec![scale.arg.into(), quality.to_string()];
+                if scale.zero_bitrate {
+                    args.extend(["-b:v".into(), "0".into()]);
+                }
+                Ok(args)
+            }
+            VideoRateControl::TargetSize(size_mb) => {
+                let kbps = self.target_video_kbps(size_mb)?;
+                Ok(vec!["-b:v".into(), format!("{kbps}k")])
+            }
+        }
+    }
+
+    fn audio_rate_args(&self, codec: Option<AudioCodec>) -> Result<Vec<String>, String> {
+        let Some(rate) = self.audio_rate else {
+            return Ok(vec![]);
+        };
+
+        match rate {
+            AudioRateControl::Bitrate(kbps) => Ok(vec!["-b:a".into(), format!("{kbps}k")]),
+            AudioRateControl::Quality(quality) => {
+                let codec = codec.ok_or("VBR quality isn't available for this format")?;
+                let scale = codec
+                    .quality_scale()
+                    .ok_or_else(|| format!("{codec} has no VBR quality mode"))?;
+                if !scale.contains(quality) {
+                    return Err(format!("{codec} quality must be within {}", scale.hint()));
+                }
+                Ok(vec![scale.arg.into(), quality.to_string()])
+            }
+        }
+    }
+
+    pub fn resolution(mut self, resolution: Option<Resolution>) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn frame_rate(mut self, fps: Option<u32>) -> Self {
+        self.frame_rate = fps;
+        self
+    }
+
+    pub fn sample_rate(mut self, rate: Option<u32>) -> Self {
+        self.sample_rate = rate;
+        self
+    }
+
+    pub fn extra_arg(mut self, arg: impl Into<String>) -> Self {
+        self.extra_args.push(arg.into());
+        self
+    }
+
+    pub fn two_pass(mut self, enabled: bool) -> Self {
+        self.two_pass = enabled;
+        self
+    }
+
+    /// 两遍编码日志的路径前缀，放在临时目录下，按输出路径区分任务
+    pub fn passlog_prefix(output: &Path) -> PathBuf {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        output.hash(&mut hasher);
+        let stem = output
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        std::env::temp_dir()
+            .join("converlex")
+            .join(format!("{stem}-{:016x}", hasher.finish()))
+    }
+
+    /// 生成每一遍 ffmpeg 的参数，单遍编码时只有一项
+    pub fn build(self) -> Result<(PathBuf, Vec<Vec<String>>), String> {
+        let input_args = self.input_args()?;
+        self.build_with_inputs(input_args)
+    }
+
+    /// 单个输入的 `-i`、截取和选流参数
+    fn input_args(&self) -> Result<Vec<String>, String> {
+        let input = self.input.clone().ok_or("Missing input path")?;
+        self.trim.validate(self.input_duration)?;
+
+        let format_type = self.output_format.get_format_type();
+        let embedded = self.embedded_subtitle();
+
+        let mut input_args = self.trim.input_args();
+        input_args.extend(["-i".into(), input.to_string_lossy().into_owned()]);
+        if let Some(file) = embedded {
+            // 快速定位时字幕也要跳过同样的时间，才能和画面对上
+            input_args.extend(self.trim.input_args());
+            input_args.extend(["-i".into(), file.path.to_string_lossy().into_owned()]);
+        }
+        input_args.extend(self.trim.output_args());
+        match (&format_type, self.audio_track, &self.stream_map) {
+            (FormatType::Subtitle(_), _, _) => {
+                let track = self.subtitle_track.unwrap_or(0);
+                input_args.extend(["-map".into(), format!("0:s:{track}")]);
+            }
+            (_, Some(track), _) => {
+                input_args.extend(["-map".into(), format!("0:a:{track}"), "-vn".into()]);
+            }
+            // 音频配画面自己映射流，图片只取一帧画面，GIF 由滤镜图选流
+            (format_type, None, Some(streams))
+                if matches!(format_type, FormatType::Audio(_))
+                    || matches!(format_type, FormatType::Video(video) if *video != Video::Gif) =>
+            {
+                input_args.extend(stream_map::stream_map_args(streams, format_type)?);
+            }
+            // 手动映射了字幕后 ffmpeg 不再自动选流，音视频各取第一路
+            _ if embedded.is_some() => {
+                input_args.extend([
+                    "-map".into(),
+                    "0:v:0?".into(),
+                    "-map".into(),
+                    "0:a:0?".into(),
+                ]);
+            }
+            _ => {}
+        }
+        if let (Some(file), FormatType::Video(container)) = (embedded, &format_type) {
+            // 排在手动选中的字幕之后
+            let output_index = self.stream_map.as_ref().map_or(0, |streams| {
+                stream_map::selected_streams(streams, &format_type)
+                    .filter(|s| s.kind == StreamKind::Subtitle)
+                    .count()
+            });
+            input_args.extend(file.embed_args(container, 1, output_index)?);
+        }
+        Ok(input_args)
+    }
+
+    /// 同 [`FfmpegTask::build`]，但输入部分（`-i` 及相关参数）由调用方给出，
+    /// 供拼接等多输入任务复用编码参数
+    pub fn build_with_inputs(
+        self,
+        inputs: Vec<String>,
+    ) -> Result<(PathBuf, Vec<Vec<String>>), String> {
+        let output = self.output.clone().ok_or("Missing output path")?;
+
+        let format_type = self.output_format.get_format_type();
+        format_type.check_codecs(self.video_codec, self.audio_codec)?;
+        if self.subtitle_file.is_some() && !matches!(format_type, FormatType::Video(_)) {
+            return Err("Subtitle files can only be added to video outputs".into());
+        }
+        if let FormatType::Subtitle(subtitle) = &format_type {
+            return self.build_subtitle(subtitle, output, inputs);
+        }
+        if let FormatType::Image(image) = &format_type {
+            return self.build_image(image, output, inputs);
+        }
+        if let FormatType::Video(Video::Gif) = &format_type {
+            return self.build_gif(output, inputs);
+        }
+
+        // 纯音频输出不带视频参数
+        let has_video = !matches!(format_type, FormatType::Audio(_));
+
+        // 质量参数和两遍日志都依赖具体编码器，这两种情况下把实际编码写明
+        let video_codec =
+            if self.two_pass || matches!(self.video_rate, Some(VideoRateControl::Quality(_))) {
+                format_type.effective_video_codec(self.video_codec)
+            } else {
+                self.video_codec
+            };
+        let audio_codec = match self.audio_rate {
+            Some(AudioRateControl::Quality(_)) => format_type.effective_audio_codec(self.audio_codec),
+            _ => self.audio_codec,
+        };
+
+        let mut video_filters = FilterChain::new();
+        if has_video {
+            if let Some(resolution) = &self.resolution {
+                resolution.validate()?;
+                video_filters.push(resolution.scale_filter());
+            }
+            if let Some(fps) = self.frame_rate {
+                output_params::validate_frame_rate(fps)?;
+            }
+            // 缩放之后再叠字幕，字号按输出分辨率渲染
+            if let Some(file) = self.burned_subtitle() {
+                video_filters.push(file.burn_in_filter(self.trim.input_seek())?);
+            }
+        }
+
+        let input_args = {
+            let mut args: Vec<String> = vec!["-y".into()];
+            args.extend(inputs);
+            // 音频配画面已经用了 -filter_complex，滤镜要拼进去
+            if let FormatType::AudioVisual(visual) = &format_type {
+                args.extend(visual.ffmpeg_args(&video_filters));
+            }
+            args
+        };
+
+        let mut video_args: Vec<String> = vec![];
+        // 没手动选流时 ffmpeg 仍会自动带上画面（mp3/flac 当封面，m4a 编成 h264），要显式去掉
+        if !has_video && self.audio_track.is_none() && self.stream_map.is_none() {
+            video_args.extend(["-vn".into(), "-sn".into()]);
+        }
+        if has_video && self.copies_video(&format_type) {
+            video_args.extend(["-c:v".into(), "copy".into()]);
+        } else if has_video {
+            if let Some(codec) = video_codec {
+                video_args.push("-c:v".into());
+                video_args.push(codec.encoder().into());
+            }
+            video_args.extend(self.video_rate_args(video_codec)?);
+
+            if !matches!(format_type, FormatType::AudioVisual(_)) {
+                video_args.extend(video_filters.to_args("-vf"));
+            }
+
+            if let Some(fps) = self.frame_rate {
+                video_args.push("-r".into());
+                video_args.push(fps.to_string());
+            }
+        }
+
+        let mut audio_args: Vec<String> = vec![];
+        if self.copies_audio(&format_type) {
+            audio_args.extend(["-c:a".into(), "copy".into()]);
+        } else if let Some(codec) = audio_codec {
+            audio_args.push("-c:a".into());
+            audio_args.push(codec.encoder().into());
+        }
+        audio_args.extend(self.audio_rate_args(audio_codec)?);
+
+        if let Some(sr) = self.sample_rate {
+            output_params::validate_sample_rate(sr)?;
+            if let Some(codec) = format_type.effective_audio_codec(audio_codec)
+                && codec
+                    .sample_rates()
+                    .is_some_and(|rates| !rates.contains(&sr))
+            {
+                return Err(format!("{codec} doesn't support {sr} Hz"));
+            }
+            audio_args.push("-ar".into());
+            audio_args.push(sr.to_string());
+        }
+        if let Some(filter) = self.normalization_filter(&format_type)? {
+            audio_args.extend(["-af".into(), filter]);
+        }
+
+        // 显式指定封装器，和能力检测用的是同一个名字
+        let mut output_args = self.metadata_args();
+        output_args.extend([
+            "-f".into(),
+            self.output_format.get_muxer(),
+            output.to_string_lossy().into_owned(),
+        ]);
+
+        if !self.two_pass {
+            let mut args = input_args;
+            args.extend(video_args);
+            args.extend(audio_args);
+            args.extend(self.extra_args);
+            args.extend(output_args);
+            return Ok((output, vec![args]));
+        }
+
+        if !has_video {
+            return Err("Two-pass encoding needs a video output".into());
+        }
+        if !matches!(
+            self.video_rate,
+            Some(VideoRateControl::Bitrate(_) | VideoRateControl::TargetSize(_))
+        ) {
+            return Err("Two-pass encoding needs a bitrate or target size".into());
+        }
+        let codec = video_codec.ok_or("Two-pass encoding isn't available for this format")?;
+
+        let passlog = Self::passlog_prefix(&output);
+        let pass_args = |pass: u32| -> Vec<String> {
+            match codec {
+                // libx265 不认 -pass，要走自己的参数
+                VideoCodec::Hevc => vec![
+                    "-x265-params".into(),
+                    format!("pass={pass}:stats={}.log", passlog.to_string_lossy()),
+                ],
+                _ => vec![
+                    "-pass".into(),
+                    pass.to_string(),
+                    "-passlogfile".into(),
+                    passlog.to_string_lossy().into_owned(),
+                ],
+            }
+        };
+
+        // 第一遍只分析视频，输出丢弃
+        let mut first = input_args.clone();
+        first.extend(video_args.clone());
+        first.extend(pass_args(1));
+        first.extend(self.extra_args.clone());
+        first.extend(["-an".into(), "-f".into(), "null".into(), "-".into()]);
+
+        let mut second = input_args;
+        second.extend(video_args);
+        second.extend(pass_args(2));
+        second.extend(audio_args);
+        second.extend(self.extra_args);
+        second.extend(output_args);
+
+        Ok((output, vec![first, second]))
+    }
+
+    /// 字幕输出只有一路字幕流，编码相同时直接复制
+    fn build_subtitle(
+        self,
+        subtitle: &Subtitle,
+        output: PathBuf,
+        inputs: Vec<String>,
+    ) -> Result<(PathBuf, Vec<Vec<String>>), String> {
+        if self.two_pass {
+            return Err("Two-pass encoding needs a video output".into());
+        }
+        let track = self.subtitle_track.unwrap_or(0);
+        let source = self.output_sources();
+        if self.source_codecs.is_some() && source.is_none() {
+            return Err(format!("The input has no subtitle track #{}", track + 1));
+        }
+        let source_codec = source.and_then(|s| s.subtitle.into_iter().next()?.1);
+        if source_codec.as_deref().is_some_and(|c| !is_text_subtitle_codec(c)) {
+            return Err(format!(
+                "Subtitle track #{} is image-based and can't be converted to text",
+                track + 1
+            ));
+        }
+
+        let codec = if self.copies_subtitle(subtitle) {
+            "copy"
+        } else {
+            subtitle
+                .encoder()
+                .ok_or_else(|| format!("ffmpeg can't write {subtitle} subtitles"))?
+        };
+        let mut args: Vec<String> = vec!["-y".into()];
+        args.extend(inputs);
+        args.extend(["-c:s".into(), codec.into()]);
+        args.extend(self.metadata_args());
+        args.extend(self.extra_args);
+        args.extend([
+            "-f".into(),
+            self.output_format.get_muxer(),
+            output.to_string_lossy().into_owned(),
+        ]);
+        Ok((output, vec![args]))
+    }
+
+    /// 单张图片只输出一帧；设置了取样方式时输出编号序列，`output` 是带编号的文件名模式。
+    /// image2 按扩展名选编码器
+    fn build_image(
+        self,
+        image: &Image,
+        output: PathBuf,
+        inputs: Vec<String>,
+    ) -> Result<(PathBuf, Vec<Vec<String>>), String> {
+        if self.two_pass {
+            return Err("Two-pass encoding needs a video output".into());
+        }
+
+        let is_image2 = self.output_format.get_muxer() == "image2";
+        let mut filters = FilterChain::new();
+        if let Some(sampling) = &self.frame_sampling {
+            if !is_image2 {
+                return Err(format!("{image} can't be written as a numbered sequence"));
+            }
+            // 先取样再缩放，丢掉的帧不用处理
+            filters.push(sampling.filter()?);
+        }
+        if let Some(resolution) = &self.resolution {
+            resolution.validate()?;
+            filters.push(resolution.scale_filter());
+        }
+        if *image == Image::Ico {
+            // 图标最大 256x256，大图按比例缩小
+            filters.push("scale='min(256,iw)':'min(256,ih)':force_original_aspect_ratio=decrease");
+        }
+
+        let mut args: Vec<String> = vec!["-y".into()];
+        args.extend(inputs);
+        args.extend(filters.to_args("-vf"));
+        match &self.frame_sampling {
+            Some(sampling) => args.extend(sampling.output_args()),
+            None => args.extend(["-frames:v".into(), "1".into()]),
+        }
+        if let Some(quality) = self.image_quality {
+            output_params::validate_image_quality(quality)?;
+            if !image.is_lossy() {
+                return Err(format!("{image} is lossless and has no quality setting"));
+            }
+            args.extend(image.quality_args(quality));
+        }
+        args.extend(self.metadata_args());
+        // 输出的是单个文件而不是序列
+        if is_image2 && self.frame_sampling.is_none() {
+            args.extend(["-update".into(), "1".into()]);
+        }
+        args.extend(self.extra_args);
+        args.extend([
+            "-f".into(),
+            self.output_format.get_muxer(),
+            output.to_string_lossy().into_owned(),
+        ]);
+        Ok((output, vec![args]))
+    }
+
+    /// GIF 分两遍：第一遍用 palettegen 生成调色板，第二遍用 paletteuse 按调色板编码。
+    /// 调色板和两遍编码日志放在同一处，跑完一起清理
+    fn build_gif(
+        self,
+        output: PathBuf,
+        inputs: Vec<String>,
+    ) -> Result<(PathBuf, Vec<Vec<String>>), String> {
+        if self.two_pass {
+            return Err("GIF is always built in two passes, turn off two-pass encoding".into());
+        }
+        self.gif.validate()?;
+
+        let mut filters = FilterChain::new();
+        for filter in self.gif.frame_filters() {
+            filters.push(filter);
+        }
+        if let Some(file) = self.burned_subtitle() {
+            filters.push(file.burn_in_filter(self.trim.input_seek())?);
+        }
+        let chain = filters.join().unwrap_or_default();
+        let passlog = Self::passlog_prefix(&output);
+        let palette = format!("{}-palette.png", passlog.to_string_lossy());
+
+        let mut first: Vec<String> = vec!["-y".into()];
+        first.extend(inputs.clone());
+        first.extend([
+            "-vf".into(),
+            format!("{chain},{}", self.gif.palettegen_filter()),
+            "-an".into(),
+            "-update".into(),
+            "1".into(),
+            "-f".into(),
+            "image2".into(),
+            palette.clone(),
+        ]);
+
+        // 调色板放在第一个输入，原输入后面的截取参数才不会作用到它
+        let mut second: Vec<String> = vec!["-y".into(), "-i".into(), palette];
+        second.extend(inputs);
+        second.extend([
+            "-filter_complex".into(),
+            format!(
+                "[1:v:0]{chain}[frames];[frames][0:v]{}",
+                self.gif.paletteuse_filter()
+            ),
+            "-an".into(),
+        ]);
+        second.extend(self.gif.loop_args());
+        second.extend(self.metadata_args());
+        second.extend(self.extra_args);
+        second.extend([
+            "-f".into(),
+            self.output_format.get_muxer(),
+            output.to_string_lossy().into_owned(),
+        ]);
+
+        Ok((output, vec![first, second]))
+    }
+
+    /// 是否要先测量响度：设置了标准化，且输出里有音频
+    pub fn normalizes_audio(&self) -> bool {
+        let has_audio = match self.output_format.get_format_type() {
+            FormatType::Audio(_) | FormatType::AudioVisual(_) => true,
+            FormatType::Video(video) => video != Video::Gif,
+            _ => false,
+        };
+        self.normalization.is_some()
+            && has_audio
+            && self
+                .source_codecs
+                .as_ref()
+                .is_none_or(|source| !source.audio.is_empty())
+    }
+
+    /// 按测量值调整音量的滤镜，还没测量时为 `None`
+    fn normalization_filter(&self, format_type: &FormatType) -> Result<Option<String>, String> {
+        let Some(normalization) = &self.normalization else {
+            return Ok(None);
+        };
+        normalization.validate()?;
+        // 测量值只有一份，多路音频各自的响度不同
+        let audio_tracks = self.stream_map.as_ref().map_or(1, |streams| {
+            stream_map::selected_streams(streams, format_type)
+                .filter(|s| s.kind == StreamKind::Audio)
+                .count()
+        });
+        if audio_tracks > 1 {
+            return Err("Audio normalization works on a single audio track".into());
+        }
+        let Some(measured) = &self.loudness_measurement else {
+            return Ok(None);
+        };
+        let mut filter = normalization.apply_filter(measured)?;
+        // loudnorm 按 192 kHz 输出，没指定采样率时改回输入的采样率
+        if matches!(normalization, Normalization::Loudness(_)) && self.sample_rate.is_none() {
+            let rate = self.source_sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE);
+            filter.push_str(&format!(",aresample={rate}"));
+        }
+        Ok(Some(filter))
+    }
+
+    fn metadata_args(&self) -> Vec<String> {
+        match self.strip_metadata {
+            true => vec!["-map_metadata".into(), "-1".into()],
+            false => vec![],
+        }
+    }
+
+    pub async fn run_with_progress(
+        &self,
+        task_id: String,
+        tx: mpsc::UnboundedSender<ProgressMsg>,
+        mut control: mpsc::UnboundedReceiver<ControlMsg>,
+    ) {
+        let total = self.output_duration().map(ProgressTotal::Duration);
+        let built = match self.clone().build() {
+            Ok(built) => built,
+            Err(e) => return send_invalid_config(task_id, &tx, e),
+        };
+        if !self.normalizes_audio() {
+            let plan = PassPlan::new(built, total);
+            return self.run_passes(task_id, plan, tx, control).await;
+        }
+
+        // 配置检查通过后再测量，再用测量值重新生成参数
+        let inputs = self.input_args().unwrap_or_default();
+        let pass_count = built.1.len() as u32;
+        let Some(measured) = self
+            .measure_loudness(&task_id, inputs, pass_count, &tx, &mut control)
+            .await
+        else {
+            return;
+        };
+        let task = self.clone().loudness_measurement(Some(measured));
+        match task.clone().build() {
+            Ok(built) => {
+                let plan = PassPlan::new(built, total).after_measuring();
+                task.run_passes(task_id, plan, tx, control).await;
+            }
+            Err(e) => send_invalid_config(task_id, &tx, e),
+        }
+    }
+
+    /// 标准化之前先跑一遍测量，`inputs` 是测量用的输入参数，`pass_count` 是之后还要跑的遍数。
+    /// 取消或出错时已经发出了对应的消息，返回 `None`
+    pub async fn measure_loudness(
+        &self,
+        task_id: &str,
+        inputs: Vec<String>,
+        pass_count: u32,
+        tx: &mpsc::UnboundedSender<ProgressMsg>,
+        control: &mut mpsc::UnboundedReceiver<ControlMsg>,
+    ) -> Option<LoudnessMeasurement> {
+        let normalization = self.normalization?;
+        let mut args: Vec<String> = vec!["-y".into()];
+        args.extend(inputs);
+        args.extend([
+            "-vn".into(),
+            "-sn".into(),
+            "-dn".into(),
+            "-af".into(),
+            normalization.analysis_filter(),
+            "-f".into(),
+            "null".into(),
+            "-".into(),
+        ]);
+
+        log::info!("[Task {task_id}] 🔊 Measuring loudness");
+        let task_id = Arc::new(task_id.to_string());
+        let callback = pass_callback(Arc::clone(&task_id), tx.clone(), 1, pass_count + 1);
+        let mut stderr = vec![];
+        let run = RunContext {
+            total: self.output_duration().map(ProgressTotal::Duration),
+            trim: &self.trim,
+            stderr_log: Some(&mut stderr),
+        };
+        let outcome = run_ffmpeg_command_with_progress(
+            self.ffmpeg_entry.clone(),
+            task_id.to_string(),
+            args,
+            run,
+            control,
+            callback,
+        )
+        .await;
+
+        let error = match outcome {
+            Ok(RunOutcome::Finished) => match normalization.parse_measurement(&stderr) {
+                Ok(measurement) => {
+                    log::info!("[Task {task_id}] 🔊 Measured {measurement}");
+                    let _ = tx.send(ProgressMsg::Measured {
+                        task_id: task_id.to_string(),
+                        measurement,
+                    });
+                    return Some(measurement);
+                }
+                Err(e) => e,
+            },
+            Ok(RunOutcome::Cancelled) => {
+                let _ = tx.send(ProgressMsg::Cancelled {
+                    task_id: task_id.to_string(),
+                });
+                log::info!("[Task {task_id}] ⏹ Task cancelled.");
+                return None;
+            }
+            Err(e) => e.to_string(),
+        };
+        log::error!("[Task {task_id}] ❌ Loudness measurement failed: {error}");
+        let _ = tx.send(ProgressMsg::Error {
+            task_id: task_id.to_string(),
+            error: format!("Loudness measurement failed: {error}"),
+        });
+        None
+    }
+
+    /// 依次运行 [`PassPlan`] 中的每一遍，并发送进度和结果
+    pub async fn run_passes(
+        &self,
+        task_id: String,
+        plan: PassPlan,
+        tx: mpsc::UnboundedSender<ProgressMsg>,
+        mut control: mpsc::UnboundedReceiver<ControlMsg>,
+    ) {
+        let PassPlan {
+            output,
+            passes,
+            done_passes,
+            total,
+        } = plan;
+        let task_id = Arc::new(task_id);
+        let encode_passes = passes.len() as u32;
+        let pass_count = encode_passes + done_passes;
+        let passlog = Self::passlog_prefix(&output);
+        if encode_passes > 1
+            && let Some(dir) = passlog.parent()
+            && let Err(e) = tokio::fs::create_dir_all(dir).await
+        {
+            log::warn!("[Task {task_id}] ⚠ Failed to create passlog dir: {e}");
+        }
+
+        let ffmpeg_entry = self.ffmpeg_entry.clone();
+        let mut outcome = Ok(RunOutcome::Finished);
+
+        for (pass, args) in passes.into_iter().enumerate() {
+            let pass = pass as u32 + 1 + done_passes;
+            let callback = pass_callback(Arc::clone(&task_id), tx.clone(), pass, pass_count);
+
+            if pass_count > 1 {
+                log::info!("[Task {task_id}] 🔁 Pass {pass}/{pass_count}");
+            }
+            let run = RunContext {
+                total,
+                trim: &self.trim,
+                stderr_log: None,
+            };
+            outcome = run_ffmpeg_command_with_progress(
+                ffmpeg_entry.clone(),
+                task_id.to_string(),
+                args,
+                run,
+                &mut control,
+                callback,
+            )
+            .await;
+            if !matches!(outcome, Ok(RunOutcome::Finished)) {
+                break;
+            }
+        }
+
+        if encode_passes > 1 {
+            remove_passlogs(&passlog).await;
+        }
+
+        match outcome {
+            Ok(RunOutcome::Cancelled) => {
+                // 删除未完成的输出
+                if let Err(e) = tokio::fs::remove_file(&output).await {
+                    log::warn!("[Task {task_id}] ⚠ Failed to remove partial output: {e}");
+                }
+                let _ = tx.send(ProgressMsg::Cancelled {
+                    task_id: task_id.to_string(),
+                });
+                log::info!("[Task {task_id}] ⏹ Task cancelled.");
+            }
+            Ok(RunOutcome::Finished) => {
+                let _ = tx.send(ProgressMsg::Done {
+                    task_id: task_id.to_string(),
+                });
+                log::info!("[Task {task_id}] ✅ Task completed.");
+            }
+            Err(e) => {
+                let _ = tx.send(ProgressMsg::Error {
+                    task_id: task_id.to_string(),
+                    error: e.to_string(),
+                });
+                log::error!("[Task {task_id}] ❌ Error: {:?}", e);
+            }
+        }
+    }
+}
+
+/// [`FfmpegTask::build`] 生成的各遍参数，以及它们在整个任务中的位置
+#[derive(Debug, Clone)]
+pub struct PassPlan {
+    pub output: PathBuf,
+    pub passes: Vec<Vec<String>>,
+    /// 之前已经跑完的遍数（先测量响度时为 1）
+    pub done_passes: u32,
+    /// 输出的时长或帧数，用于计算进度
+    pub total: Option<ProgressTotal>,
+}
+
+impl PassPlan {
+    pub fn new(
+        (output, passes): (PathBuf, Vec<Vec<String>>),
+        total: Option<ProgressTotal>,
+    ) -> Self {
+        Self {
+            output,
+            passes,
+            done_passes: 0,
+            total,
+        }
+    }
+
+    /// 前面已经跑过一遍响度测量
+    pub fn after_measuring(mut self) -> Self {
+        self.done_passes = 1;
+        self
+    }
+}
+
+/// 单次运行 ffmpeg 时计算进度和收集 stderr 的参数
+struct RunContext<'a> {
+    /// 输出的时长或帧数，`None` 时退回到 stderr 的 Duration: 行
+    total: Option<ProgressTotal>,
+    /// 从 Duration: 行换算截取后的片段长度
+    trim: &'a Trim,
+    /// 测量响度那一遍要完整的 stderr
+    stderr_log: Option<&'a mut Vec<String>>,
+}
+
+/// 每一遍的进度都标上遍数，ratio() 据此换算成整体进度
+fn pass_callback(
+    task_id: Arc<String>,
+    tx: mpsc::UnboundedSender<ProgressMsg>,
+    pass: u32,
+    pass_count: u32,
+) -> impl FnMut(RunEvent) + Send + 'static {
+    move |event: RunEvent| {
+        let task_id = task_id.as_str().to_string();
+        let _ = tx.send(match event {
+            RunEvent::Progress(mut progress) => {
+                progress.pass = pass;
+                progress.pass_count = pass_count;
+                ProgressMsg::Progress { task_id, progress }
+            }
+            RunEvent::Paused => ProgressMsg::Paused { task_id },
+            RunEvent::Resumed => ProgressMsg::Resumed { task_id },
+        });
+    }
+}
+
+/// 生成参数失败时报告错误，任务不会开始运行
+pub(crate) fn send_invalid_config(
+    task_id: String,
+    tx: &mpsc::UnboundedSender<ProgressMsg>,
+    error: String,
+) {
+    log::error!("[Task {task_id}] ❌ Invalid config: {}", error);
+    let _ = tx.send(ProgressMsg::Error {
+        task_id,
+        error: format!("Invalid config: {error}"),
+    });
+}
+
+/// 删除两遍编码留下的日志（`<prefix>-0.log`、`.mbtree`、x265 的 `.cutree` 等）
+async fn remove_passlogs(prefix: &Path) {
+    let (Some(dir), Some(name)) = (prefix.parent(), prefix.file_name()) else {
+        return;
+    };
+    let name = name.to_string_lossy().to_string();
+
+    let Ok(mut entries) = tokio::fs::read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        if entry.file_name().to_string_lossy().starts_with(&name)
+            && let Err(e) = tokio::fs::remove_file(entry.path()).await
+        {
+            log::warn!("⚠ Failed to remove passlog {}: {e}", entry.path().display());
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ProgressMsg {
+    Started { task_id: String },
+    Progress { task_id: String, progress: FfmpegProgress },
+    Paused { task_id: String },
+    Resumed { task_id: String },
+    Cancelled { task_id: String },
+    /// 响度测量完成，之后才开始标准化
+    Measured {
+        task_id: String,
+        measurement: LoudnessMeasurement,
+    },
+    Done { task_id: String },
+    Error { task_id: String, error: String },
+}
+
+/// ffmpeg 进程运行中产生的事件
+#[derive(Debug, Clone)]
+pub enum RunEvent {
+    Progress(FfmpegProgress),
+    Paused,
+    Resumed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunOutcome {
+    Finished,
+    Cancelled,
+}
+
+/// 以最多 `max_jobs` 的并发跑完一批任务，按传入顺序开始
+pub async fn run_batch(
+    tasks: Vec<(String, FfmpegTask)>,
+    max_jobs: usize,
+    controls: TaskControls,
+    tx: mpsc::UnboundedSender<ProgressMsg>,
+) -> anyhow::Result<()> {
+    let (scheduler, handle) = Scheduler::spawn(max_jobs, controls, tx);
+    for (id, task) in tasks {
+        scheduler.enqueue(id, task);
+    }
+    drop(scheduler);
+
+    handle.await?;
+    Ok(())
+}
+
+async fn run_ffmpeg_command_with_progress<F>(
+    entity: FfmpegEntry,
+    id: String,
+    args: Vec<String>,
+    run: RunContext<'_>,
+    control: &mut mpsc::UnboundedReceiver<ControlMsg>,
+    mut event_cb: F,
+) -> anyhow::Result<RunOutcome>
+where
+    F: FnMut(RunEvent) + Send + 'static,
+{
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    // 进度走 stdout 的 key=value 块，stderr 只留给日志和错误
+    let mut full_args: Vec<String> = vec!["-progress".into(), "pipe:1".into(), "-nostats".into()];
+    full_args.extend(args);
+
+    log::info!(
+        "[Task {id}] ▶ Running: {} {}",
+        entity.to_string(),
+        full_args.join(" ")
+    );
+
+    let mut cmd = Command::new(entity.to_string());
+    cmd.args(&full_args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdin(Stdio::null());
+
+    let mut child = cmd.spawn().map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to spawn ffmpeg process: {}\nCommand: {} {}",
+            e,
+            entity.to_string(),
+            full_args.join(" ")
+        )
+    })?;
+
+    let stdout = child.stdout.take().expect("Failed to capture stdout");
+    let stderr = child.stderr.take().expect("Failed to capture stderr");
+    let mut stdout_lines = BufReader::new(stdout).split(b'\n');
+    let mut stderr_lines = BufReader::new(stderr).split(b'\n');
+
+    // 没有 ffprobe 的时长或帧数时，退回到 stderr 的 Duration: 行，截取时再换算成片段长度
+    let RunContext {
+        total,
+        trim,
+        mut stderr_log,
+    } = run;
+    let mut parser = ProgressParser::new(total);
+    let mut duration_known = total.is_some();
+    let mut stderr_tail: VecDeque<String> = VecDeque::with_capacity(STDERR_TAIL_LINES);
+    let mut stdout_done = false;
+    let mut stderr_done = false;
+    let mut control_open = true;
+
+    while !(stdout_done && stderr_done) {
+        tokio::select! {
+            msg = control.recv(), if control_open => match msg {
+                Some(ControlMsg::Cancel) => {
+                    child.start_kill()?;
+                    child.wait().await?;
+                    return Ok(RunOutcome::Cancelled);
+                }
+                Some(ControlMsg::Pause) => match task_control::suspend(&child) {
+                    Ok(_) => event_cb(RunEvent::Paused),
+                    Err(e) => log::warn!("[Task {id}] ⚠ Failed to pause: {e}"),
+                },
+                Some(ControlMsg::Resume) => match task_control::resume(&child) {
+                    Ok(_) => event_cb(RunEvent::Resumed),
+                    Err(e) => log::warn!("[Task {id}] ⚠ Failed to resume: {e}"),
+                },
+                None => control_open = false,
+            },
+            line = stdout_lines.next_segment(), if !stdout_done => match line? {
+                Some(line) => {
+                    if let Some(progress) = parser.feed_line(&String::from_utf8_lossy(&line)) {
+                        event_cb(RunEvent::Progress(progress));
+                    }
+                }
+                None => stdout_done = true,
+            },
+            line = stderr_lines.next_segment(), if !stderr_done => match line? {
+                Some(line) => {
+                    let text = String::from_utf8_lossy(&line).trim_end().to_string();
+
+                    if !duration_known
+                        && let Some(dur) = parse_duration(&text)
+                    {
+                        duration_known = true;
+                        let dur = trim.output_duration(Some(dur as f64)).unwrap_or_default();
+                        parser.set_duration_us(Some((dur * 1_000_000.0) as i64));
+                        log::debug!("[Task {id}] 🎬 Duration = {}s", dur);
+                    }
+
+                    // 测量那一遍的结果在 stderr 里，要完整留下
+                    if let Some(log) = stderr_log.as_mut() {
+                        log.push(text.clone());
+                    }
+                    if stderr_tail.len() == STDERR_TAIL_LINES {
+                        stderr_tail.pop_front();
+                    }
+                    stderr_tail.push_back(text);
+                }
+                None => stderr_done = true,
+            },
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        return Err(anyhow::anyhow!(
+            "ffmpeg exited with status {}\nStderr (tail):\n{}",
+            status,
+            stderr_tail.into_iter().collect::<Vec<_>>().join("\n")
+        ));
+    }
+
+    Ok(RunOutcome::Finished)
+}
+
+fn parse_duration(line: &str) -> Option<f32> {
+    let start = line.find("Duration: ")? + 10;
+    let end = line[start..].find(',')? + start;
+    let time_str = &line[start..end];
+    parse_time_str(time_str)
+}
+
+fn parse_time_str(s: &str) -> Option<f32> {
+    let parts: Vec<&str> = s.trim().split(':').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let h = parts[0].parse::<f32>().ok()?;
+    let m = parts[1].parse::<f32>().ok()?;
+    let s = parts[2].parse::<f32>().ok()?;
+    Some(h * 3600.0 + m * 60.0 + s)
+}
+
+pub async fn find_ffmpeg() -> Option<FfmpegEntry> {
+    #[cfg(unix)]
+    let which_cmd = Command::new("which").arg("ffmpeg").output();
+
+    #[cfg(windows)]
+    let which_cmd = Command::new("where").arg("ffmpeg").output();
+
+    let mut candidates = vec![];
+
+    // 1. use cmd to get path
+    if let Ok(output) = which_cmd.await {
+        if output.status.success() {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            if let Some(path) = stdout.lines().next() {
+                candidates.push(FfmpegEntry::Path(path.trim().into()));
+            }
+        }
+    }
+
+    // 2. or use ffmpeg from current directory
+    if let Ok(exe_path) = std::env::current_exe() {
+        let exe_dir = exe_path.parent().unwrap_or(Path::new("."));
+        #[cfg(target_os = "windows")]
+        let ffmpeg_path = exe_dir.join("ffmpeg.exe");
+        #[cfg(not(target_os = "windows"))]
+        let ffmpeg_path = exe_dir.join("ffmpeg");
+
+        if ffmpeg_path.exists() {
+            candidates.push(FfmpegEntry::Path(ffmpeg_path));
+        }
+    }
+
+    // 3. let the OS resolve "ffmpeg" from PATH
+    candidates.push(FfmpegEntry::Env);
+
+    // 文件存在不代表能跑（架构不对、缺动态库等），逐个用 -version 验证
+    for entry in candidates {
+        match FfmpegCaps::probe_version(&entry).await {
+            Ok(version) => {
+                log::info!("found ffmpeg {version} at {}", entry.to_string());
+                return Some(entry);
+            }
+            Err(e) => log::warn!("skipping ffmpeg candidate {}: {e}", entry.to_string()),
+        }
+    }
+
+    // end. 404
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::media_format::{Audio, MediaFormat};
+
+    fn task(format: MediaFormat) -> FfmpegTask {
+        let output = format!("out.{}", format.get_ext());
+        FfmpegTask::new(FfmpegEntry::Env, Arc::new(format))
+            .input("in.mkv")
+            .output(output)
+    }
+
+    /// 只有一遍时的完整参数，用空格连起来方便断言
+    fn args(task: FfmpegTask) -> String {
+        let (_, passes) = task.build().unwrap();
+        assert_eq!(passes.len(), 1);
+        passes[0].join(" ")
+    }
+
+    #[test]
+    fn builds_default_conversion() {
+        assert_eq!(
+            args(task(MediaFormat::Audio(Audio::Flac))),
+            "-y -i in.mkv -vn -sn -f flac out.flac"
+        );
+    }
+
+    #[test]
+    fn drops_video_from_audio_outputs() {
+        let args = args(task(MediaFormat::Audio(Audio::Mp3)).source_codecs(h264_aac()));
+        assert!(args.starts_with("-y -i in.mkv -vn -sn "), "{args}");
+        assert!(!args.contains("-c:v"), "{args}");
+    }
+
+    #[test]
+    fn selects_encoders_for_chosen_codecs() {
+        let args = args(
+            task(MediaFormat::Video(Video::Mkv))
+                .video_codec(Some(VideoCodec::Hevc))
+                .audio_codec(Some(AudioCodec::Opus)),
+        );
+
+        assert!(args.contains("-c:v libx265"), "{args}");
+        assert!(args.contains("-c:a libopus"), "{args}");
+    }
+
+    #[test]
+    fn rejects_codecs_the_container_cannot_hold() {
+        let task = task(MediaFormat::Video(Video::Webm)).video_codec(Some(VideoCodec::H264));
+
+        assert!(task.build().is_err());
+    }
+
+    #[test]
+    fn builds_rate_control_args() {
+        let mp4 = || task(MediaFormat::Video(Video::Mp4));
+
+        let bitrate = args(
+            mp4()
+                .video_rate(Some(VideoRateControl::Bitrate(2500)))
+                .audio_rate(Some(AudioRateControl::Bitrate(192))),
+        );
+        assert!(bitrate.contains("-b:v 2500k -b:a 192k"), "{bitrate}");
+
+        // 恒定质量要写明编码器，VP9/AV1 还要 -b:v 0
+        let quality = args(mp4().video_rate(Some(VideoRateControl::Quality(20))));
+        assert!(quality.contains("-c:v libx264 -crf 20"), "{quality}");
+        let quality = args(
+            mp4()
+                .video_codec(Some(VideoCodec::Vp9))
+                .video_rate(Some(VideoRateControl::Quality(31))),
+        );
+        assert!(quality.contains("-crf 31 -b:v 0"), "{quality}");
+    }
+
+    #[test]
+    fn rejects_out_of_range_quality() {
+        let video =
+            task(MediaFormat::Video(Video::Mp4)).video_rate(Some(VideoRateControl::Quality(60)));
+        assert!(video.build().unwrap_err().contains("0-51"));
+
+        let audio =
+            task(MediaFormat::Audio(Audio::Aac)).audio_rate(Some(AudioRateControl::Quality(2)));
+        assert!(audio.build().is_err());
+    }
+
+    #[test]
+    fn converts_target_size_to_bitrate() {
+        let task = task(MediaFormat::Video(Video::Mp4)).input_duration(Some(100.0));
+
+        // 10 MB * 8192 / 100s * 0.98 - 128k 音频
+        assert_eq!(task.target_video_kbps(10), Ok(674));
+        assert!(task.target_video_kbps(1).is_err());
+        assert!(task.input_duration(None).target_video_kbps(10).is_err());
+    }
+
+    #[test]
+    fn builds_two_passes() {
+        let task = task(MediaFormat::Video(Video::Mp4))
+            .video_rate(Some(VideoRateControl::Bitrate(1000)))
+            .audio_rate(Some(AudioRateControl::Bitrate(128)))
+            .two_pass(true);
+        let passlog = FfmpegTask::passlog_prefix(Path::new("out.mp4"));
+        let passlog = passlog.to_string_lossy();
+        let (_, passes) = task.build().unwrap();
+
+        assert_eq!(passes.len(), 2);
+        let first = passes[0].join(" ");
+        let second = passes[1].join(" ");
+        assert!(
+            first.contains(&format!(
+                "-c:v libx264 -b:v 1000k -pass 1 -passlogfile {passlog}"
+            )),
+            "{first}"
+        );
+        assert!(first.ends_with("-an -f null -"), "{first}");
+        assert!(!first.contains("-b:a"), "{first}");
+        assert!(
+            second.contains(&format!("-pass 2 -passlogfile {passlog} -b:a 128k")),
+            "{second}"
+        );
+        assert!(second.ends_with("-f mp4 out.mp4"), "{second}");
+    }
+
+    #[test]
+    fn passes_x265_its_own_pass_params() {
+        let task = task(MediaFormat::Video(Video::Mkv))
+            .video_codec(Some(VideoCodec::Hevc))
+            .video_rate(Some(VideoRateControl::Bitrate(1000)))
+            .two_pass(true);
+        let (_, passes) = task.build().unwrap();
+
+        assert!(passes[0].iter().any(|arg| arg.starts_with("pass=1:stats=")));
+        assert!(!passes[1].contains(&"-pass".to_string()));
+    }
+
+    #[test]
+    fn two_pass_needs_a_bitrate_and_video() {
+        let crf = task(MediaFormat::Video(Video::Mp4))
+            .video_rate(Some(VideoRateControl::Quality(23)))
+            .two_pass(true);
+        assert!(crf.build().is_err());
+
+        let audio = task(MediaFormat::Audio(Audio::Mp3))
+            .video_rate(Some(VideoRateControl::Bitrate(1000)))
+            .two_pass(true);
+        assert!(audio.build().is_err());
+    }
+
+    #[test]
+    fn passlog_prefix_differs_per_output() {
+        let a = FfmpegTask::passlog_prefix(Path::new("/a/out.mp4"));
+        let b = FfmpegTask::passlog_prefix(Path::new("/b/out.mp4"));
+
+        assert_ne!(a, b);
+        assert!(a.file_name().unwrap().to_string_lossy().starts_with("out-"));
+    }
+
+    #[tokio::test]
+    async fn removes_only_matching_passlogs() {
+        let prefix = FfmpegTask::passlog_prefix(Path::new("/removes_only_matching_passlogs.mp4"));
+        let dir = prefix.parent().unwrap();
+        tokio::fs::create_dir_all(dir).await.unwrap();
+        let log = prefix.with_extension("log");
+        let mbtree = dir.join(format!(
+            "{}-0.log.mbtree",
+            prefix.file_name().unwrap().to_string_lossy()
+        ));
+        let other = dir.join("removes_only_matching_passlogs-other.log");
+        for path in [&log, &mbtree, &other] {
+            tokio::fs::write(path, b"").await.unwrap();
+        }
+
+        remove_passlogs(&prefix).await;
+
+        assert!(!log.exists() && !mbtree.exists());
+        assert!(other.exists());
+        tokio::fs::remove_file(other).await.unwrap();
+    }
+
+    #[test]
+    fn builds_output_params() {
+        let args = args(
+            task(MediaFormat::Video(Video::Mp4))
+                .resolution(Some(Resolution::Height(720)))
+                .frame_rate(Some(30))
+                .sample_rate(Some(44100)),
+        );
+
+        assert!(args.contains("-vf scale=-2:720 -r 30 -ar 44100"), "{args}");
+    }
+
+    #[test]
+    fn rejects_sample_rates_the_codec_cannot_encode() {
+        let task = task(MediaFormat::Audio(Audio::Opus)).sample_rate(Some(44100));
+
+        assert!(task.build().unwrap_err().contains("44100"));
+    }
+
+    #[test]
+    fn builds_trim_args_around_the_input() {
+        let trim = Trim {
+            start: Some(5.0),
+            duration: Some(10.0),
+            ..Default::default()
+        };
+        let accurate = args(task(MediaFormat::Audio(Audio::Flac)).trim(trim));
+        assert_eq!(
+            accurate,
+            "-y -i in.mkv -ss 5.000 -t 10.000 -vn -sn -f flac out.flac"
+        );
+
+        let fast = Trim {
+            seek: SeekMode::Fast,
+            ..trim
+        };
+        let fast = args(task(MediaFormat::Audio(Audio::Flac)).trim(fast));
+        assert_eq!(fast, "-y -ss 5.000 -i in.mkv -t 10.000 -vn -sn -f flac out.flac");
+    }
+
+    #[test]
+    fn rejects_trim_past_the_input() {
+        let task = task(MediaFormat::Audio(Audio::Flac))
+            .input_duration(Some(3.0))
+            .trim(Trim {
+                start: Some(5.0),
+                ..Default::default()
+            });
+
+        assert!(task.build().is_err());
+    }
+
+    fn h264_aac() -> Option<SourceCodecs> {
+        Some(SourceCodecs {
+            video: vec![(0, Some(VideoCodec::H264))],
+            audio: vec![(1, Some(AudioCodec::Aac))],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn remuxes_when_streams_fit() {
+        let task = task(MediaFormat::Video(Video::Mkv)).source_codecs(h264_aac());
+        assert_eq!(task.job_kind(), JobKind::Remux);
+
+        let args = args(task);
+        assert_eq!(args, "-y -i in.mkv -c:v copy -c:a copy -f matroska out.mkv");
+    }
+
+    #[test]
+    fn remuxes_audio_out_of_a_video() {
+        let task = task(MediaFormat::Audio(Audio::Alac)).source_codecs(h264_aac());
+        assert_eq!(task.job_kind(), JobKind::Remux);
+
+        let args = args(task);
+        assert_eq!(args, "-y -i in.mkv -vn -sn -c:a copy -f ipod out.m4a");
+    }
+
+    #[test]
+    fn reencodes_only_the_changed_streams() {
+        let task = task(MediaFormat::Video(Video::Mp4))
+            .source_codecs(h264_aac())
+            .resolution(Some(Resolution::Height(720)));
+        assert_eq!(task.job_kind(), JobKind::Transcode);
+
+        let args = args(task);
+        assert!(!args.contains("-c:v copy"), "{args}");
+        assert!(args.contains("-c:a copy"), "{args}");
+    }
+
+    #[test]
+    fn stream_copy_can_be_turned_off() {
+        let task = task(MediaFormat::Video(Video::Mkv))
+            .source_codecs(h264_aac())
+            .stream_copy(false);
+
+        assert_eq!(task.job_kind(), JobKind::Transcode);
+        assert!(!args(task).contains("copy"));
+    }
+
+    #[test]
+    fn accurate_trim_reencodes_video() {
+        let trim = Trim {
+            start: Some(5.0),
+            ..Default::default()
+        };
+        let accurate = task(MediaFormat::Video(Video::Mkv))
+            .source_codecs(h264_aac())
+            .trim(trim);
+        assert_eq!(accurate.job_kind(), JobKind::Transcode);
+
+        let fast = accurate.trim(Trim {
+            seek: SeekMode::Fast,
+            ..trim
+        });
+        assert_eq!(fast.job_kind(), JobKind::Remux);
+    }
+
+    #[test]
+    fn maps_a_single_audio_track() {
+        let task = task(MediaFormat::Audio(Audio::Alac))
+            .audio_track(Some(1))
+            .source_codecs(Some(SourceCodecs {
+                audio: vec![(1, Some(AudioCodec::Ac3)), (2, Some(AudioCodec::Aac))],
+                ..Default::default()
+            }));
+        assert_eq!(task.job_kind(), JobKind::Remux);
+
+        let args = args(task);
+        assert_eq!(
+            args,
+            "-y -i in.mkv -map 0:a:1 -vn -c:a copy -f ipod out.m4a"
+        );
+    }
+
+    #[test]
+    fn extracts_a_subtitle_track() {
+        let source = |codec: &str| {
+            Some(SourceCodecs {
+                subtitle: vec![(2, Some("ass".into())), (3, Some(codec.into()))],
+                ..Default::default()
+            })
+        };
+        let srt = || task(MediaFormat::Subtitle(Subtitle::Srt)).subtitle_track(Some(1));
+
+        assert_eq!(
+            args(srt().source_codecs(source("subrip"))),
+            "-y -i in.mkv -map 0:s:1 -c:s copy -f srt out.srt"
+        );
+        assert!(args(srt().source_codecs(source("ass"))).contains("-c:s subrip"));
+        assert!(
+            srt()
+                .source_codecs(source("hdmv_pgs_subtitle"))
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn embeds_an_external_subtitle_file() {
+        let args = args(
+            task(MediaFormat::Video(Video::Mp4))
+                .subtitle_file(Some(SubtitleFile::new("a.srt", SubtitleMode::Embed))),
+        );
+
+        assert!(
+            args.starts_with(
+                "-y -i in.mkv -i a.srt -map 0:v:0? -map 0:a:0? -map 1:s:0 -c:s:0 mov_text"
+            ),
+            "{args}"
+        );
+    }
+
+    #[test]
+    fn burns_in_subtitles_after_scaling() {
+        let task = task(MediaFormat::Video(Video::Mkv))
+            .source_codecs(Some(SourceCodecs {
+                video: vec![(0, Some(VideoCodec::H264))],
+                ..Default::default()
+            }))
+            .resolution(Some(Resolution::Height(720)))
+            .subtitle_file(Some(SubtitleFile::new("a.srt", SubtitleMode::BurnIn)));
+        let args = args(task);
+
+        assert!(args.contains("-vf scale=-2:720,subtitles=a.srt"), "{args}");
+        assert!(!args.contains("-c:v copy"), "{args}");
+    }
+
+    #[test]
+    fn writes_a_single_image() {
+        let task = task(MediaFormat::Image(Image::Jpg))
+            .input("in.png")
+            .image_quality(Some(90))
+            .strip_metadata(true);
+
+        assert_eq!(
+            args(task),
+            "-y -i in.png -frames:v 1 -q:v 4 -map_metadata -1 -update 1 -f image2 out.jpg"
+        );
+    }
+
+    #[test]
+    fn limits_icons_to_256_pixels() {
+        let args = args(task(MediaFormat::Image(Image::Ico)).input("in.png"));
+
+        assert!(
+            args.contains("-vf scale='min(256,iw)':'min(256,ih)'"),
+            "{args}"
+        );
+    }
+
+    #[test]
+    fn lossless_images_have_no_quality() {
+        let task = task(MediaFormat::Image(Image::Png)).image_quality(Some(90));
+
+        assert!(task.build().is_err());
+    }
+
+    #[test]
+    fn builds_gif_with_a_palette_pass() {
+        let (output, passes) = task(MediaFormat::Video(Video::Gif)).build().unwrap();
+        assert_eq!(output, Path::new("out.gif"));
+        assert_eq!(passes.len(), 2);
+
+        let palette = &passes[0][passes[0].len() - 1];
+        assert!(palette.ends_with("-palette.png"));
+        assert_eq!(
+            passes[0].join(" "),
+            format!(
+                "-y -i in.mkv -vf fps=15,scale=480:-1:flags=lanczos,palettegen=max_colors=256 -an -update 1 -f image2 {palette}"
+            )
+        );
+        assert_eq!(
+            passes[1].join(" "),
+            format!(
+                "-y -i {palette} -i in.mkv -filter_complex [1:v:0]fps=15,scale=480:-1:flags=lanczos[frames];[frames][0:v]paletteuse=dither=sierra2_4a:diff_mode=rectangle -an -loop 0 -f gif out.gif"
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_two_pass_gif() {
+        let task = task(MediaFormat::Video(Video::Gif)).two_pass(true);
+        assert!(task.build().unwrap_err().contains("two-pass"));
+    }
+
+    #[test]
+    fn resamples_after_loudnorm() {
+        let measured = LoudnessMeasurement::Loudness {
+            integrated: -23.0,
+            true_peak: -3.0,
+            range: 7.0,
+            threshold: -33.0,
+            offset: 0.0,
+        };
+        let normalized = |task: FfmpegTask| {
+            args(
+                task.normalization(Some(Normalization::default()))
+                    .loudness_measurement(Some(measured)),
+            )
+        };
+
+        let default_rate = normalized(task(MediaFormat::Audio(Audio::Flac)));
+        assert!(default_rate.contains(":linear=true,aresample=48000 "));
+        let source_rate =
+            normalized(task(MediaFormat::Audio(Audio::Flac)).source_sample_rate(Some(44100)));
+        assert!(source_rate.contains(":linear=true,aresample=44100 "));
+        let chosen_rate =
+            normalized(task(MediaFormat::Audio(Audio::Flac)).sample_rate(Some(22050)));
+        assert!(chosen_rate.contains("-ar 22050 -af loudnorm="));
+        assert!(!chosen_rate.contains("aresample"));
+    }
+
+    #[test]
+    fn peak_normalization_only_adjusts_volume() {
+        let task = task(MediaFormat::Audio(Audio::Flac))
+            .normalization(Some(Normalization::Peak(-1.0)))
+            .loudness_measurement(Some(LoudnessMeasurement::Peak {
+                max_volume: -6.0,
+                mean_volume: -20.0,
+            }));
+        assert_eq!(
+            args(task),
+            "-y -i in.mkv -vn -sn -af volume=5.00dB -f flac out.flac"
+        );
+    }
+
+    #[test]
+    fn skips_normalization_until_measured() {
+        let normalized = |format| task(format).normalization(Some(Normalization::default()));
+        let flac = normalized(MediaFormat::Audio(Audio::Flac));
+        assert!(flac.normalizes_audio());
+        assert_eq!(args(flac), "-y -i in.mkv -vn -sn -f flac out.flac");
+        assert!(!normalized(MediaFormat::Video(Video::Gif)).normalizes_audio());
+    }
+}
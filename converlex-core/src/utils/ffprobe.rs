@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::models::media_info::{MediaInfo, StreamInfo, StreamKind};
+
+use super::ffmpeg_wrapper::FfmpegEntry;
+
+#[derive(Deserialize, Default)]
+struct RawProbe {
+    #[serde(default)]
+    format: Option<RawFormat>,
+    #[serde(default)]
+    streams: Vec<RawStream>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawFormat {
+    #[serde(default)]
+    format_name: String,
+    format_long_name: Option<String>,
+    duration: Option<String>,
+    bit_rate: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawStream {
+    index: u32,
+    #[serde(default)]
+    codec_type: String,
+    codec_name: Option<String>,
+    codec_long_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    nb_frames: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    bit_rate: Option<String>,
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    disposition: HashMap<String, i64>,
+}
+
+/// 用 ffprobe 探测输入文件
+pub async fn probe(entry: &FfmpegEntry, input: impl AsRef<Path>) -> anyhow::Result<MediaInfo> {
+    let ffprobe = entry.ffprobe_path();
+    let input = input.as_ref();
+
+    let output = Command::new(&ffprobe)
+        .args(["-v", "error", "-print_format", "json", "-show_format", "-show_streams"])
+        .arg(input)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to spawn ffprobe ({}): {}", ffprobe.display(), e))?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "ffprobe exited with status {}\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    parse_probe_json(&output.stdout)
+}
+
+pub fn parse_probe_json(json: &[u8]) -> anyhow::Result<MediaInfo> {
+    let raw: RawProbe = serde_json::from_slice(json)?;
+    let format = raw.format.unwrap_or_default();
+
+    Ok(MediaInfo {
+        container: format.format_name,
+        container_long_name: format.format_long_name,
+        duration: format.duration.as_deref().and_then(parse_number),
+        bit_rate: format.bit_rate.as_deref().and_then(parse_number),
+        streams: raw.streams.into_iter().map(into_stream_info).collect(),
+    })
+}
+
+fn into_stream_info(raw: RawStream) -> StreamInfo {
+    // 部分容器的 avg_frame_rate 是 0/0，退回 r_frame_rate
+    let frame_rate = raw
+        .avg_frame_rate
+        .as_deref()
+        .and_then(parse_ratio)
+        .or_else(|| raw.r_frame_rate.as_deref().and_then(parse_ratio));
+
+    // mkv 等容器只在 tags 里给出 BPS / DURATION
+    let bit_rate = raw
+        .bit_rate
+        .as_deref()
+        .or_else(|| raw.tags.get("BPS").map(String::as_str))
+        .and_then(parse_number);
+
+    StreamInfo {
+        index: raw.index,
+        kind: StreamKind::from_codec_type(&raw.codec_type),
+        codec: raw.codec_name,
+        codec_long_name: raw.codec_long_name,
+        width: raw.width,
+        height: raw.height,
+        frame_rate,
+        frame_count: raw.nb_frames.as_deref().and_then(parse_number),
+        sample_rate: raw.sample_rate.as_deref().and_then(parse_number),
+        channels: raw.channels,
+        language: raw.tags.get("language").cloned(),
+        title: raw.tags.get("title").cloned(),
+        bit_rate,
+        duration: raw.duration.as_deref().and_then(parse_number),
+        is_default: raw.disposition.get("default").is_some_and(|v| *v != 0),
+        is_forced: raw.disposition.get("forced").is_some_and(|v| *v != 0),
+        is_attached_pic: raw.disposition.get("attached_pic").is_some_and(|v| *v != 0),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(s: &str) -> Option<T> {
+    s.trim().parse().ok()
+}
+
+/// "30000/1001" -> 29.97
+fn parse_ratio(s: &str) -> Option<f64> {
+    let (num, den) = s.split_once('/')?;
+    let num: f64 = num.trim().parse().ok()?;
+    let den: f64 = den.trim().parse().ok()?;
+    if den == 0.0 || num == 0.0 {
+        return None;
+    }
+    Some(num / den)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MKV_PROBE: &str = r#"{
+        "streams": [
+            {
+                "index": 0,
+                "codec_name": "h264",
+                "codec_type": "video",
+                "width": 1920,
+                "height": 1080,
+                "avg_frame_rate": "0/0",
+                "r_frame_rate": "30000/1001",
+                "tags": { "BPS": "4500000" },
+                "disposition": { "default": 1, "forced": 0 }
+            },
+            {
+                "index": 1,
+                "codec_name": "aac",
+                "codec_type": "audio",
+                "sample_rate": "48000",
+                "channels": 2,
+                "bit_rate": "128000",
+                "tags": { "language": "jpn", "title": "Commentary" }
+            },
+            {
+                "index": 2,
+                "codec_name": "ass",
+                "codec_type": "subtitle",
+                "disposition": { "default": 0, "forced": 1 }
+            }
+        ],
+        "format": {
+            "format_name": "matroska,webm",
+            "format_long_name": "Matroska / WebM",
+            "duration": "192.533000",
+            "bit_rate": "4700000"
+        }
+    }"#;
+
+    #[test]
+    fn parses_format_and_streams() {
+        let info = parse_probe_json(MKV_PROBE.as_bytes()).unwrap();
+
+        assert_eq!(info.container, "matroska,webm");
+        assert_eq!(info.container_long_name.as_deref(), Some("Matroska / WebM"));
+        assert_eq!(info.duration, Some(192.533));
+        assert_eq!(info.bit_rate, Some(4_700_000));
+        assert_eq!(info.streams.len(), 3);
+
+        let video = &info.streams[0];
+        assert_eq!(video.kind, StreamKind::Video);
+        assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+        assert!(video.is_default);
+
+        let audio = &info.streams[1];
+        assert_eq!(audio.kind, StreamKind::Audio);
+        assert_eq!(audio.sample_rate, Some(48000));
+        assert_eq!(audio.channels, Some(2));
+        assert_eq!(audio.language.as_deref(), Some("jpn"));
+        assert_eq!(audio.title.as_deref(), Some("Commentary"));
+
+        assert!(info.streams[2].is_forced);
+        assert!(!info.streams[2].is_default);
+    }
+
+    #[test]
+    fn falls_back_to_r_frame_rate_and_bps_tag() {
+        let info = parse_probe_json(MKV_PROBE.as_bytes()).unwrap();
+        let video = &info.streams[0];
+
+        let fps = video.frame_rate.unwrap();
+        assert!((fps - 29.97).abs() < 0.01);
+        assert_eq!(video.bit_rate, Some(4_500_000));
+    }
+
+    #[test]
+    fn tolerates_missing_sections() {
+        let info = parse_probe_json(b"{}").unwrap();
+        assert_eq!(info, MediaInfo::default());
+        assert!(parse_probe_json(b"not json").is_err());
+    }
+
+    #[test]
+    fn parses_ratios() {
+        assert_eq!(parse_ratio("25/1"), Some(25.0));
+        assert_eq!(parse_ratio("0/0"), None);
+        assert_eq!(parse_ratio("30"), None);
+    }
+
+    #[test]
+    fn summarizes_the_first_streams() {
+        let info = parse_probe_json(MKV_PROBE.as_bytes()).unwrap();
+        assert_eq!(info.summary(), "h264 1920x1080 · aac 48000Hz · 00:03:12");
+    }
+}
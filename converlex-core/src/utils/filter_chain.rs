@@ -0,0 +1,49 @@
+/// 按顺序拼接 ffmpeg 滤镜，如 `scale=-2:720,fps=30`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterChain {
+    filters: Vec<String>,
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, filter: impl Into<String>) -> &mut Self {
+        self.filters.push(filter.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// 用逗号连接，空链返回 None
+    pub fn join(&self) -> Option<String> {
+        (!self.filters.is_empty()).then(|| self.filters.join(","))
+    }
+
+    /// 生成 `-vf`/`-af` 之类的参数，空链不生成
+    pub fn to_args(&self, option: &str) -> Vec<String> {
+        match self.join() {
+            Some(chain) => vec![option.to_string(), chain],
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_filters_in_order() {
+        let mut chain = FilterChain::new();
+        assert!(chain.is_empty());
+        assert_eq!(chain.to_args("-vf"), Vec::<String>::new());
+
+        chain.push("scale=-2:720").push("fps=30");
+        assert_eq!(chain.join().as_deref(), Some("scale=-2:720,fps=30"));
+        assert_eq!(chain.to_args("-vf"), ["-vf", "scale=-2:720,fps=30"]);
+    }
+}
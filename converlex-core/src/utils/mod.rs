@@ -0,0 +1,10 @@
+pub mod utils;
+pub mod ffmpeg_wrapper;
+pub mod ffmpeg_progress;
+pub mod ffprobe;
+pub mod ffmpeg_caps;
+pub mod task_control;
+pub mod scheduler;
+pub mod filter_chain;
+pub mod ffmpeg_concat;
+pub mod ffmpeg_sequence;
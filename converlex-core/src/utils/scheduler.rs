@@ -0,0 +1,319 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::{Notify, mpsc};
+use tokio::task::{JoinHandle, JoinSet};
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+use crate::models::task::TaskType;
+
+use super::ffmpeg_wrapper::ProgressMsg;
+use super::task_control::{ControlMsg, RunId, TaskControls};
+
+struct Job {
+    task_id: String,
+    run: RunId,
+    task: TaskType,
+    control: mpsc::UnboundedReceiver<ControlMsg>,
+}
+
+/// 有界并发的任务调度器：按入队顺序执行，同时最多运行 `max_jobs` 个 ffmpeg 进程。
+/// 运行过程中可以继续 [`Scheduler::enqueue`]，空出位置后自动开始下一个任务。
+#[derive(Clone)]
+pub struct Scheduler {
+    queue_tx: mpsc::UnboundedSender<Job>,
+    max_jobs: Arc<AtomicUsize>,
+    limit_changed: Arc<Notify>,
+    controls: TaskControls,
+}
+
+#[cfg(feature = "vizia")]
+impl Data for Scheduler {
+    fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.max_jobs, &other.max_jobs)
+    }
+}
+
+impl Scheduler {
+    /// 启动调度循环。所有 `Scheduler` 句柄被 drop 后，
+    /// 循环会跑完剩余任务再结束，返回的 `JoinHandle` 随之完成。
+    pub fn spawn(
+        max_jobs: usize,
+        controls: TaskControls,
+        tx: mpsc::UnboundedSender<ProgressMsg>,
+    ) -> (Self, JoinHandle<()>) {
+        let (queue_tx, queue_rx) = mpsc::unbounded_channel();
+        let max_jobs = Arc::new(AtomicUsize::new(max_jobs.max(1)));
+        let limit_changed = Arc::new(Notify::new());
+
+        let handle = tokio::spawn(dispatch(
+            queue_rx,
+            Arc::clone(&max_jobs),
+            Arc::clone(&limit_changed),
+            controls.clone(),
+            tx,
+        ));
+
+        let scheduler = Self {
+            queue_tx,
+            max_jobs,
+            limit_changed,
+            controls,
+        };
+
+        (scheduler, handle)
+    }
+
+    pub fn enqueue(&self, task_id: String, task: impl Into<TaskType>) -> bool {
+        let (run, control) = self.controls.register(&task_id);
+        let job = Job {
+            task_id,
+            run,
+            task: task.into(),
+            control,
+        };
+
+        match self.queue_tx.send(job) {
+            Ok(_) => true,
+            Err(e) => {
+                self.controls.unregister(&e.0.task_id, e.0.run);
+                false
+            }
+        }
+    }
+
+    pub fn max_jobs(&self) -> usize {
+        self.max_jobs.load(Ordering::SeqCst)
+    }
+
+    /// 调小时不会打断已在运行的任务，只是暂不启动新任务
+    pub fn set_max_jobs(&self, max_jobs: usize) {
+        self.max_jobs.store(max_jobs.max(1), Ordering::SeqCst);
+        self.limit_changed.notify_one();
+    }
+}
+
+async fn dispatch(
+    mut queue_rx: mpsc::UnboundedReceiver<Job>,
+    max_jobs: Arc<AtomicUsize>,
+    limit_changed: Arc<Notify>,
+    controls: TaskControls,
+    tx: mpsc::UnboundedSender<ProgressMsg>,
+) {
+    let mut pending: VecDeque<Job> = VecDeque::new();
+    let mut running: JoinSet<()> = JoinSet::new();
+    let mut queue_open = true;
+
+    loop {
+        while running.len() < max_jobs.load(Ordering::SeqCst) {
+            let Some(job) = pending.pop_front() else {
+                break;
+            };
+            start_job(job, &mut running, &controls, &tx);
+        }
+
+        if !queue_open && pending.is_empty() && running.is_empty() {
+            break;
+        }
+
+        tokio::select! {
+            job = queue_rx.recv(), if queue_open => match job {
+                Some(job) => pending.push_back(job),
+                None => queue_open = false,
+            },
+            Some(_) = running.join_next(), if !running.is_empty() => {}
+            _ = limit_changed.notified() => {}
+        }
+    }
+}
+
+fn start_job(
+    mut job: Job,
+    running: &mut JoinSet<()>,
+    controls: &TaskControls,
+    tx: &mpsc::UnboundedSender<ProgressMsg>,
+) {
+    // 排队期间被取消的任务直接跳过；暂停/恢复对未开始的任务没有意义
+    let mut cancelled = false;
+    while let Ok(msg) = job.control.try_recv() {
+        if msg == ControlMsg::Cancel {
+            cancelled = true;
+        }
+    }
+
+    if cancelled {
+        // 取消后已经重试的任务由新的那次汇报状态
+        if controls.unregister(&job.task_id, job.run) {
+            let _ = tx.send(ProgressMsg::Cancelled {
+                task_id: job.task_id,
+            });
+        }
+        return;
+    }
+
+    let _ = tx.send(ProgressMsg::Started {
+        task_id: job.task_id.clone(),
+    });
+
+    let controls = controls.clone();
+    let tx = tx.clone();
+    running.spawn(async move {
+        job.task
+            .run_with_progress(job.task_id.clone(), tx, job.control)
+            .await;
+        controls.unregister(&job.task_id, job.run);
+    });
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicU32;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::models::media_format::{Audio, MediaFormat};
+    use crate::utils::ffmpeg_wrapper::{FfmpegEntry, FfmpegTask, run_batch};
+
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("converlex-scheduler-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// 忽略参数、睡 `secs` 秒后正常退出的假 ffmpeg
+    fn fake_ffmpeg(secs: u32) -> FfmpegEntry {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let path = test_dir().join(format!("ffmpeg-{}", NEXT.fetch_add(1, Ordering::SeqCst)));
+        std::fs::write(&path, format!("#!/bin/sh\nexec sleep {secs}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        FfmpegEntry::Path(path)
+    }
+
+    fn job(entry: FfmpegEntry, name: &str) -> FfmpegTask {
+        FfmpegTask::new(entry, Arc::new(MediaFormat::Audio(Audio::Mp3)))
+            .input(format!("{name}.wav"))
+            .output(
+                test_dir()
+                    .join(format!("{name}.mp3"))
+                    .to_string_lossy()
+                    .into_owned(),
+            )
+    }
+
+    /// 只留下开始和结束的消息，如 "started a"、"done a"
+    fn lifecycle(rx: &mut mpsc::UnboundedReceiver<ProgressMsg>) -> Vec<String> {
+        let mut events = vec![];
+        while let Ok(msg) = rx.try_recv() {
+            events.extend(match msg {
+                ProgressMsg::Started { task_id } => Some(format!("started {task_id}")),
+                ProgressMsg::Done { task_id } => Some(format!("done {task_id}")),
+                ProgressMsg::Cancelled { task_id } => Some(format!("cancelled {task_id}")),
+                ProgressMsg::Error { task_id, .. } => Some(format!("error {task_id}")),
+                _ => None,
+            });
+        }
+        events
+    }
+
+    async fn next_lifecycle(rx: &mut mpsc::UnboundedReceiver<ProgressMsg>) -> String {
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+                .await
+                .expect("timed out waiting for the scheduler")
+                .expect("scheduler stopped");
+            let (event, task_id) = match msg {
+                ProgressMsg::Started { task_id } => ("started", task_id),
+                ProgressMsg::Done { task_id } => ("done", task_id),
+                ProgressMsg::Cancelled { task_id } => ("cancelled", task_id),
+                ProgressMsg::Error { task_id, .. } => ("error", task_id),
+                _ => continue,
+            };
+            return format!("{event} {task_id}");
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_jobs_one_at_a_time_in_order() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let tasks = ["a", "b", "c"]
+            .map(|name| (name.to_string(), job(fake_ffmpeg(0), name)))
+            .to_vec();
+        run_batch(tasks, 1, TaskControls::new(), tx).await.unwrap();
+
+        assert_eq!(
+            lifecycle(&mut rx),
+            [
+                "started a",
+                "done a",
+                "started b",
+                "done b",
+                "started c",
+                "done c"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn runs_up_to_max_jobs_at_once() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let tasks = ["a", "b", "c"]
+            .map(|name| (name.to_string(), job(fake_ffmpeg(1), name)))
+            .to_vec();
+        run_batch(tasks, 2, TaskControls::new(), tx).await.unwrap();
+
+        let events = lifecycle(&mut rx);
+        assert_eq!(events[..2], ["started a", "started b"]);
+        assert_eq!(events.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn reports_jobs_that_fail_to_start() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let missing = FfmpegEntry::Path(test_dir().join("missing-ffmpeg"));
+        let tasks = vec![("a".to_string(), job(missing, "a"))];
+        run_batch(tasks, 1, TaskControls::new(), tx).await.unwrap();
+
+        assert_eq!(lifecycle(&mut rx), ["started a", "error a"]);
+    }
+
+    #[tokio::test]
+    async fn cancels_a_running_job() {
+        let controls = TaskControls::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (scheduler, handle) = Scheduler::spawn(1, controls.clone(), tx);
+        scheduler.enqueue("a".into(), job(fake_ffmpeg(30), "a"));
+        drop(scheduler);
+
+        assert_eq!(next_lifecycle(&mut rx).await, "started a");
+        assert!(controls.send("a", ControlMsg::Cancel));
+        assert_eq!(next_lifecycle(&mut rx).await, "cancelled a");
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn retrying_a_cancelled_pending_job_keeps_its_control_channel() {
+        let controls = TaskControls::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (scheduler, handle) = Scheduler::spawn(1, controls.clone(), tx);
+        scheduler.enqueue("a".into(), job(fake_ffmpeg(30), "a"));
+        scheduler.enqueue("b".into(), job(fake_ffmpeg(30), "b"));
+        assert_eq!(next_lifecycle(&mut rx).await, "started a");
+
+        // b 还在排队时取消再重试
+        assert!(controls.send("b", ControlMsg::Cancel));
+        scheduler.enqueue("b".into(), job(fake_ffmpeg(30), "b"));
+        drop(scheduler);
+
+        assert!(controls.send("a", ControlMsg::Cancel));
+        assert_eq!(next_lifecycle(&mut rx).await, "cancelled a");
+        // 旧的 b 被跳过且不发 Cancelled，重试的 b 照常运行并能被控制
+        assert_eq!(next_lifecycle(&mut rx).await, "started b");
+        assert!(controls.send("b", ControlMsg::Cancel));
+        assert_eq!(next_lifecycle(&mut rx).await, "cancelled b");
+        handle.await.unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::process::Child;
+use tokio::sync::mpsc;
+#[cfg(feature = "vizia")]
+use vizia::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControlMsg {
+    Cancel,
+    Pause,
+    Resume,
+}
+
+/// 每次入队的编号。同一个任务取消后可以重试，旧的那次结束时不能注销新的控制通道
+pub type RunId = u64;
+
+/// 某次运行的编号和它的控制通道
+type RunSender = (RunId, mpsc::UnboundedSender<ControlMsg>);
+
+/// 正在运行的任务的控制通道，按任务 id 索引
+#[derive(Clone, Default)]
+pub struct TaskControls {
+    senders: Arc<Mutex<HashMap<String, RunSender>>>,
+    next_run: Arc<AtomicU64>,
+}
+
+#[cfg(feature = "vizia")]
+impl Data for TaskControls {
+    fn same(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.senders, &other.senders)
+    }
+}
+
+impl TaskControls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 替换同一任务之前的控制通道，返回这次运行的编号
+    pub fn register(&self, task_id: &str) -> (RunId, mpsc::UnboundedReceiver<ControlMsg>) {
+        let run = self.next_run.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.senders
+            .lock()
+            .unwrap()
+            .insert(task_id.to_string(), (run, tx));
+        (run, rx)
+    }
+
+    /// 只注销 `run` 自己的通道，任务已经重新入队时保留新的并返回 false
+    pub fn unregister(&self, task_id: &str, run: RunId) -> bool {
+        let mut senders = self.senders.lock().unwrap();
+        let current = senders.get(task_id).is_some_and(|(current, _)| *current == run);
+        if current {
+            senders.remove(task_id);
+        }
+        current
+    }
+
+    /// 任务不在运行时返回 false
+    pub fn send(&self, task_id: &str, msg: ControlMsg) -> bool {
+        match self.senders.lock().unwrap().get(task_id) {
+            Some((_, tx)) => tx.send(msg).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn send_all(&self, msg: ControlMsg) {
+        for (_, tx) in self.senders.lock().unwrap().values() {
+            let _ = tx.send(msg);
+        }
+    }
+}
+
+/// 挂起进程（SIGSTOP）
+#[cfg(unix)]
+pub fn suspend(child: &Child) -> anyhow::Result<()> {
+    send_signal(child, libc::SIGSTOP)
+}
+
+/// 恢复进程（SIGCONT）
+#[cfg(unix)]
+pub fn resume(child: &Child) -> anyhow::Result<()> {
+    send_signal(child, libc::SIGCONT)
+}
+
+#[cfg(unix)]
+fn send_signal(child: &Child, signal: libc::c_int) -> anyhow::Result<()> {
+    let pid = child
+        .id()
+        .ok_or_else(|| anyhow::anyhow!("Process has already exited"))?;
+
+    // SAFETY: pid 来自仍由我们持有的子进程
+    let ret = unsafe { libc::kill(pid as libc::pid_t, signal) };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// 挂起进程的所有线程（NtSuspendProcess）
+#[cfg(windows)]
+pub fn suspend(child: &Child) -> anyhow::Result<()> {
+    // SAFETY: 句柄来自仍由我们持有的子进程
+    nt_process_call(child, |handle| unsafe { NtSuspendProcess(handle) })
+}
+
+/// 恢复进程（NtResumeProcess）
+#[cfg(windows)]
+pub fn resume(child: &Child) -> anyhow::Result<()> {
+    // SAFETY: 同上
+    nt_process_call(child, |handle| unsafe { NtResumeProcess(handle) })
+}
+
+// Windows 没有 SIGSTOP，ntdll 的这两个函数没有文档但从 XP 起一直可用
+#[cfg(windows)]
+#[link(name = "ntdll")]
+unsafe extern "system" {
+    fn NtSuspendProcess(process: std::os::windows::io::RawHandle) -> i32;
+    fn NtResumeProcess(process: std::os::windows::io::RawHandle) -> i32;
+}
+
+#[cfg(windows)]
+fn nt_process_call(
+    child: &Child,
+    call: impl FnOnce(std::os::windows::io::RawHandle) -> i32,
+) -> anyhow::Result<()> {
+    let handle = child
+        .raw_handle()
+        .ok_or_else(|| anyhow::anyhow!("Process has already exited"))?;
+
+    // 返回 NTSTATUS，负数表示失败
+    let status = call(handle);
+    if status < 0 {
+        return Err(anyhow::anyhow!("NTSTATUS {:#010x}", status as u32));
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn suspend(_child: &Child) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("Pausing isn't supported on this platform"))
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn resume(_child: &Child) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("Resuming isn't supported on this platform"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_to_registered_tasks_only() {
+        let controls = TaskControls::new();
+        let (_, mut rx) = controls.register("a");
+
+        assert!(controls.send("a", ControlMsg::Pause));
+        assert!(!controls.send("b", ControlMsg::Pause));
+        assert_eq!(rx.try_recv().ok(), Some(ControlMsg::Pause));
+    }
+
+    #[test]
+    fn send_all_reaches_every_task() {
+        let controls = TaskControls::new();
+        let (_, mut a) = controls.register("a");
+        let (_, mut b) = controls.register("b");
+
+        controls.send_all(ControlMsg::Cancel);
+        assert_eq!(a.try_recv().ok(), Some(ControlMsg::Cancel));
+        assert_eq!(b.try_recv().ok(), Some(ControlMsg::Cancel));
+    }
+
+    #[test]
+    fn unregistered_tasks_are_unreachable() {
+        let controls = TaskControls::new();
+        let (run, _rx) = controls.register("a");
+
+        assert!(controls.unregister("a", run));
+        assert!(!controls.send("a", ControlMsg::Cancel));
+    }
+
+    /// /proc/<pid>/stat 的第三项，T 表示已停止
+    #[cfg(target_os = "linux")]
+    fn process_state(child: &Child) -> char {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", child.id().unwrap())).unwrap();
+        let (_, rest) = stat.rsplit_once(')').unwrap();
+        rest.trim_start().chars().next().unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn suspends_and_resumes_a_process() {
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("5")
+            .spawn()
+            .unwrap();
+
+        suspend(&child).unwrap();
+        // 信号是异步送达的
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(process_state(&child), 'T');
+
+        resume(&child).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_ne!(process_state(&child), 'T');
+
+        child.kill().await.unwrap();
+    }
+}
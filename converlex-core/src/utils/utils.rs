@@ -0,0 +1,241 @@
+use std::{io::{BufRead, BufReader}, path::Path, process::{Command, Stdio}};
+
+use crate::models::convertible_format::ConvertibleFormat;
+
+
+
+pub fn convert_media(input: &str, output: &str) -> Result<(), String> {
+    let ffmpeg_path = "./ffmpeg.exe"; // 确保可执行文件打包进程序目录
+    if !Path::new(ffmpeg_path).exists() {
+        return Err("找不到 ffmpeg.exe".to_string());
+    }
+
+    let status = Command::new(ffmpeg_path)
+        .args(["-i", input, output])
+        .status()
+        .map_err(|e| format!("启动失败: {}", e))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err("ffmpeg 转换失败".into())
+    }
+}
+
+
+pub fn convert_media_with_progress<F>(input: &str, output: &str, mut on_progress: F) -> Result<(), String>
+where
+    F: FnMut(f32),
+{
+    let ffmpeg_path = "./ffmpeg.exe";
+
+    if !Path::new(ffmpeg_path).exists() {
+        return Err("找不到 ffmpeg.exe".to_string());
+    }
+
+    // 第一步：先获取总时长
+    let duration = get_media_duration(input)?;
+    if duration == 0.0 {
+        return Err("无法解析媒体时长".to_string());
+    }
+
+    let mut child = Command::new(ffmpeg_path)
+        .args(["-i", input, "-y", output])
+        .stderr(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("无法启动 ffmpeg：{}", e))?;
+
+    if let Some(stderr) = child.stderr.take() {
+        let reader = BufReader::new(stderr);
+        for line in reader.lines() {
+            if let Ok(line) = line {
+                if let Some(time_str) = line.split("time=").nth(1) {
+                    if let Some(time_part) = time_str.split_whitespace().next() {
+                        if let Ok(seconds) = parse_ffmpeg_time(time_part) {
+                            let progress = seconds / duration;
+                            on_progress(progress.clamp(0.0, 1.0));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let status = child.wait().map_err(|e| format!("等待进程失败：{}", e))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err("转换失败".into())
+    }
+}
+
+
+fn parse_ffmpeg_time(time_str: &str) -> Result<f32, std::num::ParseFloatError> {
+    let parts: Vec<&str> = time_str.split(':').collect();
+    if parts.len() != 3 {
+        return Ok(0.0); // 默认错误处理
+    }
+    let hours: f32 = parts[0].parse()?;
+    let minutes: f32 = parts[1].parse()?;
+    let seconds: f32 = parts[2].parse()?;
+    Ok(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+fn get_media_duration(input: &str) -> Result<f32, String> {
+    let output = Command::new("./ffmpeg.exe")
+        .args(["-i", input])
+        .stderr(Stdio::piped())
+        .output()
+        .map_err(|e| format!("获取时长失败：{}", e))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for line in stderr.lines() {
+        if line.contains("Duration:") {
+            if let Some(dur_str) = line.split("Duration: ").nth(1) {
+                if let Some(time_str) = dur_str.split(',').next() {
+                    return parse_ffmpeg_time(time_str).map_err(|e| e.to_string());
+                }
+            }
+        }
+    }
+
+    Err("未找到媒体时长".into())
+}
+
+
+pub fn get_output_path(input_path: &str, new_format: &dyn ConvertibleFormat, overwrite: bool) -> String {
+    let parent = Path::new(input_path).parent().unwrap_or_else(|| Path::new(""));
+    get_output_path_in(input_path, parent, new_format, overwrite)
+}
+
+/// 同 [`get_output_path`]，但输出到指定目录
+pub fn get_output_path_in(
+    input_path: &str,
+    output_dir: &Path,
+    new_format: &dyn ConvertibleFormat,
+    overwrite: bool,
+) -> String {
+    tagged_output_path(input_path, output_dir, "converted", new_format, overwrite)
+}
+
+/// 同 [`get_output_path`]，文件名里用 `tag` 代替 "converted"，用于区分同一输入的多个输出
+pub fn get_tagged_output_path(
+    input_path: &str,
+    tag: &str,
+    new_format: &dyn ConvertibleFormat,
+    overwrite: bool,
+) -> String {
+    let parent = Path::new(input_path).parent().unwrap_or_else(|| Path::new(""));
+    tagged_output_path(input_path, parent, tag, new_format, overwrite)
+}
+
+fn tagged_output_path(
+    input_path: &str,
+    output_dir: &Path,
+    tag: &str,
+    new_format: &dyn ConvertibleFormat,
+    overwrite: bool,
+) -> String {
+    let path = Path::new(input_path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let mut output_path = output_dir.join(format!("{}_{}.{}", stem, tag, new_format.get_ext()));
+    let mut count = 1;
+
+    if !overwrite {
+        while output_path.exists() {
+            output_path = output_dir.join(format!("{}_{}_{}.{}", stem, tag, count, new_format.get_ext()));
+            count += 1;
+        }
+    }
+
+    output_path.to_string_lossy().to_string()
+}
+
+/// 输入旁边的输出文件夹，如 `clip_frames`，不覆盖时已存在就加序号
+pub fn get_output_dir(input_path: &str, tag: &str, overwrite: bool) -> String {
+    let path = Path::new(input_path);
+    let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let mut output_dir = parent.join(format!("{}_{}", stem, tag));
+    let mut count = 1;
+
+    if !overwrite {
+        while output_dir.exists() {
+            output_dir = parent.join(format!("{}_{}_{}", stem, tag, count));
+            count += 1;
+        }
+    }
+
+    output_dir.to_string_lossy().to_string()
+}
+
+/// 把流的语言、标题等文本变成能放进文件名的片段，去掉路径分隔符等特殊字符
+pub fn file_name_part(text: &str) -> String {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '-'))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+
+/// 秒数格式化为 hh:mm:ss
+pub fn format_timestamp(secs: f64) -> String {
+    let total = secs.max(0.0) as u64;
+    format!("{:02}:{:02}:{:02}", total / 3600, total % 3600 / 60, total % 60)
+}
+
+/// 如 "3.2 MB"、"850 KB"
+pub fn format_size(bytes: u64) -> String {
+    let kb = bytes as f64 / 1024.0;
+    if kb < 1024.0 {
+        format!("{kb:.0} KB")
+    } else {
+        format!("{:.1} MB", kb / 1024.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::media_format::{Audio, MediaFormat};
+
+    #[test]
+    fn tags_output_names() {
+        let m4a = MediaFormat::Audio(Audio::Alac);
+
+        assert_eq!(
+            get_tagged_output_path("/nonexistent/movie.mkv", "audio2_eng", &m4a, false),
+            "/nonexistent/movie_audio2_eng.m4a"
+        );
+        assert_eq!(
+            get_output_path("/nonexistent/movie.mkv", &m4a, false),
+            "/nonexistent/movie_converted.m4a"
+        );
+    }
+
+    #[test]
+    fn numbers_outputs_that_already_exist() {
+        let dir = std::env::temp_dir().join(format!("converlex-utils-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("clip.wav");
+        std::fs::write(dir.join("clip_audio1.mp3"), b"").unwrap();
+        let input = input.to_string_lossy();
+        let mp3 = MediaFormat::Audio(Audio::Mp3);
+
+        let numbered = get_tagged_output_path(&input, "audio1", &mp3, false);
+        assert_eq!(Path::new(&numbered), dir.join("clip_audio1_1.mp3"));
+        let overwritten = get_tagged_output_path(&input, "audio1", &mp3, true);
+        assert_eq!(Path::new(&overwritten), dir.join("clip_audio1.mp3"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn strips_unsafe_characters_from_name_parts() {
+        assert_eq!(file_name_part("Director's Cut / 5.1"), "Director-s-Cut-5-1");
+        assert_eq!(file_name_part("日本語"), "日本語");
+        assert_eq!(file_name_part("../"), "");
+    }
+}
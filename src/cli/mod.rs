@@ -0,0 +1,754 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use clap::Parser;
+use log::LevelFilter;
+use tokio::sync::mpsc;
+
+use crate::{
+    models::{
+        app_settings::AppSettings,
+        codec::{AudioCodec, VideoCodec},
+        convertible_format::ConvertibleFormat,
+        gif::{GifDither, GifOptions},
+        loudness::{LoudnessTarget, Normalization},
+        media_format::MediaFormat,
+        output_params::{self, Resolution},
+        rate_control::{AudioRateControl, VideoRateControl},
+        stream_copy::SourceCodecs,
+        stream_map::StreamSelection,
+        subtitle::{SubtitleFile, SubtitleMode},
+        trim::{self, SeekMode, Trim},
+    },
+    utils::{
+        ffmpeg_caps::FfmpegCaps,
+        ffmpeg_wrapper::{self, FfmpegEntry, FfmpegTask, ProgressMsg},
+        ffprobe, logger,
+        task_control::{ControlMsg, TaskControls},
+        utils::{format_timestamp, get_output_path_in},
+    },
+};
+
+/// 全部成功
+pub const EXIT_OK: i32 = 0;
+/// 有任务失败或被取消
+pub const EXIT_TASK_FAILED: i32 = 1;
+/// 参数错误或没有可转换的输入
+pub const EXIT_USAGE: i32 = 2;
+/// 被 Ctrl-C 中断
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// 无界面模式，使用与 GUI 相同的调度和 ffmpeg 封装
+#[derive(Parser, Debug)]
+#[command(name = "converlex", version, about = "Convert media files without the GUI")]
+pub struct CliArgs {
+    /// 以无界面模式运行（由 main 识别）
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Input files or glob patterns (e.g. "videos/*.mkv")
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+
+    /// Target format extension, e.g. mp4, mp3, flac
+    #[arg(short, long)]
+    pub format: String,
+
+    /// Output directory, defaults to each input's directory
+    #[arg(short, long)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Video codec, e.g. h264, hevc, av1, vp9 (default: container default)
+    #[arg(long, value_parser = parse_video_codec)]
+    pub video_codec: Option<VideoCodec>,
+
+    /// Audio codec, e.g. aac, mp3, opus, flac (default: container default)
+    #[arg(long, value_parser = parse_audio_codec)]
+    pub audio_codec: Option<AudioCodec>,
+
+    /// Video bitrate in kbps
+    #[arg(long, group = "video_rate")]
+    pub video_bitrate: Option<u32>,
+
+    /// Constant video quality (CRF/CQ), range depends on the codec
+    #[arg(long, group = "video_rate")]
+    pub video_quality: Option<u32>,
+
+    /// Target output size in MB, the video bitrate is derived from the input duration
+    #[arg(long, group = "video_rate")]
+    pub target_size: Option<u32>,
+
+    /// Encode video in two passes (needs --video-bitrate or --target-size)
+    #[arg(long)]
+    pub two_pass: bool,
+
+    /// Audio bitrate in kbps
+    #[arg(long, group = "audio_rate")]
+    pub audio_bitrate: Option<u32>,
+
+    /// VBR audio quality level (-q:a), range depends on the codec
+    #[arg(long, group = "audio_rate")]
+    pub audio_quality: Option<u32>,
+
+    /// Output resolution: a height like 720p (keeps aspect ratio) or WIDTHxHEIGHT
+    #[arg(long, value_parser = Resolution::parse)]
+    pub resolution: Option<Resolution>,
+
+    /// Output frame rate
+    #[arg(long, value_parser = parse_frame_rate)]
+    pub fps: Option<u32>,
+
+    /// Output audio sample rate in Hz
+    #[arg(long, value_parser = parse_sample_rate)]
+    pub sample_rate: Option<u32>,
+
+    /// Start of the clip to convert, e.g. 00:01:30.5
+    #[arg(long, value_parser = trim::parse_timestamp)]
+    pub start: Option<f64>,
+
+    /// End of the clip, measured in the input's time
+    #[arg(long, value_parser = trim::parse_timestamp, conflicts_with = "duration")]
+    pub end: Option<f64>,
+
+    /// Length of the clip
+    #[arg(long, value_parser = trim::parse_timestamp)]
+    pub duration: Option<f64>,
+
+    /// Seek to the nearest keyframe before --start instead of decoding up to it
+    #[arg(long)]
+    pub fast_seek: bool,
+
+    /// Always re-encode, even when the streams already fit the output format
+    #[arg(long)]
+    pub no_copy: bool,
+
+    /// Keep every video, audio and subtitle stream instead of ffmpeg's one-per-type pick
+    #[arg(long)]
+    pub all_streams: bool,
+
+    /// Quality of lossy image outputs (jpg, webp, avif), 1-100
+    #[arg(long, value_parser = parse_image_quality)]
+    pub image_quality: Option<u32>,
+
+    /// Drop metadata such as EXIF and titles from the output
+    #[arg(long)]
+    pub strip_metadata: bool,
+
+    /// GIF output frame rate
+    #[arg(long)]
+    pub gif_fps: Option<u32>,
+
+    /// GIF output width in pixels, the height follows the aspect ratio [default: 480]
+    #[arg(long)]
+    pub gif_width: Option<u32>,
+
+    /// Keep the input size instead of scaling the GIF to --gif-width
+    #[arg(long, conflicts_with = "gif_width")]
+    pub gif_original_size: bool,
+
+    /// How many extra times the GIF plays after the first, 0 plays it once [default: forever]
+    #[arg(long)]
+    pub gif_repeat: Option<u32>,
+
+    /// GIF dithering: none, bayer, floyd_steinberg, sierra2, sierra2_4a
+    #[arg(long, value_parser = parse_gif_dither)]
+    pub gif_dither: Option<GifDither>,
+
+    /// Number of colors in the GIF palette, 2-256
+    #[arg(long)]
+    pub gif_colors: Option<u32>,
+
+    /// Normalize the audio to an EBU R128 loudness target ("loudness") or a peak level ("peak")
+    #[arg(long, value_parser = parse_normalization)]
+    pub normalize: Option<Normalization>,
+
+    /// Target integrated loudness for --normalize loudness, in LUFS [default: -16]
+    #[arg(long, allow_negative_numbers = true, requires = "normalize")]
+    pub target_lufs: Option<f64>,
+
+    /// Target true peak for --normalize loudness, in dBTP [default: -1.5]
+    #[arg(long, allow_negative_numbers = true, requires = "normalize")]
+    pub target_tp: Option<f64>,
+
+    /// Target loudness range for --normalize loudness, in LU [default: 11]
+    #[arg(long, requires = "normalize")]
+    pub target_lra: Option<f64>,
+
+    /// Target peak level for --normalize peak, in dBFS [default: -1]
+    #[arg(long, allow_negative_numbers = true, requires = "normalize")]
+    pub target_peak: Option<f64>,
+
+    /// Subtitle file (srt, ass, vtt, sub) to add to video outputs as a subtitle track
+    #[arg(long)]
+    pub subtitle: Option<PathBuf>,
+
+    /// Burn the --subtitle file into the picture instead of adding a track
+    #[arg(long, requires = "subtitle")]
+    pub burn_subtitle: bool,
+
+    /// Subtitle track to extract when converting to a subtitle format, starting at 1
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub subtitle_track: Option<u32>,
+
+    /// Maximum number of parallel ffmpeg processes
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+
+    /// Overwrite existing output files instead of picking a new name
+    #[arg(long)]
+    pub overwrite: bool,
+
+    /// Path to the ffmpeg executable
+    #[arg(long)]
+    pub ffmpeg: Option<PathBuf>,
+
+    /// Also log each ffmpeg command and pass, not just warnings and errors
+    #[arg(short, long)]
+    pub verbose: bool,
+}
+
+impl CliArgs {
+    fn video_rate(&self) -> Option<VideoRateControl> {
+        self.video_bitrate
+            .map(VideoRateControl::Bitrate)
+            .or(self.video_quality.map(VideoRateControl::Quality))
+            .or(self.target_size.map(VideoRateControl::TargetSize))
+    }
+
+    fn audio_rate(&self) -> Option<AudioRateControl> {
+        self.audio_bitrate
+            .map(AudioRateControl::Bitrate)
+            .or(self.audio_quality.map(AudioRateControl::Quality))
+    }
+
+    fn subtitle_file(&self) -> Option<SubtitleFile> {
+        let mode = if self.burn_subtitle {
+            SubtitleMode::BurnIn
+        } else {
+            SubtitleMode::Embed
+        };
+        self.subtitle.as_ref().map(|path| SubtitleFile::new(path, mode))
+    }
+
+    fn gif(&self) -> GifOptions {
+        let defaults = GifOptions::default();
+        GifOptions {
+            fps: self.gif_fps.unwrap_or(defaults.fps),
+            width: if self.gif_original_size {
+                None
+            } else {
+                self.gif_width.or(defaults.width)
+            },
+            repeat: self.gif_repeat,
+            dither: self.gif_dither.unwrap_or(defaults.dither),
+            max_colors: self.gif_colors.unwrap_or(defaults.max_colors),
+        }
+    }
+
+    fn normalization(&self) -> Option<Normalization> {
+        self.normalize.map(|normalization| match normalization {
+            Normalization::Loudness(target) => Normalization::Loudness(LoudnessTarget {
+                integrated: self.target_lufs.unwrap_or(target.integrated),
+                true_peak: self.target_tp.unwrap_or(target.true_peak),
+                range: self.target_lra.unwrap_or(target.range),
+            }),
+            Normalization::Peak(peak) => Normalization::Peak(self.target_peak.unwrap_or(peak)),
+        })
+    }
+
+    fn trim(&self) -> Trim {
+        Trim {
+            start: self.start,
+            end: self.end,
+            duration: self.duration,
+            seek: if self.fast_seek {
+                SeekMode::Fast
+            } else {
+                SeekMode::Accurate
+            },
+        }
+    }
+}
+
+fn parse_frame_rate(s: &str) -> Result<u32, String> {
+    let fps = s.parse().map_err(|_| format!("invalid frame rate \"{s}\""))?;
+    output_params::validate_frame_rate(fps)?;
+    Ok(fps)
+}
+
+fn parse_sample_rate(s: &str) -> Result<u32, String> {
+    let rate = s.parse().map_err(|_| format!("invalid sample rate \"{s}\""))?;
+    output_params::validate_sample_rate(rate)?;
+    Ok(rate)
+}
+
+fn parse_image_quality(s: &str) -> Result<u32, String> {
+    let quality = s.parse().map_err(|_| format!("invalid image quality \"{s}\""))?;
+    output_params::validate_image_quality(quality)?;
+    Ok(quality)
+}
+
+fn parse_gif_dither(s: &str) -> Result<GifDither, String> {
+    GifDither::from_name(s).ok_or_else(|| {
+        let names: Vec<&str> = GifDither::ALL.iter().map(|d| d.name()).collect();
+        format!("unknown dither \"{s}\", expected one of: {}", names.join(", "))
+    })
+}
+
+fn parse_normalization(s: &str) -> Result<Normalization, String> {
+    match s {
+        "loudness" => Ok(Normalization::default()),
+        "peak" => Ok(Normalization::Peak(Normalization::DEFAULT_PEAK)),
+        _ => Err(format!(
+            "unknown normalization \"{s}\", expected loudness or peak"
+        )),
+    }
+}
+
+fn parse_video_codec(s: &str) -> Result<VideoCodec, String> {
+    VideoCodec::from_id(s).ok_or_else(|| {
+        let ids: Vec<&str> = VideoCodec::ALL.iter().map(|c| c.id()).collect();
+        format!("unknown video codec \"{s}\", expected one of: {}", ids.join(", "))
+    })
+}
+
+fn parse_audio_codec(s: &str) -> Result<AudioCodec, String> {
+    AudioCodec::from_id(s).ok_or_else(|| {
+        let ids: Vec<&str> = AudioCodec::ALL.iter().map(|c| c.id()).collect();
+        format!("unknown audio codec \"{s}\", expected one of: {}", ids.join(", "))
+    })
+}
+
+/// 命令行中是否要求无界面模式
+pub fn is_headless() -> bool {
+    std::env::args().skip(1).any(|arg| arg == "--headless")
+}
+
+/// 解析命令行并跑完所有任务，返回进程退出码
+pub async fn run() -> i32 {
+    let args = CliArgs::parse();
+    // 日志和进度行都在 stderr，stdout 不输出任何内容
+    let level = match args.verbose {
+        true => LevelFilter::Info,
+        false => LevelFilter::Warn,
+    };
+    logger::init(level, true);
+
+    let Some(format) = MediaFormat::new(&args.format) else {
+        eprintln!("❌ Unsupported target format: {}", args.format);
+        return EXIT_USAGE;
+    };
+
+    if let Err(reason) = format
+        .get_format_type()
+        .check_codecs(args.video_codec, args.audio_codec)
+    {
+        eprintln!("❌ Cannot convert to {}: {reason}", args.format);
+        return EXIT_USAGE;
+    }
+
+    // 和输入时长有关的检查留到每个文件探测之后
+    if let Err(reason) = args.trim().validate(None) {
+        eprintln!("❌ Invalid trim: {reason}");
+        return EXIT_USAGE;
+    }
+    if let Err(reason) = args.gif().validate() {
+        eprintln!("❌ Invalid GIF options: {reason}");
+        return EXIT_USAGE;
+    }
+    if let Some(Err(reason)) = args.normalization().map(|n| n.validate()) {
+        eprintln!("❌ Invalid normalization: {reason}");
+        return EXIT_USAGE;
+    }
+
+    if let Some(dir) = &args.output_dir
+        && let Err(e) = std::fs::create_dir_all(dir)
+    {
+        eprintln!("❌ Failed to create output directory {}: {e}", dir.display());
+        return EXIT_USAGE;
+    }
+
+    let inputs = expand_inputs(&args.inputs);
+    if inputs.is_empty() {
+        eprintln!("❌ No input files matched");
+        return EXIT_USAGE;
+    }
+
+    let settings = AppSettings::load().ok().flatten();
+    let ffmpeg_entry = match (&args.ffmpeg, settings.as_ref().and_then(|s| s.ffmpeg_entry.clone())) {
+        (Some(path), _) => FfmpegEntry::Path(path.clone()),
+        (None, Some(entry)) => entry,
+        (None, None) => ffmpeg_wrapper::find_ffmpeg().await.unwrap_or(FfmpegEntry::Env),
+    };
+
+    match FfmpegCaps::get_or_probe(&ffmpeg_entry).await {
+        Ok(caps) => {
+            if let Err(reason) = caps
+                .check_format(&format)
+                .and_then(|_| caps.check_codecs(args.video_codec, args.audio_codec))
+            {
+                eprintln!("❌ Cannot convert to {}: {reason}", args.format);
+                return EXIT_USAGE;
+            }
+        }
+        Err(e) => eprintln!("⚠ Could not probe ffmpeg capabilities: {e}"),
+    }
+
+    let max_jobs = args
+        .jobs
+        .or(settings.as_ref().map(|s| s.max_parallel_jobs))
+        .unwrap_or_else(crate::models::app_settings::default_max_parallel_jobs);
+
+    let output_format: Arc<dyn ConvertibleFormat> = Arc::new(format);
+    let mut claimed_outputs: HashSet<String> = HashSet::new();
+    let mut tasks: Vec<(String, FfmpegTask)> = vec![];
+    let mut names: HashMap<String, String> = HashMap::new();
+    let mut skipped = 0;
+
+    for input in inputs {
+        let input_path = input.to_string_lossy().to_string();
+        let output_dir = match &args.output_dir {
+            Some(dir) => dir.clone(),
+            None => input.parent().map(Path::to_path_buf).unwrap_or_default(),
+        };
+        let output_path =
+            get_output_path_in(&input_path, &output_dir, output_format.as_any(), args.overwrite);
+
+        if output_path == input_path || !claimed_outputs.insert(output_path.clone()) {
+            eprintln!("⚠ Skipping {input_path}: output path {output_path} is already taken");
+            skipped += 1;
+            continue;
+        }
+
+        let media_info = match ffprobe::probe(&ffmpeg_entry, &input).await {
+            Ok(info) if !info.is_convertible() => {
+                eprintln!("⚠ Skipping {input_path}: no audio, video or text subtitle streams");
+                skipped += 1;
+                continue;
+            }
+            Ok(info) => Some(info),
+            Err(e) => {
+                eprintln!("⚠ Could not probe {input_path}, progress will be approximate: {e}");
+                None
+            }
+        };
+
+        let task = FfmpegTask::new(ffmpeg_entry.clone(), Arc::clone(&output_format))
+            .input(input_path.clone())
+            .output(output_path)
+            .input_duration(media_info.as_ref().and_then(|info| info.duration))
+            .source_codecs(media_info.as_ref().map(SourceCodecs::from_media_info))
+            .source_sample_rate(media_info.as_ref().and_then(|info| info.sample_rate()))
+            .stream_copy(!args.no_copy)
+            .stream_map(
+                media_info
+                    .as_ref()
+                    .filter(|_| args.all_streams)
+                    .map(StreamSelection::from_media_info),
+            )
+            .video_codec(args.video_codec)
+            .audio_codec(args.audio_codec)
+            .video_rate(args.video_rate())
+            .audio_rate(args.audio_rate())
+            .two_pass(args.two_pass)
+            .resolution(args.resolution)
+            .frame_rate(args.fps)
+            .sample_rate(args.sample_rate)
+            .trim(args.trim())
+            .subtitle_track(args.subtitle_track.map(|n| n as usize - 1))
+            .subtitle_file(args.subtitle_file())
+            .image_quality(args.image_quality)
+            .strip_metadata(args.strip_metadata)
+            .gif(args.gif())
+            .normalization(args.normalization());
+
+        // 码率/质量参数是否有效要结合编码和时长才知道
+        if let Err(e) = task.clone().build() {
+            eprintln!("⚠ Skipping {input_path}: {e}");
+            skipped += 1;
+            continue;
+        }
+
+        let task_id = (tasks.len() + 1).to_string();
+        let name = input
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or(input_path);
+        names.insert(task_id.clone(), format!("{name} ({})", task.job_kind()));
+        tasks.push((task_id, task));
+    }
+
+    if tasks.is_empty() {
+        eprintln!("❌ Nothing to convert");
+        return EXIT_USAGE;
+    }
+
+    let total = tasks.len();
+    eprintln!("▶ Converting {total} file(s) to {} with up to {max_jobs} job(s)", output_format);
+
+    let controls = TaskControls::new();
+    let (tx, rx) = mpsc::unbounded_channel::<ProgressMsg>();
+    let batch = tokio::spawn(ffmpeg_wrapper::run_batch(tasks, max_jobs, controls.clone(), tx));
+    let mut display = ProgressDisplay::new(total, names);
+
+    let interrupted = {
+        let consume = display.consume(rx);
+        tokio::pin!(consume);
+        let interrupted = tokio::select! {
+            _ = &mut consume => false,
+            _ = tokio::signal::ctrl_c() => {
+                eprintln!("\n⏹ Interrupted, cancelling running tasks...");
+                controls.send_all(ControlMsg::Cancel);
+                true
+            }
+        };
+        // 取消后批处理还会发来各任务的结束消息，收完统计才准确；再按一次 Ctrl-C 直接退出
+        if interrupted {
+            tokio::select! {
+                _ = &mut consume => {}
+                _ = tokio::signal::ctrl_c() => {
+                    eprintln!("\n⏹ Interrupted again, exiting");
+                    return EXIT_INTERRUPTED;
+                }
+            }
+        }
+        interrupted
+    };
+
+    if let Err(e) = batch.await.map_err(anyhow::Error::from).and_then(|r| r) {
+        eprintln!("❌ Batch failed: {e}");
+    }
+
+    display.finish(skipped);
+
+    if interrupted {
+        EXIT_INTERRUPTED
+    } else if display.failed > 0 || display.cancelled > 0 || skipped > 0 {
+        EXIT_TASK_FAILED
+    } else {
+        EXIT_OK
+    }
+}
+
+/// 展开通配符，保持顺序并去重
+fn expand_inputs(patterns: &[String]) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    let mut inputs = vec![];
+
+    for pattern in patterns {
+        let path = PathBuf::from(pattern);
+        let matches: Vec<PathBuf> = if path.is_file() {
+            vec![path]
+        } else {
+            match glob::glob(pattern) {
+                Ok(paths) => paths.filter_map(Result::ok).filter(|p| p.is_file()).collect(),
+                Err(e) => {
+                    eprintln!("⚠ Invalid pattern {pattern}: {e}");
+                    continue;
+                }
+            }
+        };
+
+        if matches.is_empty() {
+            eprintln!("⚠ No files match {pattern}");
+        }
+        for path in matches {
+            if seen.insert(path.clone()) {
+                inputs.push(path);
+            }
+        }
+    }
+
+    inputs
+}
+
+/// 在 stderr 上显示整体进度，每个任务结束时单独打一行
+struct ProgressDisplay {
+    total: usize,
+    names: HashMap<String, String>,
+    running: HashMap<String, String>,
+    done: usize,
+    failed: usize,
+    cancelled: usize,
+}
+
+impl ProgressDisplay {
+    fn new(total: usize, names: HashMap<String, String>) -> Self {
+        Self {
+            total,
+            names,
+            running: HashMap::new(),
+            done: 0,
+            failed: 0,
+            cancelled: 0,
+        }
+    }
+
+    async fn consume(&mut self, mut rx: mpsc::UnboundedReceiver<ProgressMsg>) {
+        while let Some(msg) = rx.recv().await {
+            self.handle(msg);
+        }
+    }
+
+    fn handle(&mut self, msg: ProgressMsg) {
+        match msg {
+            ProgressMsg::Started { task_id } => {
+                self.running.insert(task_id, "starting".to_string());
+            }
+            ProgressMsg::Progress { task_id, progress } => {
+                let mut status = match progress.ratio() {
+                    Some(ratio) => format!("{:.0}%", ratio * 100.0),
+                    None => format_timestamp(progress.out_time_secs().unwrap_or_default()),
+                };
+                if let Some(pass) = progress.pass_label() {
+                    status.push_str(&format!(" {pass}"));
+                }
+                if let Some(speed) = progress.speed {
+                    status.push_str(&format!(" {:.2}x", speed));
+                }
+                self.running.insert(task_id, status);
+            }
+            ProgressMsg::Paused { task_id } => {
+                self.running.insert(task_id, "paused".to_string());
+            }
+            ProgressMsg::Resumed { .. } => {}
+            ProgressMsg::Measured {
+                task_id,
+                measurement,
+            } => {
+                eprint!("\r\x1b[K");
+                eprintln!("{} 🔊 measured {measurement}", self.name(&task_id));
+            }
+            ProgressMsg::Done { task_id } => {
+                self.done += 1;
+                self.finish_line(&task_id, "✅ done");
+            }
+            ProgressMsg::Cancelled { task_id } => {
+                self.cancelled += 1;
+                self.finish_line(&task_id, "⏹ cancelled");
+            }
+            ProgressMsg::Error { task_id, error } => {
+                self.failed += 1;
+                self.finish_line(&task_id, &format!("❌ failed: {error}"));
+            }
+        }
+        self.redraw();
+    }
+
+    fn name(&self, task_id: &str) -> String {
+        self.names.get(task_id).cloned().unwrap_or_else(|| task_id.to_string())
+    }
+
+    fn finish_line(&mut self, task_id: &str, status: &str) {
+        self.running.remove(task_id);
+        eprint!("\r\x1b[K");
+        eprintln!("{} {status}", self.name(task_id));
+    }
+
+    fn redraw(&self) {
+        let finished = self.done + self.failed + self.cancelled;
+        let mut running: Vec<(&String, &String)> = self.running.iter().collect();
+        running.sort();
+        let running = running
+            .into_iter()
+            .map(|(id, status)| format!("{} {status}", self.name(id)))
+            .collect::<Vec<_>>()
+            .join(" | ");
+
+        eprint!("\r\x1b[K[{finished}/{}] {running}", self.total);
+        let _ = std::io::stderr().flush();
+    }
+
+    fn finish(&self, skipped: usize) {
+        eprint!("\r\x1b[K");
+        eprintln!(
+            "✅ {} done, ❌ {} failed, ⏹ {} cancelled, ⚠ {} skipped",
+            self.done, self.failed, self.cancelled, skipped
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, clap::Error> {
+        CliArgs::try_parse_from(["converlex", "--headless"].iter().chain(args))
+    }
+
+    #[test]
+    fn parses_inputs_and_options() {
+        let args = parse(&[
+            "a.mkv", "b/*.mov", "-f", "mp4", "-o", "out", "-j", "2", "-v",
+        ])
+        .unwrap();
+
+        assert!(args.headless);
+        assert_eq!(args.inputs, ["a.mkv", "b/*.mov"]);
+        assert_eq!(args.format, "mp4");
+        assert_eq!(args.output_dir, Some(PathBuf::from("out")));
+        assert_eq!(args.jobs, Some(2));
+        assert!(args.verbose);
+        assert!(!args.overwrite);
+    }
+
+    #[test]
+    fn requires_inputs_and_format() {
+        assert!(parse(&["-f", "mp4"]).is_err());
+        assert!(parse(&["a.mkv"]).is_err());
+    }
+
+    /// 在独立的临时目录中创建空文件
+    fn temp_files(names: &[&str]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("converlex-cli-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in names {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn expands_globs_in_order_without_duplicates() {
+        let dir = temp_files(&["b.mkv", "a.mkv", "c.txt"]);
+        let patterns = [
+            dir.join("b.mkv").to_string_lossy().into_owned(),
+            dir.join("*.mkv").to_string_lossy().into_owned(),
+            dir.join("missing-*.mp4").to_string_lossy().into_owned(),
+        ];
+
+        assert_eq!(
+            expand_inputs(&patterns),
+            [dir.join("b.mkv"), dir.join("a.mkv")]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn counts_finished_tasks() {
+        let names = HashMap::from([("1".to_string(), "a.mkv".to_string())]);
+        let mut display = ProgressDisplay::new(3, names);
+        display.handle(ProgressMsg::Started {
+            task_id: "1".into(),
+        });
+        assert!(display.running.contains_key("1"));
+
+        display.handle(ProgressMsg::Done {
+            task_id: "1".into(),
+        });
+        display.handle(ProgressMsg::Cancelled {
+            task_id: "2".into(),
+        });
+        display.handle(ProgressMsg::Error {
+            task_id: "3".into(),
+            error: "boom".into(),
+        });
+
+        assert!(display.running.is_empty());
+        assert_eq!((display.done, display.cancelled, display.failed), (1, 1, 1));
+    }
+}
@@ -1,22 +1,39 @@
-use std::{collections::HashMap, fs, path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
-use tokio::sync::Mutex;
 use uuid::Uuid;
 use vizia::prelude::*;
 
 use super::app_event::AppEvent;
 use crate::{
-    err_msgbox,
     models::{
         app_settings::AppSettings,
-        convertible_format::ConvertibleFormat,
-        media_format::{Audio, MediaFormat, Video},
+        convertible_format::{ConvertibleFormat, FormatType},
+        codec::AudioCodec,
+        media_format::{Audio, Image, MediaFormat, Subtitle, Video},
+        preset::Preset,
+        preset_store,
+        rate_control::VideoRateControl,
+        stream_copy::SourceCodecs,
+        stream_map::StreamSelection,
+        subtitle::{SubtitleFile, SubtitleMode},
         task::{Task, TaskStatus, TaskType},
+        trim,
     },
-    unwrap_or_msgbox,
+    err_msgbox, unwrap_or_msgbox,
     utils::{
-        ffmpeg_wrapper::{self, FfmpegTask, ProgressMsg},
+        ffmpeg_caps::FfmpegCaps,
+        ffmpeg_concat::ConcatInput,
+        ffmpeg_sequence::ImageSequence,
+        ffmpeg_wrapper::{FfmpegEntry, ProgressMsg},
+        ffprobe,
+        scheduler::Scheduler,
+        task_control::{ControlMsg, TaskControls},
         fs::get_file_extension,
         utils::get_output_path,
     },
@@ -32,6 +49,12 @@ pub struct AppData {
     pub settings: AppSettings,
     pub show_settings_window: bool,
     pub show_format_selctor_window: bool,
+    pub controls: TaskControls,
+    pub scheduler: Option<Scheduler>,
+    /// 当前 ffmpeg 支持的封装器等，探测失败时为 None
+    pub ffmpeg_caps: Option<Arc<FfmpegCaps>>,
+    /// 内置预设在前，用户预设在后
+    pub presets: Vec<Preset>,
 }
 
 impl Model for AppData {
@@ -41,17 +64,7 @@ impl Model for AppData {
                 let final_name = match name {
                     Some(n) => n.clone(),
                     None => {
-                        let video_formats =
-                            Video::all().iter().map(|f| f.ext()).collect::<Vec<_>>();
-                        let audio_formats =
-                            Audio::all().iter().map(|f| f.ext()).collect::<Vec<_>>();
-                        match FileDialog::new()
-                            .add_filter("video", video_formats.as_slice())
-                            .add_filter("audio", audio_formats.as_slice())
-                            .add_filter("image", &["jpg", "jpeg", "png", "gif"])
-                            .add_filter("All Files", &["*"])
-                            .pick_file()
-                        {
+                        match media_file_dialog().pick_file() {
                             Some(path) => path.to_string_lossy().to_string(),
                             None => return,
                         }
@@ -73,162 +86,384 @@ impl Model for AppData {
 
                 let ffmpeg_entry =
                     unwrap_or_msgbox!(&self.settings.ffmpeg_entry, "未找到ffmpeg，请在设置中配置");
-                let task = Task::new(
+                let mut task = Task::new(
                     final_name.clone(),
                     get_output_path(&final_name, output_format, false),
                     arc_formats,
                     0,
                     ffmpeg_entry.clone(),
                 );
+                self.apply_default_preset(&mut task);
                 self.tasks.insert(id.clone(), task);
+                self.spawn_probe(cx, &id);
 
                 self.task_ids.push(id);
             }
-            AppEvent::RemoveAll => {
-                self.task_ids.clear();
-                self.tasks.clear();
-                self.show_config_page = false;
-            }
-            AppEvent::ChangeOutputFormat(index, selected_format) => {
-                if let Some(task) = self.tasks.get_mut(index) {
-                    task.selected_output_format = *selected_format;
+            AppEvent::AddConcatTask(paths) => {
+                let paths = match paths {
+                    Some(paths) => paths.clone(),
+                    None => match media_file_dialog().pick_files() {
+                        Some(paths) => paths
+                            .iter()
+                            .map(|p| p.to_string_lossy().to_string())
+                            .collect(),
+                        None => return,
+                    },
+                };
+                if paths.len() < 2 {
+                    err_msgbox!("拼接至少需要两个文件");
+                }
 
-                    let format = &*task.supported_output_formats[*selected_format];
+                let arc_formats = unwrap_or_msgbox!(
+                    concat_output_formats(&paths[0]),
+                    "不支持的文件格式"
+                );
+                let output_format = arc_formats.first().unwrap().as_any();
+                let ffmpeg_entry =
+                    unwrap_or_msgbox!(&self.settings.ffmpeg_entry, "未找到ffmpeg，请在设置中配置");
+                let mut task = Task::new_concat(
+                    paths.clone(),
+                    get_output_path(&paths[0], output_format, false),
+                    arc_formats,
+                    0,
+                    ffmpeg_entry.clone(),
+                );
+                self.apply_default_preset(&mut task);
 
-                    if task.auto_rename {
-                        let new_output_path = get_output_path(&task.input_path, format, false);
-                        task.output_path = new_output_path;
-                    }
+                let id = Uuid::new_v4().to_string();
+                self.tasks.insert(id.clone(), task);
+                self.spawn_concat_probe(cx, &id, paths);
+                self.task_ids.push(id);
+            }
+            AppEvent::AddConcatInputs(task_id) => {
+                let Some(paths) = media_file_dialog().pick_files() else {
+                    return;
+                };
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                let TaskType::Concat(concat_task) = &mut task.task_type else {
+                    return;
+                };
+                concat_task
+                    .inputs
+                    .extend(paths.iter().cloned().map(ConcatInput::new));
+                let paths = paths
+                    .iter()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect();
+                self.spawn_concat_probe(cx, task_id, paths);
+            }
+            AppEvent::MoveConcatInput(task_id, index, offset) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                let TaskType::Concat(concat_task) = &mut task.task_type else {
+                    return;
+                };
+                let Some(target) = index.checked_add_signed(*offset) else {
+                    return;
+                };
+                if *index < concat_task.inputs.len() && target < concat_task.inputs.len() {
+                    concat_task.inputs.swap(*index, target);
+                    sync_concat_first_input(task);
                 }
             }
-            AppEvent::StartConvert(task_ids) => {
-                for task_id in task_ids.clone().unwrap_or(self.task_ids.clone()) {
-                    let task = &self.tasks[&task_id];
-                    println!("开始转换任务：{:?}", task.clone());
-                    if task.status != TaskStatus::Queued {
-                        continue;
+            AppEvent::RemoveConcatInput(task_id, index) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                let TaskType::Concat(concat_task) = &mut task.task_type else {
+                    return;
+                };
+                if concat_task.inputs.len() <= 2 {
+                    err_msgbox!("拼接至少需要两个文件");
+                }
+                if *index < concat_task.inputs.len() {
+                    concat_task.inputs.remove(*index);
+                    sync_concat_first_input(task);
+                }
+            }
+            AppEvent::UpdateConcatInputInfo(task_id, path, result) => {
+                let Some(task) = self.tasks.get_mut(task_id) else {
+                    return;
+                };
+                let TaskType::Concat(concat_task) = &mut task.task_type else {
+                    return;
+                };
+                let media_info = match result {
+                    Ok(media_info) => {
+                        println!("[Task {task_id}] 🔍 {}: {}", path.display(), media_info.summary());
+                        Some(media_info.clone())
                     }
-
-                    let input_path = &task.input_path;
-
-                    let output_format =
-                        task.supported_output_formats[task.selected_output_format].as_any();
-                    let mut output_path = get_output_path(input_path, output_format, true);
-
-                    if input_path == &output_path {
-                        println!("输入输出路径相同，跳过任务：{}", input_path);
-                        rfd::MessageDialog::new()
-                            .set_title("Failed")
-                            .set_description(format!(
-                                "输入输出路径相同，跳过任务：\n\n源文件:\n{}\n输出文件:\n{}",
-                                input_path, output_path
-                            ))
-                            .show();
-                        continue;
+                    Err(e) => {
+                        eprintln!("[Task {task_id}] ❌ Probe failed for {}: {e}", path.display());
+                        None
                     }
+                };
+                // 同一个文件可以出现多次
+                for input in concat_task.inputs.iter_mut().filter(|i| &i.path == path) {
+                    input.media_info = media_info.clone();
+                }
+            }
+            AppEvent::ExtractAudio(task_id) => {
+                let source = unwrap_or_msgbox!(self.tasks.get(task_id)).clone();
+                let media_info =
+                    unwrap_or_msgbox!(source.media_info.clone(), "还没有探测到音轨，请稍后再试");
+                let codecs: Vec<Option<AudioCodec>> = media_info
+                    .audio_streams()
+                    .map(|a| a.codec.as_deref().and_then(AudioCodec::from_probe_name))
+                    .collect();
+                if codecs.is_empty() {
+                    err_msgbox!("输入文件中没有音轨");
+                }
 
-                    if Path::new(&output_path).exists() {
-                        let overwrite = MessageDialog::new()
-                            .set_level(MessageLevel::Warning)
-                            .set_title("文件已存在")
-                            .set_description(format!(
-                                "输出文件已存在，是否覆盖？\n\n源文件:\n{}\n输出文件:\n{}",
-                                input_path, output_path
-                            ))
-                            .set_buttons(MessageButtons::YesNo)
-                            .show();
-
-                        match overwrite {
-                            MessageDialogResult::Yes => {
-                                if let Err(e) = fs::remove_file(&output_path) {
-                                    println!("无法删除已存在的文件：{}，错误：{}", output_path, e);
-                                    continue;
-                                }
-                            }
-                            MessageDialogResult::No => {
-                                output_path = get_output_path(input_path, output_format, false);
-                                // *task.output_path = output_path.clone();
-                            }
-                            _ => {}
-                        }
-                    }
+                let input_format = MediaFormat::new(&get_file_extension(&source.input_path));
+                let formats: Vec<Arc<dyn ConvertibleFormat>> =
+                    MediaFormat::output_formats_for(input_format.as_ref(), Some(&media_info))
+                        .into_iter()
+                        .map(Arc::from)
+                        .filter(|f: &Arc<dyn ConvertibleFormat>| {
+                            matches!(f.get_format_type(), FormatType::Audio(_))
+                        })
+                        .collect();
+                if formats.is_empty() {
+                    err_msgbox!("这个文件没有可用的音频输出格式");
+                }
+                let ffmpeg_entry =
+                    unwrap_or_msgbox!(&self.settings.ffmpeg_entry, "未找到ffmpeg，请在设置中配置");
 
-                    let input_path_clone = input_path.clone();
-                    let callback = move |progress: f32| {
-                        println!("{:?} 转换进度: {:.2}%", input_path_clone, progress * 100.0);
-                    };
+                // 每路音频一个任务，排在原任务后面
+                let position = self
+                    .task_ids
+                    .iter()
+                    .position(|id| id == task_id)
+                    .map_or(self.task_ids.len(), |i| i + 1);
+                for (track, codec) in codecs.into_iter().enumerate() {
+                    // 默认选能直接复制这路音频的格式
+                    let selected = codec
+                        .map(|c| Audio::for_codec(c).ext())
+                        .and_then(|ext| formats.iter().position(|f| f.get_ext() == ext))
+                        .unwrap_or(0);
+                    let mut task = Task::new(
+                        source.input_path.clone(),
+                        String::new(),
+                        formats.clone(),
+                        selected,
+                        ffmpeg_entry.clone(),
+                    );
+                    task.media_info = Some(media_info.clone());
+                    task.task_type.map_ffmpeg(|t| t.audio_track(Some(track)));
+                    task.output_path = task.default_output_path(false);
 
-                    // tokio::spawn(async move {
-                    //     let cb = move |p: f32| {
-                    //         // e.g. cx.emit(AppEvent::UpdateProgress(idx, p));
-                    //         println!("{} => {:.1}%", input_path_clone.clone(), p * 100.0);
-                    //     };
-                    //     match crate::utils::utils::convert_media_with_progress(&input_path_clone, &output_path, cb) {
-                    //         Ok(_) => println!("Done: {} → {}", input_path_clone, output_path),
-                    //         Err(e) => eprintln!("Error: {}", e),
-                    //     }
-                    // });
+                    let id = Uuid::new_v4().to_string();
+                    self.tasks.insert(id.clone(), task);
+                    self.task_ids.insert(position + track, id);
+                }
+            }
+            AppEvent::ChangeAudioTrack(task_id, track) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| t.audio_track(*track));
+                if task.auto_rename {
+                    task.output_path = task.default_output_path(false);
+                }
+            }
+            AppEvent::ToggleStreamMap(task_id) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                let streams = match task.task_type.ffmpeg().stream_map {
+                    Some(_) => None,
+                    None => Some(StreamSelection::from_media_info(unwrap_or_msgbox!(
+                        task.media_info.as_ref(),
+                        "还没有探测到输入文件的流，请稍后再试"
+                    ))),
+                };
+                task.task_type.map_ffmpeg(|t| t.stream_map(streams));
+            }
+            AppEvent::ToggleStreamIncluded(task_id, pos) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                if let Some(stream) = stream_selection_mut(task).and_then(|s| s.get_mut(*pos)) {
+                    stream.included = !stream.included;
+                }
+            }
+            AppEvent::MoveStream(task_id, pos, offset) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                let Some(streams) = stream_selection_mut(task) else {
+                    return;
+                };
+                if let Some(target) = pos.checked_add_signed(*offset)
+                    && *pos < streams.len()
+                    && target < streams.len()
+                {
+                    streams.swap(*pos, target);
+                }
+            }
+            AppEvent::ToggleStreamDisposition(task_id, pos, disposition) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                if let Some(stream) = stream_selection_mut(task).and_then(|s| s.get_mut(*pos)) {
+                    stream.toggle(*disposition);
+                }
+            }
+            AppEvent::ChangeSubtitleTrack(task_id, track) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| t.subtitle_track(*track));
+                if task.auto_rename {
+                    task.output_path = task.default_output_path(false);
+                }
+            }
+            AppEvent::ChooseSubtitleFile(task_id) => {
+                let subtitle_formats = Subtitle::all().iter().map(|f| f.ext()).collect::<Vec<_>>();
+                let Some(path) = FileDialog::new()
+                    .add_filter("subtitle", subtitle_formats.as_slice())
+                    .pick_file()
+                else {
+                    return;
+                };
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                // 换文件时保留原来的用法
+                let mode = task
+                    .task_type
+                    .ffmpeg()
+                    .subtitle_file
+                    .as_ref()
+                    .map_or(SubtitleMode::default(), |file| file.mode);
+                task.task_type
+                    .map_ffmpeg(|t| t.subtitle_file(Some(SubtitleFile::new(path, mode))));
+            }
+            AppEvent::ClearSubtitleFile(task_id) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| t.subtitle_file(None));
+            }
+            AppEvent::ChangeSubtitleMode(task_id, mode) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                if let Some(file) = task.task_type.ffmpeg_mut().subtitle_file.as_mut() {
+                    file.mode = *mode;
+                }
+            }
+            AppEvent::ExportFrames(task_id) => {
+                let source = unwrap_or_msgbox!(self.tasks.get(task_id)).clone();
+                let media_info =
+                    unwrap_or_msgbox!(source.media_info.clone(), "还没有探测到视频流，请稍后再试");
+                if !media_info.has_video() {
+                    err_msgbox!("输入文件中没有视频流");
                 }
+                let formats: Vec<Arc<dyn ConvertibleFormat>> = MediaFormat::frame_export_formats()
+                    .into_iter()
+                    .map(Arc::from)
+                    .collect();
+                let ffmpeg_entry =
+                    unwrap_or_msgbox!(&self.settings.ffmpeg_entry, "未找到ffmpeg，请在设置中配置");
 
-                let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<ProgressMsg>();
-                let rx = Arc::new(Mutex::new(rx));
+                let mut task = Task::new_frame_export(
+                    source.input_path.clone(),
+                    String::new(),
+                    formats,
+                    0,
+                    ffmpeg_entry.clone(),
+                );
+                task.media_info = Some(media_info);
+                task.output_path = task.default_output_path(false);
+
+                // 排在原任务后面
+                let position = self
+                    .task_ids
+                    .iter()
+                    .position(|id| id == task_id)
+                    .map_or(self.task_ids.len(), |i| i + 1);
+                let id = Uuid::new_v4().to_string();
+                self.tasks.insert(id.clone(), task);
+                self.task_ids.insert(position, id);
+            }
+            AppEvent::ChangeFrameSampling(task_id, sampling) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                if let TaskType::FrameExport(export_task) = &mut task.task_type {
+                    export_task.sampling = *sampling;
+                }
+            }
+            AppEvent::AddImageSequenceTask(dir) => {
+                let dir = match dir {
+                    Some(dir) => dir.clone(),
+                    None => match FileDialog::new().pick_folder() {
+                        Some(dir) => dir,
+                        None => return,
+                    },
+                };
+                let sequence = match ImageSequence::detect(&dir) {
+                    Ok(sequence) => sequence,
+                    Err(e) => err_msgbox!(e),
+                };
+                println!(
+                    "找到图片序列：{}，共 {} 帧",
+                    dir.join(&sequence.pattern).display(),
+                    sequence.frame_count
+                );
 
+                let arc_formats: Vec<Arc<dyn ConvertibleFormat>> =
+                    MediaFormat::image_sequence_formats()
+                        .into_iter()
+                        .map(Arc::from)
+                        .collect();
+                let output_format = arc_formats.first().unwrap().as_any();
                 let ffmpeg_entry =
                     unwrap_or_msgbox!(&self.settings.ffmpeg_entry, "未找到ffmpeg，请在设置中配置");
+                let mut task = Task::new_image_sequence(
+                    sequence,
+                    get_output_path(&dir.to_string_lossy(), output_format, false),
+                    arc_formats,
+                    0,
+                    ffmpeg_entry.clone(),
+                );
+                self.apply_default_preset(&mut task);
 
-                let tasks: Vec<(String, FfmpegTask)> = self
-                    .tasks
+                let id = Uuid::new_v4().to_string();
+                self.tasks.insert(id.clone(), task);
+                self.task_ids.push(id);
+            }
+            AppEvent::RemoveAll => {
+                self.controls.send_all(ControlMsg::Cancel);
+                self.task_ids.clear();
+                self.tasks.clear();
+                self.show_config_page = false;
+            }
+            AppEvent::ChangeOutputFormat(index, selected_format) => {
+                if let Some(task) = self.tasks.get_mut(index) {
+                    task.selected_output_format = *selected_format;
+                    fit_codecs(task);
+
+                    if task.auto_rename {
+                        task.output_path = task.default_output_path(false);
+                    }
+                }
+            }
+            AppEvent::StartConvert(task_ids) => {
+                let task_ids = task_ids.clone().unwrap_or(self.task_ids.clone());
+                self.start_convert(cx, task_ids);
+            }
+            AppEvent::StartSelected => {
+                let task_ids = self
+                    .task_ids
                     .iter()
-                    .enumerate()
-                    .map(|(_, (task_id, task))| {
-                        let t = Into::<FfmpegTask>::into(task.clone())
-                            .ffmpeg_entry(ffmpeg_entry.clone())
-                            .input(task.input_path.clone())
-                            .output(task.output_path.clone());
-
-                        (task_id.clone(), t.clone())
+                    .filter(|id| self.tasks.get(*id).is_some_and(|t| t.selected))
+                    .cloned()
+                    .collect();
+                self.start_convert(cx, task_ids);
+            }
+            AppEvent::RetryTasks(task_ids) => {
+                let task_ids: Vec<TaskId> = task_ids
+                    .clone()
+                    .unwrap_or(self.task_ids.clone())
+                    .into_iter()
+                    .filter(|id| {
+                        self.tasks.get(id).is_some_and(|t| {
+                            matches!(t.status, TaskStatus::Failed | TaskStatus::Cancelled)
+                        })
                     })
                     .collect();
 
-                tokio::spawn(async move {
-                    // 🧵 后台并发运行
-                    if let Err(e) = ffmpeg_wrapper::run_batch(tasks, tx).await {
-                        eprintln!("任务失败：{}", e);
+                for task_id in &task_ids {
+                    if let Some(task) = self.tasks.get_mut(task_id) {
+                        task.reset();
                     }
-                });
-
-                let mut event_proxy = cx.get_proxy();
-
-                let rx_clone = rx.clone();
-                tokio::spawn(async move {
-                    let mut rx = rx_clone.lock().await;
-                    while let Some(msg) = rx.recv().await {
-                        match msg {
-                            ProgressMsg::Progress { task_id, progress } => {
-                                let _ = event_proxy
-                                    .emit(AppEvent::UpdateProgress(task_id, progress))
-                                    .map_err(|e| {
-                                        eprintln!("❗ Error emitting PROGRESS event: {}", e);
-                                    });
-                            }
-                            ProgressMsg::Done { task_id } => {
-                                let _ = event_proxy
-                                    .emit(AppEvent::MarkDone(task_id, true))
-                                    .map_err(|e| {
-                                        eprintln!("❗ Error emitting COMPLETE event: {}", e);
-                                    });
-                            }
-                            ProgressMsg::Error { task_id, error } => {
-                                let _ = event_proxy
-                                    .emit(AppEvent::MarkDone(task_id.clone(), false))
-                                    .map_err(|e| {
-                                        eprintln!("❗ Error emitting ERROR event: {}", e);
-                                    });
-                                err_msgbox!(format!("Task {task_id} failed\nErr: {error}"))
-                            }
-                        }
-                    }
-                });
+                }
+                self.start_convert(cx, task_ids);
+            }
+            AppEvent::ToggleTaskSelected(idx) => {
+                if let Some(task) = self.tasks.get_mut(idx) {
+                    task.selected = !task.selected;
+                }
             }
             AppEvent::ToggleConifg(idx) => {
                 self.show_config_page = true;
@@ -238,15 +473,12 @@ impl Model for AppData {
                 if let Some(task) = self.tasks.get_mut(idx) {
                     task.auto_rename = !task.auto_rename;
                     if task.auto_rename {
-                        task.output_path = get_output_path(
-                            &task.input_path,
-                            task.supported_output_formats[task.selected_output_format].as_any(),
-                            false,
-                        );
+                        task.output_path = task.default_output_path(false);
                     }
                 }
             }
             AppEvent::RemoveTask(id) => {
+                self.controls.send(id, ControlMsg::Cancel);
                 if let Some(index) = self.task_ids.iter().position(|x| x == id) {
                     self.task_ids.remove(index);
                 }
@@ -257,12 +489,17 @@ impl Model for AppData {
                 }
             }
             AppEvent::UpdateTask(index, task) => {
+                let mut input_changed = false;
                 if let Some(existing_task) = self.tasks.get_mut(index) {
+                    input_changed = existing_task.input_path != task.input_path;
                     existing_task.input_path = task.input_path.clone();
                     existing_task.output_path = task.output_path.clone();
                     existing_task.auto_rename = task.auto_rename;
                     existing_task.selected_output_format = task.selected_output_format;
                 }
+                if input_changed {
+                    self.spawn_probe(cx, index);
+                }
             }
             AppEvent::ConfigWindowClosing => {
                 self.show_config_page = false;
@@ -270,8 +507,14 @@ impl Model for AppData {
             }
             AppEvent::UpdateProgress(idx, new_progress) => {
                 if let Some(task) = self.tasks.get_mut(idx) {
-                    task.progress = *new_progress;
-                    task.status = TaskStatus::Running;
+                    if let Some(ratio) = new_progress.ratio() {
+                        task.progress = ratio;
+                    }
+                    task.progress_info = Some(new_progress.clone());
+                    // 暂停后缓冲区里的进度还会陆续到达，不能把状态改回运行中
+                    if matches!(task.status, TaskStatus::Pending | TaskStatus::Running) {
+                        task.status = TaskStatus::Running;
+                    }
                 }
             }
             AppEvent::MarkDone(idx, is_sucess) => {
@@ -284,16 +527,62 @@ impl Model for AppData {
                     }
                 }
             }
+            AppEvent::UpdateStatus(idx, status) => {
+                if let Some(task) = self.tasks.get_mut(idx) {
+                    task.status = status.clone();
+                }
+            }
+            AppEvent::CancelTask(idx) => {
+                if !self.controls.send(idx, ControlMsg::Cancel) {
+                    println!("任务未在运行：{}", idx);
+                }
+                // 还在排队的任务不会再启动，直接标记
+                if let Some(task) = self.tasks.get_mut(idx)
+                    && task.status == TaskStatus::Pending
+                {
+                    task.status = TaskStatus::Cancelled;
+                }
+            }
+            AppEvent::CancelAll => {
+                self.controls.send_all(ControlMsg::Cancel);
+                for task in self.tasks.values_mut() {
+                    if task.status == TaskStatus::Pending {
+                        task.status = TaskStatus::Cancelled;
+                    }
+                }
+            }
+            AppEvent::UpdateMaxParallelJobs(max_jobs) => {
+                self.settings.max_parallel_jobs = (*max_jobs).max(1);
+                if let Some(scheduler) = &self.scheduler {
+                    scheduler.set_max_jobs(self.settings.max_parallel_jobs);
+                }
+                self.save_settings();
+            }
+            AppEvent::PauseTask(idx) => {
+                self.controls.send(idx, ControlMsg::Pause);
+            }
+            AppEvent::ResumeTask(idx) => {
+                self.controls.send(idx, ControlMsg::Resume);
+            }
             AppEvent::UpdateAppSettings(f) => {
-                unimplemented!();
-                // f(&mut self.settings);
+                f(&mut self.settings);
                 println!("✅ 更新设置: {:?}", self.settings);
+                self.save_settings();
             }
             AppEvent::ToggleSettingsWindow => {
                 self.show_settings_window = !self.show_settings_window;
             }
             AppEvent::UpdateFfmpegEntry(app_settings) => {
                 self.settings.ffmpeg_entry = app_settings.clone();
+                self.save_settings();
+                // 重新选择时可能换了同一路径下的 ffmpeg，不用旧缓存
+                if let Some(entry) = &self.settings.ffmpeg_entry {
+                    FfmpegCaps::invalidate(entry);
+                }
+                self.spawn_caps_probe(cx);
+            }
+            AppEvent::UpdateFfmpegCaps(caps) => {
+                self.ffmpeg_caps = caps.clone();
             }
             AppEvent::ToggleFormatSelectorWindow(idx) => {
                 self.show_format_selctor_window = true;
@@ -302,27 +591,749 @@ impl Model for AppData {
             AppEvent::FormatSelectorWindowClosing => {
                 self.show_format_selctor_window = false;
             }
-            AppEvent::ChangeAudioBitrate(task_id, new_bitrate) => {
+            AppEvent::ChangeAudioRate(task_id, new_rate) => {
                 let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
-                match &task.task_type {
-                    TaskType::Ffmpeg(ffmpeg_task) => {
-                        let new = ffmpeg_task.clone().audio_bitrate(*new_bitrate);
-                        task.task_type = TaskType::Ffmpeg(new);
-                    }
+                task.task_type.map_ffmpeg(|t| t.audio_rate(*new_rate));
+            }
+            AppEvent::ChangeVideoRate(task_id, new_rate) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| {
+                    // 两遍编码只对固定码率和目标大小有意义
+                    let two_pass = t.two_pass
+                        && matches!(
+                            new_rate,
+                            Some(VideoRateControl::Bitrate(_) | VideoRateControl::TargetSize(_))
+                        );
+                    t.video_rate(*new_rate).two_pass(two_pass)
+                });
+            }
+            AppEvent::ToggleTwoPass(task_id) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| {
+                    let two_pass = !t.two_pass;
+                    t.two_pass(two_pass)
+                });
+            }
+            AppEvent::ToggleStreamCopy(task_id) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| {
+                    let stream_copy = !t.stream_copy;
+                    t.stream_copy(stream_copy)
+                });
+            }
+            AppEvent::ChangeVideoCodec(task_id, codec) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| t.video_codec(*codec));
+            }
+            AppEvent::ChangeAudioCodec(task_id, codec) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| t.audio_codec(*codec));
+            }
+            AppEvent::ChangeResolution(task_id, resolution) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                if let Some(Err(e)) = resolution.map(|r| r.validate()) {
+                    err_msgbox!(e);
+                }
+                task.task_type.map_ffmpeg(|t| t.resolution(*resolution));
+            }
+            AppEvent::ChangeFrameRate(task_id, fps) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| t.frame_rate(*fps));
+            }
+            AppEvent::ChangeSampleRate(task_id, rate) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| t.sample_rate(*rate));
+            }
+            AppEvent::ChangeImageQuality(task_id, quality) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| t.image_quality(*quality));
+            }
+            AppEvent::UpdateGifOptions(task_id, f) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                let mut gif = task.task_type.ffmpeg().gif;
+                f(&mut gif);
+                if let Err(e) = gif.validate() {
+                    err_msgbox!(e);
+                }
+                task.task_type.map_ffmpeg(|t| t.gif(gif));
+            }
+            AppEvent::ChangeNormalization(task_id, normalization) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                if let Some(Err(e)) = normalization.as_ref().map(|n| n.validate()) {
+                    err_msgbox!(e);
+                }
+                let normalization = *normalization;
+                task.task_type
+                    .map_ffmpeg(|t| t.normalization(normalization));
+            }
+            AppEvent::UpdateLoudness(task_id, measurement) => {
+                if let Some(task) = self.tasks.get_mut(task_id) {
+                    task.loudness = Some(*measurement);
+                }
+            }
+            AppEvent::ToggleStripMetadata(task_id) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                task.task_type.map_ffmpeg(|t| {
+                    let strip = !t.strip_metadata;
+                    t.strip_metadata(strip)
+                });
+            }
+            AppEvent::ChangeTrimPoint(task_id, point, text) => {
+                let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
+                let value = match text.trim() {
+                    "" => None,
+                    text => match trim::parse_timestamp(text) {
+                        Ok(secs) => Some(secs),
+                        Err(e) => err_msgbox!(e),
+                    },
                 };
-
-                println!("change audio bitrate{:?}", task);
-                println!("tasks: {:?}", self.tasks);
+                if matches!(task.task_type, TaskType::Concat(_)) {
+                    err_msgbox!("拼接任务不支持截取");
+                }
+                if matches!(task.task_type, TaskType::ImageSequence(_)) {
+                    err_msgbox!("图片序列不支持截取");
+                }
+                let duration = task.media_info.as_ref().and_then(|m| m.duration);
+                let new_trim = task.task_type.ffmpeg().trim.with(*point, value);
+                if let Err(e) = new_trim.validate(duration) {
+                    err_msgbox!(e);
+                }
+                task.task_type.map_ffmpeg(|t| t.trim(new_trim));
             }
-            AppEvent::ChangeVideoBitrate(task_id, new_bitrate) => {
+            AppEvent::ChangeSeekMode(task_id, seek) => {
                 let task = unwrap_or_msgbox!(self.tasks.get_mut(task_id));
-                match &task.task_type {
-                    TaskType::Ffmpeg(ffmpeg_task) => {
-                        let new = ffmpeg_task.clone().video_bitrate(*new_bitrate);
-                        task.task_type = TaskType::Ffmpeg(new);
+                let new_trim = trim::Trim {
+                    seek: *seek,
+                    ..task.task_type.ffmpeg().trim
+                };
+                task.task_type.map_ffmpeg(|t| t.trim(new_trim));
+            }
+            AppEvent::UpdateMediaInfo(task_id, result) => {
+                if let Some(task) = self.tasks.get_mut(task_id) {
+                    match result {
+                        Ok(media_info) => {
+                            println!("[Task {task_id}] 🔍 {}", media_info.summary());
+                            task.media_info = Some(media_info.clone());
+                            // 换了输入后原来的流序号不再对应
+                            task.task_type.map_ffmpeg(|t| t.stream_map(None));
+                            refresh_output_formats(task);
+                        }
+                        Err(e) => {
+                            eprintln!("[Task {task_id}] ❌ Probe failed: {e}");
+                            task.media_info = None;
+                        }
+                    }
+                }
+            }
+            AppEvent::SavePreset(task_id, name) => {
+                let task = unwrap_or_msgbox!(self.tasks.get(task_id));
+                let preset = Preset::from_task(name.trim(), task);
+                if let Err(e) = preset.validate() {
+                    err_msgbox!(e);
+                }
+                if self.presets.iter().any(|p| p.builtin && p.name == preset.name) {
+                    err_msgbox!(format!("\"{}\" 是内置预设，请换一个名字", preset.name));
+                }
+                preset_store::merge(&mut self.presets, vec![preset]);
+                self.save_presets();
+            }
+            AppEvent::DeletePreset(name) => {
+                self.presets.retain(|p| p.builtin || &p.name != name);
+                if self.settings.default_preset.as_ref() == Some(name) {
+                    self.settings.default_preset = None;
+                    self.save_settings();
+                }
+                self.save_presets();
+            }
+            AppEvent::ApplyPreset(name, task_ids) => {
+                let preset = unwrap_or_msgbox!(
+                    self.presets.iter().find(|p| &p.name == name).cloned(),
+                    format!("找不到预设 {name}")
+                );
+                let task_ids = task_ids.clone().unwrap_or_else(|| {
+                    self.task_ids
+                        .iter()
+                        .filter(|id| self.tasks.get(*id).is_some_and(|t| t.selected))
+                        .cloned()
+                        .collect()
+                });
+
+                let mut failures = vec![];
+                for task_id in &task_ids {
+                    let Some(task) = self.tasks.get_mut(task_id) else {
+                        continue;
+                    };
+                    // 已提交或正在运行的任务不改配置
+                    if matches!(
+                        task.status,
+                        TaskStatus::Pending | TaskStatus::Running | TaskStatus::Paused
+                    ) {
+                        continue;
+                    }
+                    if let Err(e) = preset.apply_to(task) {
+                        failures.push(e);
                     }
+                }
+                if !failures.is_empty() {
+                    err_msgbox!(format!(
+                        "预设 {} 未能套用到 {} 个任务：\n\n{}",
+                        preset.name,
+                        failures.len(),
+                        failures.join("\n")
+                    ));
+                }
+            }
+            AppEvent::ChangeDefaultPreset(name) => {
+                self.settings.default_preset = name.clone();
+                self.save_settings();
+            }
+            AppEvent::ImportPresets => {
+                let Some(path) = FileDialog::new()
+                    .add_filter("Converlex Presets", &["toml"])
+                    .pick_file()
+                else {
+                    return;
+                };
+                let imported = match preset_store::import(&path) {
+                    Ok(presets) => presets,
+                    Err(e) => err_msgbox!(format!("导入预设失败：{e}")),
                 };
+                println!("⚙️ Imported {} presets from {}", imported.len(), path.display());
+                preset_store::merge(&mut self.presets, imported);
+                self.save_presets();
+            }
+            AppEvent::ExportPresets => {
+                if self.presets.iter().all(|p| p.builtin) {
+                    err_msgbox!("没有可导出的自定义预设");
+                }
+                let Some(path) = FileDialog::new()
+                    .add_filter("Converlex Presets", &["toml"])
+                    .set_file_name("converlex-presets.toml")
+                    .save_file()
+                else {
+                    return;
+                };
+                if let Err(e) = preset_store::export(&path, &self.presets) {
+                    err_msgbox!(format!("导出预设失败：{e}"));
+                }
             }
         });
     }
 }
+
+impl AppData {
+    fn save_settings(&self) {
+        if let Err(e) = self.settings.save() {
+            eprintln!("❗ Failed to save settings: {e}");
+        }
+    }
+
+    fn save_presets(&self) {
+        if let Err(e) = preset_store::save_user_presets(&self.presets) {
+            eprintln!("❗ Failed to save presets: {e}");
+        }
+    }
+
+    fn default_preset(&self) -> Option<&Preset> {
+        let name = self.settings.default_preset.as_ref()?;
+        self.presets.iter().find(|p| &p.name == name)
+    }
+
+    /// 新任务套用默认预设，不适用于这个文件时保持默认配置，不打断批量添加
+    fn apply_default_preset(&self, task: &mut Task) {
+        if let Some(preset) = self.default_preset()
+            && let Err(e) = preset.apply_to(task)
+        {
+            println!("默认预设 {} 未套用：{}", preset.name, e);
+        }
+    }
+
+    /// 检查并提交指定的任务，只处理其中状态为 Queued 的任务
+    fn start_convert(&mut self, cx: &mut EventContext, task_ids: Vec<TaskId>) {
+        let ffmpeg_entry =
+            unwrap_or_msgbox!(&self.settings.ffmpeg_entry, "未找到ffmpeg，请在设置中配置").clone();
+
+        // 通过检查的任务、最终输出路径和要提交的任务参数
+        let mut accepted: Vec<(TaskId, String, TaskType)> = vec![];
+
+        for task_id in task_ids {
+            let Some(task) = self.tasks.get(&task_id) else {
+                continue;
+            };
+            if task.status != TaskStatus::Queued {
+                continue;
+            }
+            println!("开始转换任务：{:?}", task.clone());
+
+            let input_path = &task.input_path;
+
+            if let Some(media_info) = &task.media_info
+                && !media_info.is_convertible()
+            {
+                rfd::MessageDialog::new()
+                    .set_title("Failed")
+                    .set_description(format!(
+                        "输入文件中没有可转换的音视频流，跳过任务：\n\n{}",
+                        input_path
+                    ))
+                    .show();
+                continue;
+            }
+
+            let output_format =
+                task.supported_output_formats[task.selected_output_format].as_any();
+
+            if let Some(caps) = &self.ffmpeg_caps
+                && let Err(reason) = caps.check_format(output_format)
+            {
+                rfd::MessageDialog::new()
+                    .set_title("Failed")
+                    .set_description(format!(
+                        "无法输出为 {}，跳过任务：\n\n{}\n\n{}",
+                        output_format.get_label(),
+                        input_path,
+                        reason
+                    ))
+                    .show();
+                continue;
+            }
+
+            let ffmpeg_task = task.task_type.ffmpeg();
+            let (video_codec, audio_codec) = (ffmpeg_task.video_codec, ffmpeg_task.audio_codec);
+            let codec_check = output_format
+                .get_format_type()
+                .check_codecs(video_codec, audio_codec)
+                .and_then(|_| match &self.ffmpeg_caps {
+                    Some(caps) => caps.check_codecs(video_codec, audio_codec),
+                    None => Ok(()),
+                });
+            if let Err(reason) = codec_check {
+                rfd::MessageDialog::new()
+                    .set_title("Failed")
+                    .set_description(format!(
+                        "编码设置无效，跳过任务：\n\n{}\n\n{}",
+                        input_path, reason
+                    ))
+                    .show();
+                continue;
+            }
+
+            // 自动命名时从默认文件名开始检查，否则尊重用户填写的路径
+            let mut output_path = if task.auto_rename {
+                task.default_output_path(true)
+            } else {
+                task.output_path.clone()
+            };
+
+            let overwrites_input = match &task.task_type {
+                TaskType::Concat(concat_task) => concat_task
+                    .inputs
+                    .iter()
+                    .any(|input| input.path == Path::new(&output_path)),
+                _ => input_path == &output_path,
+            };
+            if overwrites_input {
+                println!("输入输出路径相同，跳过任务：{}", input_path);
+                rfd::MessageDialog::new()
+                    .set_title("Failed")
+                    .set_description(format!(
+                        "输入输出路径相同，跳过任务：\n\n源文件:\n{}\n输出文件:\n{}",
+                        input_path, output_path
+                    ))
+                    .show();
+                continue;
+            }
+
+            // 码率/质量等参数要结合编码和时长才能检查，要在删除已存在的输出之前检查
+            let mut job = Self::prepare_job(task, &ffmpeg_entry, &output_path);
+            if let Err(e) = job.build() {
+                rfd::MessageDialog::new()
+                    .set_title("Failed")
+                    .set_description(format!(
+                        "转换参数无效，跳过任务：\n\n{}\n\n{}",
+                        input_path, e
+                    ))
+                    .show();
+                continue;
+            }
+
+            if Path::new(&output_path).exists() {
+                let overwrite = MessageDialog::new()
+                    .set_level(MessageLevel::Warning)
+                    .set_title("文件已存在")
+                    .set_description(format!(
+                        "输出文件已存在，是否覆盖？\n\n源文件:\n{}\n输出文件:\n{}",
+                        input_path, output_path
+                    ))
+                    .set_buttons(MessageButtons::YesNo)
+                    .show();
+
+                match overwrite {
+                    // 导出帧的文件夹不删除，同名图片由 ffmpeg 覆盖
+                    MessageDialogResult::Yes if Path::new(&output_path).is_dir() => {}
+                    MessageDialogResult::Yes => {
+                        if let Err(e) = fs::remove_file(&output_path) {
+                            println!("无法删除已存在的文件：{}，错误：{}", output_path, e);
+                            continue;
+                        }
+                    }
+                    MessageDialogResult::No if task.auto_rename => {
+                        output_path = task.default_output_path(false);
+                        job = Self::prepare_job(task, &ffmpeg_entry, &output_path);
+                    }
+                    _ => {
+                        println!("不覆盖已存在的文件，跳过任务：{}", output_path);
+                        continue;
+                    }
+                }
+            }
+
+            accepted.push((task_id, output_path, job));
+        }
+
+        if accepted.is_empty() {
+            return;
+        }
+
+        let scheduler = self.get_or_spawn_scheduler(cx);
+        for (task_id, output_path, job) in accepted {
+            let Some(task) = self.tasks.get_mut(&task_id) else {
+                continue;
+            };
+            task.output_path = output_path;
+
+            if scheduler.enqueue(task_id.clone(), job) {
+                task.status = TaskStatus::Pending;
+                task.loudness = None;
+                task.selected = false;
+            }
+        }
+    }
+
+    /// 按任务当前的设置和探测结果生成提交给调度器的任务参数
+    fn prepare_job(task: &Task, ffmpeg_entry: &FfmpegEntry, output_path: &str) -> TaskType {
+        let output_format = Arc::clone(&task.supported_output_formats[task.selected_output_format]);
+        let mut job = task.task_type.clone();
+        job.map_ffmpeg(|t| {
+            t.ffmpeg_entry(ffmpeg_entry.clone())
+                .output_format(output_format)
+                .output(output_path.to_string())
+        });
+        match &mut job {
+            TaskType::Ffmpeg(t) => {
+                *t = t
+                    .clone()
+                    .input(task.input_path.clone())
+                    .input_duration(task.media_info.as_ref().and_then(|m| m.duration))
+                    .source_codecs(task.media_info.as_ref().map(SourceCodecs::from_media_info))
+                    .source_sample_rate(task.media_info.as_ref().and_then(|m| m.sample_rate()));
+            }
+            TaskType::FrameExport(export_task) => {
+                let media_info = task.media_info.as_ref();
+                export_task.ffmpeg = export_task
+                    .ffmpeg
+                    .clone()
+                    .input(task.input_path.clone())
+                    .input_duration(media_info.and_then(|m| m.duration));
+                export_task.source_frame_rate = media_info
+                    .and_then(|m| m.video_streams().next())
+                    .and_then(|v| v.frame_rate);
+            }
+            TaskType::Concat(_) | TaskType::ImageSequence(_) => {}
+        }
+        job
+    }
+
+    /// 后台用 ffprobe 探测任务的输入文件，结果通过 UpdateMediaInfo 发回
+    fn spawn_probe(&self, cx: &mut EventContext, task_id: &TaskId) {
+        let (Some(task), Some(ffmpeg_entry)) = (self.tasks.get(task_id), &self.settings.ffmpeg_entry)
+        else {
+            return;
+        };
+
+        let ffmpeg_entry = ffmpeg_entry.clone();
+        let input_path = task.input_path.clone();
+        let task_id = task_id.clone();
+        let mut event_proxy = cx.get_proxy();
+
+        tokio::spawn(async move {
+            let result = ffprobe::probe(&ffmpeg_entry, &input_path)
+                .await
+                .map_err(|e| e.to_string());
+            let _ = event_proxy
+                .emit(AppEvent::UpdateMediaInfo(task_id, result))
+                .map_err(|e| {
+                    eprintln!("❗ Error emitting PROBE event: {}", e);
+                });
+        });
+    }
+
+    /// 逐个探测拼接任务的输入，结果通过 UpdateConcatInputInfo 发回
+    fn spawn_concat_probe(&self, cx: &mut EventContext, task_id: &TaskId, paths: Vec<String>) {
+        let Some(ffmpeg_entry) = self.settings.ffmpeg_entry.clone() else {
+            return;
+        };
+        let task_id = task_id.clone();
+        let mut event_proxy = cx.get_proxy();
+
+        tokio::spawn(async move {
+            for path in paths {
+                let result = ffprobe::probe(&ffmpeg_entry, &path)
+                    .await
+                    .map_err(|e| e.to_string());
+                let event = AppEvent::UpdateConcatInputInfo(task_id.clone(), PathBuf::from(path), result);
+                let _ = event_proxy.emit(event).map_err(|e| {
+                    eprintln!("❗ Error emitting PROBE event: {}", e);
+                });
+            }
+        });
+    }
+
+    /// 后台探测 ffmpeg 支持的功能，结果通过 UpdateFfmpegCaps 发回
+    fn spawn_caps_probe(&self, cx: &mut EventContext) {
+        let Some(ffmpeg_entry) = self.settings.ffmpeg_entry.clone() else {
+            cx.emit(AppEvent::UpdateFfmpegCaps(None));
+            return;
+        };
+        let mut event_proxy = cx.get_proxy();
+
+        tokio::spawn(async move {
+            let caps = match FfmpegCaps::get_or_probe(&ffmpeg_entry).await {
+                Ok(caps) => Some(caps),
+                Err(e) => {
+                    eprintln!("❌ Failed to probe ffmpeg capabilities: {e}");
+                    None
+                }
+            };
+            let _ = event_proxy
+                .emit(AppEvent::UpdateFfmpegCaps(caps))
+                .map_err(|e| {
+                    eprintln!("❗ Error emitting CAPS event: {}", e);
+                });
+        });
+    }
+
+    /// 首次开始转换时创建调度器，并启动进度监听
+    fn get_or_spawn_scheduler(&mut self, cx: &mut EventContext) -> Scheduler {
+        if let Some(scheduler) = &self.scheduler {
+            return scheduler.clone();
+        }
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<ProgressMsg>();
+        let (scheduler, _) = Scheduler::spawn(
+            self.settings.max_parallel_jobs,
+            self.controls.clone(),
+            tx,
+        );
+        spawn_progress_listener(cx, rx);
+
+        self.scheduler = Some(scheduler.clone());
+        scheduler
+    }
+}
+
+/// 按探测到的流重新计算可选输出格式，尽量保留原来的选择
+fn refresh_output_formats(task: &mut Task) {
+    let input_format = MediaFormat::new(&get_file_extension(&task.input_path));
+    // 提取音轨的任务只能输出音频，导出帧只能输出图片序列
+    let audio_only = task.task_type.ffmpeg().audio_track.is_some();
+    let candidates = match task.task_type {
+        TaskType::FrameExport(_) => MediaFormat::frame_export_formats(),
+        _ => MediaFormat::output_formats_for(input_format.as_ref(), task.media_info.as_ref()),
+    };
+    let formats: Vec<Arc<dyn ConvertibleFormat>> = candidates
+        .into_iter()
+        .map(Arc::from)
+        .filter(|f: &Arc<dyn ConvertibleFormat>| {
+            !audio_only || matches!(f.get_format_type(), FormatType::Audio(_))
+        })
+        .collect();
+    if formats.is_empty() {
+        return;
+    }
+
+    let selected_label = task
+        .supported_output_formats
+        .get(task.selected_output_format)
+        .map(|f| f.get_label());
+    task.selected_output_format = formats
+        .iter()
+        .position(|f| Some(f.get_label()) == selected_label)
+        .unwrap_or(0);
+    task.supported_output_formats = formats;
+    fit_codecs(task);
+
+    if task.auto_rename {
+        task.output_path = task.default_output_path(false);
+    }
+}
+
+/// 手动选择的流列表，没有开启手动选流时为 None
+fn stream_selection_mut(task: &mut Task) -> Option<&mut Vec<StreamSelection>> {
+    task.task_type.ffmpeg_mut().stream_map.as_mut()
+}
+
+/// 打开文件选择框，按音视频、字幕和图片分类过滤
+fn media_file_dialog() -> FileDialog {
+    let video_formats = Video::all().iter().map(|f| f.ext()).collect::<Vec<_>>();
+    let audio_formats = Audio::all().iter().map(|f| f.ext()).collect::<Vec<_>>();
+    let subtitle_formats = Subtitle::all().iter().map(|f| f.ext()).collect::<Vec<_>>();
+    let image_formats = Image::all()
+        .iter()
+        .map(|f| f.ext())
+        .chain(["jpeg", "tif", "gif"])
+        .collect::<Vec<_>>();
+    FileDialog::new()
+        .add_filter("video", video_formats.as_slice())
+        .add_filter("audio", audio_formats.as_slice())
+        .add_filter("subtitle", subtitle_formats.as_slice())
+        .add_filter("image", image_formats.as_slice())
+        .add_filter("All Files", &["*"])
+}
+
+/// 拼接任务可选的输出格式，按第一个输入决定；音频可视化和字幕不能拼接
+fn concat_output_formats(first_input: &str) -> Option<Vec<Arc<dyn ConvertibleFormat>>> {
+    let input_format = MediaFormat::new(&get_file_extension(first_input))?;
+    let formats: Vec<Arc<dyn ConvertibleFormat>> =
+        MediaFormat::get_supported_output_formats(&input_format)
+            .into_iter()
+            .map(Arc::from)
+            .filter(|f: &Arc<dyn ConvertibleFormat>| {
+                matches!(f.get_format_type(), FormatType::Audio(_) | FormatType::Video(_))
+            })
+            .collect();
+    (!formats.is_empty()).then_some(formats)
+}
+
+/// 调整顺序后第一个输入可能变了，自动命名的输出跟着变
+fn sync_concat_first_input(task: &mut Task) {
+    let TaskType::Concat(concat_task) = &task.task_type else {
+        return;
+    };
+    let Some(first) = concat_task.inputs.first() else {
+        return;
+    };
+    task.input_path = first.path.to_string_lossy().to_string();
+    if task.auto_rename {
+        task.output_path = task.default_output_path(false);
+    }
+}
+
+/// 换了输出格式后，去掉新容器装不下的编码，退回默认编码；
+/// 选音轨只对音频输出有意义，选字幕轨只对字幕输出有意义，外挂字幕只加到视频输出，
+/// 质量只对有损图片有意义，GIF 自己分两遍生成调色板，没有音频的输出不做标准化
+fn fit_codecs(task: &mut Task) {
+    let format_type = task.supported_output_formats[task.selected_output_format].get_format_type();
+    let ffmpeg_task = task.task_type.ffmpeg_mut();
+    if !matches!(format_type, FormatType::Audio(_)) {
+        ffmpeg_task.audio_track = None;
+    }
+    if !matches!(format_type, FormatType::Subtitle(_)) {
+        ffmpeg_task.subtitle_track = None;
+    }
+    if !matches!(format_type, FormatType::Video(_)) {
+        ffmpeg_task.subtitle_file = None;
+    }
+    if !matches!(&format_type, FormatType::Image(image) if image.is_lossy()) {
+        ffmpeg_task.image_quality = None;
+    }
+    if matches!(format_type, FormatType::Video(Video::Gif)) {
+        ffmpeg_task.two_pass = false;
+    }
+    if matches!(
+        format_type,
+        FormatType::Subtitle(_) | FormatType::Image(_) | FormatType::Video(Video::Gif)
+    ) {
+        ffmpeg_task.normalization = None;
+    }
+    if ffmpeg_task
+        .video_codec
+        .is_some_and(|c| !format_type.video_codecs().contains(&c))
+    {
+        ffmpeg_task.video_codec = None;
+    }
+    if ffmpeg_task
+        .audio_codec
+        .is_some_and(|c| !format_type.audio_codecs().contains(&c))
+    {
+        ffmpeg_task.audio_codec = None;
+    }
+}
+
+fn spawn_progress_listener(
+    cx: &mut EventContext,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<ProgressMsg>,
+) {
+    let mut event_proxy = cx.get_proxy();
+
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                ProgressMsg::Started { task_id } => {
+                    let _ = event_proxy
+                        .emit(AppEvent::UpdateStatus(task_id, TaskStatus::Running))
+                        .map_err(|e| {
+                            eprintln!("❗ Error emitting STARTED event: {}", e);
+                        });
+                }
+                ProgressMsg::Progress { task_id, progress } => {
+                    let _ = event_proxy
+                        .emit(AppEvent::UpdateProgress(task_id, progress))
+                        .map_err(|e| {
+                            eprintln!("❗ Error emitting PROGRESS event: {}", e);
+                        });
+                }
+                ProgressMsg::Paused { task_id } => {
+                    let _ = event_proxy
+                        .emit(AppEvent::UpdateStatus(task_id, TaskStatus::Paused))
+                        .map_err(|e| {
+                            eprintln!("❗ Error emitting PAUSED event: {}", e);
+                        });
+                }
+                ProgressMsg::Resumed { task_id } => {
+                    let _ = event_proxy
+                        .emit(AppEvent::UpdateStatus(task_id, TaskStatus::Running))
+                        .map_err(|e| {
+                            eprintln!("❗ Error emitting RESUMED event: {}", e);
+                        });
+                }
+                ProgressMsg::Cancelled { task_id } => {
+                    let _ = event_proxy
+                        .emit(AppEvent::UpdateStatus(task_id, TaskStatus::Cancelled))
+                        .map_err(|e| {
+                            eprintln!("❗ Error emitting CANCELLED event: {}", e);
+                        });
+                }
+                ProgressMsg::Measured {
+                    task_id,
+                    measurement,
+                } => {
+                    let _ = event_proxy
+                        .emit(AppEvent::UpdateLoudness(task_id, measurement))
+                        .map_err(|e| {
+                            eprintln!("❗ Error emitting MEASURED event: {}", e);
+                        });
+                }
+                ProgressMsg::Done { task_id } => {
+                    let _ = event_proxy
+                        .emit(AppEvent::MarkDone(task_id, true))
+                        .map_err(|e| {
+                            eprintln!("❗ Error emitting COMPLETE event: {}", e);
+                        });
+                }
+                ProgressMsg::Error { task_id, error } => {
+                    let _ = event_proxy
+                        .emit(AppEvent::MarkDone(task_id.clone(), false))
+                        .map_err(|e| {
+                            eprintln!("❗ Error emitting ERROR event: {}", e);
+                        });
+                    // 不能用 err_msgbox!，它会 return 掉整个监听循环
+                    rfd::MessageDialog::new()
+                        .set_title("Error")
+                        .set_description(format!("Task {task_id} failed\nErr: {error}"))
+                        .show();
+                }
+            }
+        }
+    });
+}
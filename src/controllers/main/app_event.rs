@@ -1,24 +1,109 @@
-use crate::{models::{app_settings::AppSettings, task::Task}, utils::ffmpeg_wrapper::FfmpegEntry};
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{
+    models::{
+        app_settings::AppSettings,
+        codec::{AudioCodec, VideoCodec},
+        gif::GifOptions,
+        loudness::{LoudnessMeasurement, Normalization},
+        media_info::MediaInfo,
+        output_params::Resolution,
+        rate_control::{AudioRateControl, VideoRateControl},
+        stream_map::Disposition,
+        subtitle::SubtitleMode,
+        task::{Task, TaskStatus},
+        trim::{SeekMode, TrimPoint},
+    },
+    utils::{
+        ffmpeg_caps::FfmpegCaps, ffmpeg_progress::FfmpegProgress, ffmpeg_sequence::FrameSampling,
+        ffmpeg_wrapper::FfmpegEntry,
+    },
+};
 
 type TaskId = String;
 
 pub enum AppEvent {
     AddTask(Option<String>),
+    /// 按给定顺序拼接多个文件，`None` 时弹出文件选择框
+    AddConcatTask(Option<Vec<String>>),
+    AddConcatInputs(TaskId),
+    /// 把第几个输入上移（负数）或下移
+    MoveConcatInput(TaskId, usize, isize),
+    RemoveConcatInput(TaskId, usize),
+    UpdateConcatInputInfo(TaskId, PathBuf, Result<MediaInfo, String>),
+    /// 为视频的每一路音频各建一个提取任务
+    ExtractAudio(TaskId),
+    /// 音频输出只取第几路音频，`None` 时交给 ffmpeg 选
+    ChangeAudioTrack(TaskId, Option<usize>),
+    /// 在 ffmpeg 默认选流和手动选流之间切换
+    ToggleStreamMap(TaskId),
+    /// 以下的 usize 是流在选择列表里的位置
+    ToggleStreamIncluded(TaskId, usize),
+    MoveStream(TaskId, usize, isize),
+    ToggleStreamDisposition(TaskId, usize, Disposition),
+    /// 字幕输出取第几路字幕，`None` 时取第一路
+    ChangeSubtitleTrack(TaskId, Option<usize>),
+    /// 选择封装或烧录进视频输出的字幕文件
+    ChooseSubtitleFile(TaskId),
+    ClearSubtitleFile(TaskId),
+    ChangeSubtitleMode(TaskId, SubtitleMode),
+    /// 用视频任务的输入建一个导出帧的任务
+    ExportFrames(TaskId),
+    ChangeFrameSampling(TaskId, FrameSampling),
+    /// 把文件夹里的图片序列编码成视频，`None` 时弹出文件夹选择框
+    AddImageSequenceTask(Option<PathBuf>),
     RemoveTask(TaskId),
     UpdateTask(String, Task),
     ToggleAutoRename(TaskId),
     ChangeOutputFormat(TaskId, usize),
     StartConvert(Option<Vec<TaskId>>),
+    StartSelected,
+    RetryTasks(Option<Vec<TaskId>>),
+    ToggleTaskSelected(TaskId),
     RemoveAll,
     ToggleConifg(TaskId),
     ConfigWindowClosing,
-    UpdateProgress(TaskId, f32),
+    UpdateProgress(TaskId, FfmpegProgress),
     MarkDone(TaskId,bool),
-    UpdateAppSettings(Box<dyn FnOnce(&mut AppSettings) + Send>),
+    UpdateStatus(TaskId, TaskStatus),
+    CancelTask(TaskId),
+    CancelAll,
+    PauseTask(TaskId),
+    ResumeTask(TaskId),
+    UpdateMaxParallelJobs(usize),
+    UpdateAppSettings(Box<dyn Fn(&mut AppSettings) + Send>),
     UpdateFfmpegEntry(Option<FfmpegEntry>),
+    UpdateFfmpegCaps(Option<Arc<FfmpegCaps>>),
     ToggleSettingsWindow,
     ToggleFormatSelectorWindow(TaskId),
     FormatSelectorWindowClosing,
-    ChangeAudioBitrate(TaskId,Option<u32>),
-    ChangeVideoBitrate(TaskId,Option<u32>),
+    ChangeAudioRate(TaskId, Option<AudioRateControl>),
+    ChangeVideoRate(TaskId, Option<VideoRateControl>),
+    ToggleTwoPass(TaskId),
+    ToggleStreamCopy(TaskId),
+    ChangeResolution(TaskId, Option<Resolution>),
+    ChangeFrameRate(TaskId, Option<u32>),
+    ChangeSampleRate(TaskId, Option<u32>),
+    ChangeImageQuality(TaskId, Option<u32>),
+    /// 修改 GIF 参数，改完不合法时提示并保留原值
+    UpdateGifOptions(TaskId, Box<dyn Fn(&mut GifOptions) + Send>),
+    /// `None` 时不做标准化
+    ChangeNormalization(TaskId, Option<Normalization>),
+    /// 运行时测得的响度
+    UpdateLoudness(TaskId, LoudnessMeasurement),
+    ToggleStripMetadata(TaskId),
+    ChangeVideoCodec(TaskId, Option<VideoCodec>),
+    ChangeAudioCodec(TaskId, Option<AudioCodec>),
+    UpdateMediaInfo(TaskId, Result<MediaInfo, String>),
+    /// 界面输入的时间文本，空串表示清除
+    ChangeTrimPoint(TaskId, TrimPoint, String),
+    ChangeSeekMode(TaskId, SeekMode),
+    /// 把任务当前的配置存为指定名字的预设
+    SavePreset(TaskId, String),
+    DeletePreset(String),
+    /// `None` 时套用到所有勾选的任务
+    ApplyPreset(String, Option<Vec<TaskId>>),
+    ChangeDefaultPreset(Option<String>),
+    ImportPresets,
+    ExportPresets,
 }
\ No newline at end of file
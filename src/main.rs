@@ -3,26 +3,52 @@ use std::{collections::HashMap, path::Path, sync::Arc};
 use controllers::main::{app_data::AppData, app_event::AppEvent};
 use models::{
     app_settings::AppSettings,
-    task::{Task, TaskStatus},
+    preset_store,
+    task::{Task, TaskStatus, TaskType},
+};
+use utils::{
+    ffmpeg_caps::FfmpegCaps, fs::shorten_path, task_control::TaskControls,
+    utils::format_timestamp,
 };
-use utils::fs::shorten_path;
 use views::pages::task_config_page;
 use vizia::{
-    icons::{ICON_SETTINGS, ICON_TRASH},
+    icons::{
+        ICON_MUSIC, ICON_PHOTO, ICON_PLAYER_PAUSE, ICON_PLAYER_PLAY, ICON_PLAYER_STOP,
+        ICON_SETTINGS, ICON_TRASH,
+    },
     prelude::*,
 };
 
+mod cli;
 mod controllers;
-mod macros;
 mod models;
 mod utils;
 mod views;
 
 #[tokio::main]
 async fn main() -> Result<(), ApplicationError> {
+    if cli::is_headless() {
+        std::process::exit(cli::run().await);
+    }
+
+    utils::logger::init(log::LevelFilter::Info, false);
+
     let app_settings = AppSettings::omg_default().await;
     println!("AppSettings: {:?}", app_settings);
 
+    let ffmpeg_caps = match &app_settings.ffmpeg_entry {
+        Some(entry) => match FfmpegCaps::get_or_probe(entry).await {
+            Ok(caps) => Some(caps),
+            Err(e) => {
+                eprintln!("❌ Failed to probe ffmpeg capabilities: {e}");
+                None
+            }
+        },
+        None => None,
+    };
+
+    let presets = preset_store::load_all();
+
     Application::new(move |cx| {
         AppData {
             show_config_page: false,
@@ -32,6 +58,10 @@ async fn main() -> Result<(), ApplicationError> {
             task_ids: vec![],
             tasks: HashMap::new(),
             show_format_selctor_window: false,
+            controls: TaskControls::new(),
+            scheduler: None,
+            ffmpeg_caps: ffmpeg_caps.clone(),
+            presets: presets.clone(),
         }
         .build(cx);
 
@@ -46,10 +76,50 @@ async fn main() -> Result<(), ApplicationError> {
                 HStack::new(cx, |cx| {
                     Button::new(cx, |cx| Label::new(cx, "Add Task"))
                         .on_press(|ex| ex.emit(AppEvent::AddTask(None)));
+                    Button::new(cx, |cx| Label::new(cx, "Join Files"))
+                        .on_press(|ex| ex.emit(AppEvent::AddConcatTask(None)));
+                    Button::new(cx, |cx| Label::new(cx, "Image Sequence"))
+                        .on_press(|ex| ex.emit(AppEvent::AddImageSequenceTask(None)));
                     Button::new(cx, |cx| Label::new(cx, "Remove All"))
                         .on_press(|ex| ex.emit(AppEvent::RemoveAll));
                     Button::new(cx, |cx| Label::new(cx, "Start Convert"))
                         .on_press(|ex| ex.emit(AppEvent::StartConvert(None)));
+                    Button::new(cx, |cx| Label::new(cx, "Start Selected"))
+                        .on_press(|ex| ex.emit(AppEvent::StartSelected));
+                    Button::new(cx, |cx| Label::new(cx, "Retry Failed"))
+                        .on_press(|ex| ex.emit(AppEvent::RetryTasks(None)));
+                    Button::new(cx, |cx| Label::new(cx, "Cancel All"))
+                        .on_press(|ex| ex.emit(AppEvent::CancelAll));
+
+                    // 新任务自动套用这里选中的预设，按钮把它套用到勾选的任务
+                    let default_preset = AppData::settings.then(AppSettings::default_preset);
+                    Binding::new(cx, AppData::presets, move |cx, presets| {
+                        let names: Vec<String> =
+                            presets.get(cx).iter().map(|p| p.name.clone()).collect();
+                        let options = std::iter::once("No Preset".to_string())
+                            .chain(names.iter().cloned())
+                            .collect::<Vec<_>>();
+                        let names2 = names.clone();
+                        let selected = default_preset.map(move |name| {
+                            name.as_ref()
+                                .and_then(|n| names.iter().position(|p| p == n))
+                                .map_or(0, |i| i + 1)
+                        });
+                        PickList::new(cx, presets.map(move |_| options.clone()), selected, true)
+                            .width(Pixels(160.0))
+                            .on_select(move |ex, idx| {
+                                ex.emit(AppEvent::ChangeDefaultPreset(
+                                    idx.checked_sub(1).map(|i| names2[i].clone()),
+                                ));
+                            });
+                    });
+                    Button::new(cx, |cx| Label::new(cx, "Apply Preset"))
+                        .on_press(move |ex| {
+                            if let Some(name) = default_preset.get(ex) {
+                                ex.emit(AppEvent::ApplyPreset(name, None));
+                            }
+                        })
+                        .disabled(default_preset.map(|name| name.is_none()));
 
                     Button::new(cx, |cx| Label::new(cx, "Settings"))
                         .on_press(|ex| ex.emit(AppEvent::ToggleSettingsWindow));
@@ -69,14 +139,21 @@ async fn main() -> Result<(), ApplicationError> {
                             Binding::new(cx, item.then(Task::status), move |cx, status| {
                                 let index4click = Arc::clone(&index);
                                 let index4togglecfg = Arc::clone(&index);
+                                let index4control = Arc::clone(&index);
+                                let index4select = Arc::clone(&index);
+                                let index4start = Arc::clone(&index);
 
                                 let input_path = item.then(Task::input_path);
                                 let output_path = item.then(Task::output_path);
 
-                                let input_filename = input_path.map(|path| {
-                                    let path = Path::new(path);
-                                    let p = shorten_path(path, 50);
-                                    p
+                                // 拼接任务按顺序列出所有输入
+                                let input_filename = item.map(|task| match &task.task_type {
+                                    TaskType::Concat(concat_task) => concat_task.input_names(),
+                                    TaskType::ImageSequence(sequence_task) => {
+                                        let sequence = &sequence_task.sequence;
+                                        shorten_path(&sequence.dir.join(&sequence.pattern), 50)
+                                    }
+                                    _ => shorten_path(Path::new(&task.input_path), 50),
                                 });
 
                                 let output_filename = output_path.map(|path| {
@@ -89,6 +166,16 @@ async fn main() -> Result<(), ApplicationError> {
                                     path.split('.').last().unwrap_or_default().to_string()
                                 });
 
+                                let media_summary = item.map(|task| match &task.task_type {
+                                    TaskType::Concat(concat_task) => concat_task.summary(),
+                                    TaskType::ImageSequence(sequence_task) => sequence_task.summary(),
+                                    _ => task
+                                        .media_info
+                                        .as_ref()
+                                        .map(|i| i.summary())
+                                        .unwrap_or_default(),
+                                });
+
                                 let progress = item.then(Task::progress);
 
                                 let index4color = Arc::clone(&index);
@@ -105,9 +192,20 @@ async fn main() -> Result<(), ApplicationError> {
                                     VStack::new(cx, |cx| {
                                         VStack::new(cx, |cx| {
                                             HStack::new(cx, |cx| {
+                                                Checkbox::new(cx, item.then(Task::selected))
+                                                    .on_toggle(move |cx| {
+                                                        cx.emit(AppEvent::ToggleTaskSelected(
+                                                            (&index4select).to_string(),
+                                                        ));
+                                                    })
+                                                    .padding_right(Pixels(5.0));
                                                 Label::new(cx, input_format).class("badge-label");
                                                 Label::new(cx, input_filename)
                                                     .padding_left(Pixels(5.0));
+                                                Label::new(cx, media_summary)
+                                                    .class("badge-label")
+                                                    .class("lite")
+                                                    .left(Pixels(5.0));
                                             });
 
                                             HStack::new(cx, |cx| {
@@ -126,14 +224,75 @@ async fn main() -> Result<(), ApplicationError> {
                                                 );
                                                 Label::new(cx, output_filename)
                                                     .padding_left(Pixels(5.0));
+                                                Label::new(
+                                                    cx,
+                                                    item.map(|task| task.job_kind().to_string()),
+                                                )
+                                                .class("badge-label")
+                                                .class("lite")
+                                                .left(Pixels(5.0));
                                             });
                                         })
                                         .class("task-paths")
                                         .alignment(Alignment::Left);
 
-                                        if status.get(cx) == TaskStatus::Queued {
+                                        let current_status = status.get(cx);
+                                        if matches!(
+                                            current_status,
+                                            TaskStatus::Queued
+                                                | TaskStatus::Failed
+                                                | TaskStatus::Cancelled
+                                        ) {
                                             HStack::new(cx, |cx| {
                                                 let index4togglecfg2 = Arc::clone(&index4togglecfg);
+                                                let is_queued = current_status == TaskStatus::Queued;
+                                                Button::new(cx, |cx| Svg::new(cx, ICON_PLAYER_PLAY))
+                                                    .on_press(move |cx| {
+                                                        let ids = vec![(&index4start).to_string()];
+                                                        if is_queued {
+                                                            cx.emit(AppEvent::StartConvert(Some(ids)));
+                                                        } else {
+                                                            cx.emit(AppEvent::RetryTasks(Some(ids)));
+                                                        }
+                                                    })
+                                                    .class("rounded-btn");
+                                                // 只有同时带画面和音频的单文件任务才能提取音轨
+                                                let cannot_extract = item.map(|task| {
+                                                    let is_plain = matches!(
+                                                        &task.task_type,
+                                                        TaskType::Ffmpeg(t) if t.audio_track.is_none()
+                                                    );
+                                                    !(is_plain
+                                                        && task.media_info.as_ref().is_some_and(
+                                                            |i| i.has_video() && i.has_audio(),
+                                                        ))
+                                                });
+                                                let index4extract = Arc::clone(&index4togglecfg);
+                                                Button::new(cx, |cx| Svg::new(cx, ICON_MUSIC))
+                                                    .on_press(move |cx| {
+                                                        cx.emit(AppEvent::ExtractAudio(
+                                                            (&index4extract).to_string(),
+                                                        ));
+                                                    })
+                                                    .disabled(cannot_extract)
+                                                    .class("rounded-btn");
+                                                // 单文件任务的输入带画面时可以导出帧
+                                                let cannot_export = item.map(|task| {
+                                                    !(matches!(task.task_type, TaskType::Ffmpeg(_))
+                                                        && task
+                                                            .media_info
+                                                            .as_ref()
+                                                            .is_some_and(|i| i.has_video()))
+                                                });
+                                                let index4frames = Arc::clone(&index4togglecfg);
+                                                Button::new(cx, |cx| Svg::new(cx, ICON_PHOTO))
+                                                    .on_press(move |cx| {
+                                                        cx.emit(AppEvent::ExportFrames(
+                                                            (&index4frames).to_string(),
+                                                        ));
+                                                    })
+                                                    .disabled(cannot_export)
+                                                    .class("rounded-btn");
                                                 Button::new(cx, |cx| Svg::new(cx, ICON_SETTINGS))
                                                     .on_press(move |cx| {
                                                         cx.emit(AppEvent::ToggleConifg(
@@ -153,13 +312,71 @@ async fn main() -> Result<(), ApplicationError> {
                                             })
                                             .class("task-btns-row");
                                         }
+
+                                        if matches!(
+                                            current_status,
+                                            TaskStatus::Running | TaskStatus::Paused
+                                        ) {
+                                            HStack::new(cx, |cx| {
+                                                let index4cancel = Arc::clone(&index4control);
+                                                if current_status == TaskStatus::Paused {
+                                                    Button::new(cx, |cx| {
+                                                        Svg::new(cx, ICON_PLAYER_PLAY)
+                                                    })
+                                                    .on_press(move |cx| {
+                                                        cx.emit(AppEvent::ResumeTask(
+                                                            (&index4control).to_string(),
+                                                        ));
+                                                    })
+                                                    .class("rounded-btn");
+                                                } else {
+                                                    Button::new(cx, |cx| {
+                                                        Svg::new(cx, ICON_PLAYER_PAUSE)
+                                                    })
+                                                    .on_press(move |cx| {
+                                                        cx.emit(AppEvent::PauseTask(
+                                                            (&index4control).to_string(),
+                                                        ));
+                                                    })
+                                                    .class("rounded-btn");
+                                                }
+                                                Button::new(cx, |cx| Svg::new(cx, ICON_PLAYER_STOP))
+                                                    .class("rounded-btn")
+                                                    .class("del-btn")
+                                                    .variant(ButtonVariant::Text)
+                                                    .on_press(move |cx| {
+                                                        cx.emit(AppEvent::CancelTask(
+                                                            (&index4cancel).to_string(),
+                                                        ))
+                                                    });
+                                            })
+                                            .class("task-btns-row");
+                                        }
                                     });
 
                                     match status.get(cx) {
-                                        TaskStatus::Running => {
-                                            Binding::new(cx, progress, |cx, progress| {
-                                                let progress_txt =
-                                                    format!("{:.0}%", progress.get(cx) * 100.0);
+                                        TaskStatus::Running | TaskStatus::Paused => {
+                                            let is_paused = status.get(cx) == TaskStatus::Paused;
+                                            let progress_info = item.then(Task::progress_info);
+                                            Binding::new(cx, progress_info, move |cx, info| {
+                                                let info = info.get(cx).unwrap_or_default();
+                                                let mut progress_txt = match info.ratio() {
+                                                    Some(ratio) => format!("{:.0}%", ratio * 100.0),
+                                                    None => format_timestamp(
+                                                        info.out_time_secs().unwrap_or_default(),
+                                                    ),
+                                                };
+                                                if let Some(frames) = info.frame_label() {
+                                                    progress_txt.push_str(&format!(" · {frames}"));
+                                                }
+                                                if let Some(pass) = info.pass_label() {
+                                                    progress_txt.push_str(&format!(" · {pass}"));
+                                                }
+                                                if is_paused {
+                                                    progress_txt.push_str(" · Paused");
+                                                } else if let Some(speed) = info.speed {
+                                                    progress_txt.push_str(&format!(" · {:.2}x", speed));
+                                                }
                                                 Label::new(cx, progress_txt)
                                                     .class("badge-label")
                                                     .class("warning");
@@ -171,6 +388,12 @@ async fn main() -> Result<(), ApplicationError> {
                                             });
                                         }
                                         TaskStatus::Done => {
+                                            // 标准化过的任务显示运行前测得的响度
+                                            let loudness = item.then(Task::loudness);
+                                            if let Some(loudness) = loudness.get(cx) {
+                                                Label::new(cx, format!("Measured {loudness}"))
+                                                    .class("p-decs");
+                                            }
                                             Label::new(cx, "Done")
                                                 .position_type(PositionType::Absolute)
                                                 .right(Pixels(10.0))
@@ -181,6 +404,16 @@ async fn main() -> Result<(), ApplicationError> {
                                         TaskStatus::Failed => {
                                             Label::new(cx, "Failed").class("badge-label-error");
                                         }
+                                        TaskStatus::Pending => {
+                                            Label::new(cx, "Pending")
+                                                .class("badge-label")
+                                                .class("lite");
+                                        }
+                                        TaskStatus::Cancelled => {
+                                            Label::new(cx, "Cancelled")
+                                                .class("badge-label")
+                                                .class("lite");
+                                        }
                                         _ => {}
                                     }
                                 })
@@ -202,9 +435,14 @@ async fn main() -> Result<(), ApplicationError> {
             .on_drop(|ex, data| {
                 if let DropData::File(file) = data {
                     println!("Dropped File: {:?}", file);
-                    ex.emit(AppEvent::AddTask(Some(
-                        file.to_str().unwrap_or_default().to_string(),
-                    )));
+                    // 拖入文件夹时按图片序列处理
+                    if file.is_dir() {
+                        ex.emit(AppEvent::AddImageSequenceTask(Some(file.to_path_buf())));
+                    } else {
+                        ex.emit(AppEvent::AddTask(Some(
+                            file.to_str().unwrap_or_default().to_string(),
+                        )));
+                    }
                 }
             })
             .on_hover(|ex| {
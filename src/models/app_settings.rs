@@ -1,19 +1,42 @@
+use std::{fs, path::PathBuf};
+
 use crate::utils::ffmpeg_wrapper::{self, FfmpegEntry};
+use serde::{Deserialize, Serialize};
 use vizia::prelude::*;
 
-#[derive(Lens, Debug, Clone, Data)]
+/// 设置文件的结构版本，字段有不兼容改动时递增并在 `migrate` 中补上迁移
+const SETTINGS_VERSION: i64 = 1;
+const SETTINGS_FILE_NAME: &str = "settings.toml";
+
+#[derive(Lens, Debug, Clone, Data, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub ffmpeg_entry: Option<FfmpegEntry>,
+    /// 同时运行的 ffmpeg 进程数上限
+    pub max_parallel_jobs: usize,
+    /// 新添加的任务自动套用的预设名
+    pub default_preset: Option<String>,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AppSettings {
     pub fn new() -> Self {
-        Self { ffmpeg_entry: None }
+        Self {
+            ffmpeg_entry: None,
+            max_parallel_jobs: default_max_parallel_jobs(),
+            default_preset: None,
+        }
     }
 
     pub fn with_ffmpeg_entry(ffmpeg_entry: FfmpegEntry) -> Self {
         Self {
             ffmpeg_entry: Some(ffmpeg_entry),
+            ..Self::new()
         }
     }
 
@@ -21,11 +44,111 @@ impl AppSettings {
     //     &self.ffmpeg_entry
     // }
 
+    /// 优先读取设置文件，没有时才自动查找 ffmpeg 并写入新的设置文件
     pub async fn omg_default() -> AppSettings {
-        first_run_init().await
+        match AppSettings::load() {
+            Ok(Some(settings)) => return settings,
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("❗ Failed to load settings, falling back to defaults: {e}");
+                backup_broken_settings();
+            }
+        }
+
+        let settings = first_run_init().await;
+        if let Err(e) = settings.save() {
+            eprintln!("❗ Failed to save settings: {e}");
+        }
+        settings
+    }
+
+    /// 设置文件路径，Linux 上为 `$XDG_CONFIG_HOME/converlex/settings.toml`
+    pub fn settings_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("converlex").join(SETTINGS_FILE_NAME))
+    }
+
+    /// 设置文件不存在时返回 `Ok(None)`
+    pub fn load() -> anyhow::Result<Option<AppSettings>> {
+        let Some(path) = Self::settings_path() else {
+            return Ok(None);
+        };
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&path)?;
+        let mut table: toml::Table = toml::from_str(&content)?;
+        let version = table
+            .remove("version")
+            .and_then(|v| v.as_integer())
+            .unwrap_or(0);
+
+        if version > SETTINGS_VERSION {
+            return Err(anyhow::anyhow!(
+                "Settings file version {version} is newer than supported version {SETTINGS_VERSION}"
+            ));
+        }
+
+        migrate(&mut table, version);
+        let settings: AppSettings = table.try_into()?;
+        log::info!("⚙️ Loaded settings from {}", path.display());
+        Ok(Some(settings))
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = Self::settings_path()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut table = toml::Table::try_from(self)?;
+        table.insert("version".to_string(), toml::Value::Integer(SETTINGS_VERSION));
+
+        // 先写临时文件再替换，避免写到一半时留下损坏的设置文件
+        let tmp_path = path.with_extension("toml.tmp");
+        fs::write(&tmp_path, toml::to_string_pretty(&table)?)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
     }
 }
 
+/// 逐版本把旧设置迁移到当前结构
+fn migrate(table: &mut toml::Table, from_version: i64) {
+    let mut version = from_version;
+    while version < SETTINGS_VERSION {
+        // 0: 没有版本号的早期文件，缺少 max_parallel_jobs
+        if version == 0 {
+            table
+                .entry("max_parallel_jobs")
+                .or_insert(toml::Value::Integer(default_max_parallel_jobs() as i64));
+        }
+        version += 1;
+        log::info!("⚙️ Migrated settings to version {version}");
+    }
+}
+
+/// 解析失败的设置文件改名保留，避免被默认设置覆盖
+fn backup_broken_settings() {
+    let Some(path) = AppSettings::settings_path() else {
+        return;
+    };
+    let backup = path.with_extension("toml.bak");
+    if let Err(e) = fs::rename(&path, &backup) {
+        eprintln!("❗ Failed to back up settings file: {e}");
+    } else {
+        eprintln!("⚙️ Broken settings file moved to {}", backup.display());
+    }
+}
+
+/// CPU 核数的一半，至少为 1
+pub fn default_max_parallel_jobs() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get() / 2)
+        .unwrap_or(1)
+        .max(1)
+}
+
 async fn first_run_init() -> AppSettings {
     let ffmpeg_entry = ffmpeg_wrapper::find_ffmpeg()
         .await
@@ -35,3 +158,53 @@ async fn first_run_init() -> AppSettings {
 
     settings
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_toml() {
+        let settings = AppSettings {
+            ffmpeg_entry: Some(FfmpegEntry::Path("/usr/bin/ffmpeg".into())),
+            max_parallel_jobs: 3,
+            default_preset: Some("Web 720p".into()),
+        };
+        let text = toml::to_string_pretty(&settings).unwrap();
+        let loaded: AppSettings = toml::from_str(&text).unwrap();
+
+        assert_eq!(loaded.ffmpeg_entry, settings.ffmpeg_entry);
+        assert_eq!(loaded.max_parallel_jobs, 3);
+        assert_eq!(loaded.default_preset.as_deref(), Some("Web 720p"));
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let loaded: AppSettings = toml::from_str("").unwrap();
+
+        assert_eq!(loaded.ffmpeg_entry, None);
+        assert_eq!(loaded.max_parallel_jobs, default_max_parallel_jobs());
+        assert_eq!(loaded.default_preset, None);
+    }
+
+    #[test]
+    fn migrates_unversioned_files() {
+        let mut table: toml::Table = toml::from_str("ffmpeg_entry = \"Env\"").unwrap();
+        migrate(&mut table, 0);
+
+        assert_eq!(
+            table["max_parallel_jobs"].as_integer(),
+            Some(default_max_parallel_jobs() as i64)
+        );
+        let loaded: AppSettings = table.try_into().unwrap();
+        assert_eq!(loaded.ffmpeg_entry, Some(FfmpegEntry::Env));
+    }
+
+    #[test]
+    fn migration_keeps_existing_values() {
+        let mut table: toml::Table = toml::from_str("max_parallel_jobs = 7").unwrap();
+        migrate(&mut table, 0);
+
+        assert_eq!(table["max_parallel_jobs"].as_integer(), Some(7));
+    }
+}
@@ -1,5 +1,8 @@
 pub mod convert_config;
-pub mod media_format;
-pub mod task;
-pub mod convertible_format;
-pub mod app_settings;
\ No newline at end of file
+pub mod app_settings;
+pub mod preset_store;
+
+pub use converlex_core::models::{
+    codec, convertible_format, gif, loudness, media_format, media_info, output_params, preset,
+    rate_control, stream_copy, stream_map, subtitle, task, trim,
+};
@@ -0,0 +1,175 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{app_settings::AppSettings, preset::Preset};
+
+/// 预设文件的结构版本，和设置文件分开计数
+const PRESETS_VERSION: i64 = 1;
+const PRESETS_FILE_NAME: &str = "presets.toml";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PresetFile {
+    #[serde(default)]
+    version: i64,
+    #[serde(default)]
+    presets: Vec<Preset>,
+}
+
+/// 和设置文件放在同一目录
+pub fn presets_path() -> Option<PathBuf> {
+    AppSettings::settings_path().map(|path| path.with_file_name(PRESETS_FILE_NAME))
+}
+
+/// 内置预设在前，用户预设在后；读取失败时只返回内置预设
+pub fn load_all() -> Vec<Preset> {
+    let mut presets = Preset::builtins();
+    let Some(path) = presets_path() else {
+        return presets;
+    };
+    if !path.exists() {
+        return presets;
+    }
+    match read_presets(&path) {
+        Ok(user_presets) => {
+            println!("⚙️ Loaded {} presets from {}", user_presets.len(), path.display());
+            merge(&mut presets, user_presets);
+        }
+        Err(e) => eprintln!("❗ Failed to load presets: {e}"),
+    }
+    presets
+}
+
+/// 只保存用户预设
+pub fn save_user_presets(presets: &[Preset]) -> anyhow::Result<()> {
+    let path =
+        presets_path().ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // 先写临时文件再替换，避免写到一半时留下损坏的预设文件
+    let tmp_path = path.with_extension("toml.tmp");
+    write_presets(&tmp_path, presets)?;
+    fs::rename(&tmp_path, &path)?;
+    Ok(())
+}
+
+/// 读取导入的预设文件，名字重复的以后出现的为准
+pub fn import(path: &Path) -> anyhow::Result<Vec<Preset>> {
+    let mut presets = vec![];
+    merge(&mut presets, read_presets(path)?);
+    Ok(presets)
+}
+
+pub fn export(path: &Path, presets: &[Preset]) -> anyhow::Result<()> {
+    write_presets(path, presets)
+}
+
+/// 按名字合并：同名的用户预设覆盖原有的，内置预设不会被覆盖
+pub fn merge(presets: &mut Vec<Preset>, new_presets: Vec<Preset>) {
+    for preset in new_presets {
+        match presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) if existing.builtin => {
+                eprintln!("❗ Preset \"{}\" conflicts with a built-in preset, skipped", preset.name);
+            }
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+    }
+}
+
+fn read_presets(path: &Path) -> anyhow::Result<Vec<Preset>> {
+    let content = fs::read_to_string(path)?;
+    let file: PresetFile = toml::from_str(&content)?;
+    if file.version > PRESETS_VERSION {
+        return Err(anyhow::anyhow!(
+            "Preset file version {} is newer than supported version {PRESETS_VERSION}",
+            file.version
+        ));
+    }
+    for preset in &file.presets {
+        preset
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid preset \"{}\": {e}", preset.name))?;
+    }
+    Ok(file.presets)
+}
+
+fn write_presets(path: &Path, presets: &[Preset]) -> anyhow::Result<()> {
+    let file = PresetFile {
+        version: PRESETS_VERSION,
+        presets: presets.iter().filter(|p| !p.builtin).cloned().collect(),
+    };
+    fs::write(path, toml::to_string_pretty(&file)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("converlex-presets-{}.toml", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn exports_only_user_presets() {
+        let path = temp_path();
+        let mut presets = Preset::builtins();
+        presets.push(Preset {
+            frame_rate: Some(30),
+            ..Preset::new("Mine", "mkv")
+        });
+        export(&path, &presets).unwrap();
+
+        let imported = import(&path).unwrap();
+        assert_eq!(imported, [presets.pop().unwrap()]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn merge_replaces_user_presets_but_not_builtins() {
+        let mut presets = Preset::builtins();
+        let builtin = presets[0].clone();
+        presets.push(Preset::new("Mine", "mkv"));
+
+        merge(
+            &mut presets,
+            vec![
+                Preset::new(builtin.name.clone(), "mkv"),
+                Preset::new("Mine", "webm"),
+                Preset::new("New", "mp3"),
+            ],
+        );
+
+        assert_eq!(presets[0], builtin);
+        let format = |name: &str| {
+            presets
+                .iter()
+                .find(|p| p.name == name)
+                .map(|p| p.format.clone())
+        };
+        assert_eq!(format("Mine").as_deref(), Some("webm"));
+        assert_eq!(format("New").as_deref(), Some("mp3"));
+    }
+
+    #[test]
+    fn rejects_invalid_or_newer_files() {
+        let path = temp_path();
+
+        fs::write(
+            &path,
+            "version = 1\n[[presets]]\nname = \"Bad\"\nformat = \"mp4\"\nframe_rate = 0\n",
+        )
+        .unwrap();
+        assert!(import(&path).is_err());
+
+        fs::write(&path, format!("version = {}\n", PRESETS_VERSION + 1)).unwrap();
+        assert!(import(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
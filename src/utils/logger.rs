@@ -0,0 +1,63 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// 把 converlex 自己的日志打印到 stderr，忽略 vizia 等依赖的日志
+struct StderrLogger {
+    /// 无界面模式下 stderr 的最后一行是正在刷新的进度，先清掉再打印
+    clear_line: bool,
+}
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target().starts_with("converlex") && metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        if self.clear_line {
+            eprint!("\r\x1b[K");
+        }
+        eprintln!("{}", record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger { clear_line: false };
+static PROGRESS_LOGGER: StderrLogger = StderrLogger { clear_line: true };
+
+/// 只能调用一次，之后的调用会被忽略。`progress_line` 表示 stderr 上有 `\r` 刷新的进度行
+pub fn init(level: LevelFilter, progress_line: bool) {
+    let logger = match progress_line {
+        true => &PROGRESS_LOGGER,
+        false => &LOGGER,
+    };
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::*;
+
+    fn metadata(target: &str, level: Level) -> Metadata<'_> {
+        Metadata::builder().target(target).level(level).build()
+    }
+
+    #[test]
+    fn only_accepts_converlex_targets_up_to_max_level() {
+        log::set_max_level(LevelFilter::Warn);
+
+        assert!(LOGGER.enabled(&metadata("converlex_core::utils::scheduler", Level::Warn)));
+        assert!(LOGGER.enabled(&metadata("converlex::cli", Level::Error)));
+        assert!(!LOGGER.enabled(&metadata(
+            "converlex_core::utils::ffmpeg_wrapper",
+            Level::Info
+        )));
+        assert!(!LOGGER.enabled(&metadata("vizia_core::context", Level::Error)));
+    }
+}
@@ -1,3 +1,7 @@
-pub mod utils;
-pub mod ffmpeg_wrapper;
-pub mod fs;
\ No newline at end of file
+pub mod fs;
+pub mod logger;
+
+pub use converlex_core::utils::{
+    ffmpeg_caps, ffmpeg_concat, ffmpeg_progress, ffmpeg_sequence, ffmpeg_wrapper, ffprobe,
+    scheduler, task_control, utils,
+};
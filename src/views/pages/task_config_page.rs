@@ -8,12 +8,52 @@ use vizia::{
 use crate::{
     controllers::main::{app_data::AppData, app_event::AppEvent},
     models::{
+        codec::{AudioCodec, VideoCodec},
         convertible_format::FormatType,
+        gif::{GifDither, GifOptions},
+        loudness::Normalization,
+        media_format::Video,
+        output_params::{Resolution, FRAME_RATE_PRESETS, SAMPLE_RATE_PRESETS},
+        rate_control::{AudioRateControl, VideoRateControl},
+        stream_map::Disposition,
+        subtitle::SubtitleMode,
         task::{Task, TaskType},
+        trim::{format_timestamp_ms, SeekMode, TrimPoint},
+    },
+    utils::{
+        ffmpeg_concat::ConcatMethod,
+        ffmpeg_sequence::{FrameSampling, DEFAULT_SEQUENCE_FPS},
+        ffmpeg_wrapper::FfmpegTask,
+        utils::format_size,
     },
 };
 
+/// “另存为预设”输入框的内容
+#[derive(Lens, Data, Clone)]
+pub struct PresetNameData {
+    name: String,
+}
+
+pub enum PresetNameEvent {
+    UpdateName(String),
+}
+
+impl Model for PresetNameData {
+    fn event(&mut self, _cx: &mut EventContext, event: &mut Event) {
+        event.map(|preset_event, _| match preset_event {
+            PresetNameEvent::UpdateName(name) => {
+                self.name.clone_from(name);
+            }
+        })
+    }
+}
+
 pub fn new(cx: &mut Context) -> Handle<VStack> {
+    PresetNameData {
+        name: Default::default(),
+    }
+    .build(cx);
+
     VStack::new(cx, |cx| {
         Binding::new(cx, AppData::configuring_taskid, |cx, tid| {
             let taskid_opt = tid.get(cx);
@@ -27,33 +67,50 @@ pub fn new(cx: &mut Context) -> Handle<VStack> {
                 let is_auto_rename = item.then(Task::auto_rename);
                 let task_type = item.then(Task::task_type);
                 let task_status = item.then(Task::status);
+                // 任务类型创建后不会变，建页面时判断一次即可
+                let kind = task_type.get(cx);
+                let is_concat = matches!(kind, TaskType::Concat(_));
+                let is_sequence = matches!(kind, TaskType::ImageSequence(_));
+                let is_frame_export = matches!(kind, TaskType::FrameExport(_));
+                // 选流、选轨和外挂字幕只对单文件转换有意义
+                let is_plain = matches!(kind, TaskType::Ffmpeg(_));
 
                 VStack::new(cx, |cx| {
-                    HStack::new(cx, |cx| {
-                        Label::new(cx, "Input").padding_right(Pixels(10.0));
+                    if is_concat {
+                        concat_input_rows(cx, task_type, Arc::clone(&taskid_clone));
+                    } else if is_sequence {
+                        sequence_input_row(cx, task_type);
+                    } else {
+                        HStack::new(cx, |cx| {
+                            Label::new(cx, "Input").padding_right(Pixels(10.0));
 
-                        let input_index = Arc::clone(&taskid_clone);
-                        Textbox::new(cx, input_path).width(Stretch(1.0)).on_edit(
-                            move |cx, new_input| {
-                                cx.emit(AppEvent::UpdateTask(
-                                    (&input_index).to_string(),
-                                    Task {
-                                        input_path: new_input.to_string(),
-                                        output_path: output_path.get(cx).clone(),
-                                        supported_output_formats: supported_output_formats
-                                            .get(cx)
-                                            .clone(),
-                                        selected_output_format: selected_output_format_idx.get(cx),
-                                        auto_rename: is_auto_rename.get(cx),
-                                        progress: 0.0,
-                                        task_type: task_type.get(cx).clone(),
-                                        status: task_status.get(cx).clone(),
-                                    },
-                                ));
-                            },
-                        );
-                    })
-                    .class("config-row");
+                            let input_index = Arc::clone(&taskid_clone);
+                            Textbox::new(cx, input_path).width(Stretch(1.0)).on_edit(
+                                move |cx, new_input| {
+                                    cx.emit(AppEvent::UpdateTask(
+                                        (&input_index).to_string(),
+                                        Task {
+                                            input_path: new_input.to_string(),
+                                            output_path: output_path.get(cx).clone(),
+                                            supported_output_formats: supported_output_formats
+                                                .get(cx)
+                                                .clone(),
+                                            selected_output_format: selected_output_format_idx.get(cx),
+                                            auto_rename: is_auto_rename.get(cx),
+                                            selected: false,
+                                            progress: 0.0,
+                                            progress_info: None,
+                                            media_info: None,
+                                            task_type: task_type.get(cx).clone(),
+                                            status: task_status.get(cx).clone(),
+                                            loudness: None,
+                                        },
+                                    ));
+                                },
+                            );
+                        })
+                        .class("config-row");
+                    }
 
                     HStack::new(cx, |cx| {
                         Label::new(cx, "Output").padding_right(Pixels(10.0));
@@ -72,9 +129,13 @@ pub fn new(cx: &mut Context) -> Handle<VStack> {
                                             .clone(),
                                         selected_output_format: selected_output_format_idx.get(cx),
                                         auto_rename: is_auto_rename.get(cx),
+                                        selected: false,
                                         progress: 0.0,
+                                        progress_info: None,
+                                        media_info: None,
                                         task_type: task_type.get(cx).clone(),
                                         status: task_status.get(cx).clone(),
+                                        loudness: None,
                                     },
                                 ));
                             })
@@ -145,23 +206,25 @@ pub fn new(cx: &mut Context) -> Handle<VStack> {
                     })
                     .class("config-row");
 
-                    let task_type = item.then(Task::task_type);
-                    let task_type = task_type.map(|tt| match tt {
-                        TaskType::Ffmpeg(a) => a.clone(),
-                    });
-
-                    let audio_bitrate = task_type.map(|tt| match tt.audio_bitrate {
-                        Some(new) => new.to_string(),
-                        None => String::default(),
-                    });
+                    preset_rows(cx, Arc::clone(&taskid_clone));
+                    // 图片和视频之间总要重新编码
+                    if is_plain || is_concat {
+                        stream_copy_row(cx, item, Arc::clone(&taskid_clone));
+                    }
+                    if is_plain {
+                        stream_rows(cx, item, Arc::clone(&taskid_clone));
+                    }
+                    if is_frame_export {
+                        frame_sampling_rows(cx, task_type, Arc::clone(&taskid_clone));
+                    }
 
-                    let video_bitrate = task_type.map(|tt| match tt.video_bitrate {
-                        Some(new) => new.to_string(),
-                        None => String::default(),
-                    });
+                    let task_type = item.then(Task::task_type);
+                    let task_type = task_type.map(|tt| tt.ffmpeg().clone());
 
-                    let is_enable_audio_bitrate = task_type.map(|tt| tt.audio_bitrate.is_some());
-                    let is_enable_video_bitrate = task_type.map(|tt| tt.video_bitrate.is_some());
+                    // 拼接任务和图片序列不支持截取
+                    if !is_concat && !is_sequence {
+                        trim_rows(cx, task_type, Arc::clone(&taskid_clone));
+                    }
 
                     let taskid = Arc::new(tid);
                     Binding::new(
@@ -176,137 +239,102 @@ pub fn new(cx: &mut Context) -> Handle<VStack> {
                                 let taskid2 = Arc::clone(&taskid);
                                 let format_type = format_binding.get(cx).get_format_type();
 
-                                match &format_type {
-                                    FormatType::Audio(_audio) => {
-                                        HStack::new(cx, |cx| {
-                                            Checkbox::new(cx, is_enable_audio_bitrate).on_toggle(
-                                                move |ex| {
-                                                    if ex.is_checked() {
-                                                        ex.emit(AppEvent::ChangeAudioBitrate(
-                                                            taskid.to_string(),
-                                                            None,
-                                                        ));
-                                                    } else {
-                                                        ex.emit(AppEvent::ChangeAudioBitrate(
-                                                            taskid.to_string(),
-                                                            Some(128), // Default bitrate
-                                                        ));
-                                                    }
-                                                });
-                                            Label::new(cx, "Audio Bitrate").width(Stretch(1.0)).padding_left(Pixels(5.0));
-                                            Textbox::new(cx, audio_bitrate)
-                                                .on_edit(move |ex, new_text| {
-                                                    let digits_only: String = new_text
-                                                        .chars()
-                                                        .filter(|c| c.is_ascii_digit())
-                                                        .collect();
-                                                    let a = digits_only.parse::<u32>();
-                                                    if let Ok(new_bitrate) = a {
-                                                        ex.emit(AppEvent::ChangeAudioBitrate(
-                                                            taskid2.to_string(),
-                                                            Some(new_bitrate),
-                                                        ));
-                                                    } else {
-                                                        ex.set_text("1");
-                                                        ex.emit(AppEvent::ChangeAudioBitrate(
-                                                            taskid2.to_string(),
-                                                            None,
-                                                        ));
-                                                        ex.focus_next();
-                                                    }
-                                                })
-                                                .width(Pixels(100.0));
-                                        })
-                                        .class("config-row");
-                                    }
-                                    FormatType::Video(_video) => {
-                                        VStack::new(cx, |cx| {
-                                            let taskid2 = Arc::clone(&taskid);
-                                            let taskid3 = Arc::clone(&taskid);
-                                            HStack::new(cx, |cx| {
-                                                Checkbox::new(cx, is_enable_video_bitrate).on_toggle(
-                                                move |ex| {
-                                                    if ex.is_checked() {
-                                                        ex.emit(AppEvent::ChangeVideoBitrate(
-                                                            taskid3.to_string(),
-                                                            None,
-                                                        ));
-                                                    } else {
-                                                        ex.emit(AppEvent::ChangeVideoBitrate(
-                                                            taskid3.to_string(),
-                                                            Some(128), // Default bitrate
-                                                        ));
-                                                    }
-                                                });
-                                                Label::new(cx, "Video Bitrate").width(Stretch(1.0)).padding_left(Pixels(5.0));
-                                                Textbox::new(cx, video_bitrate)
-                                                    .on_edit(move |ex, new_text| {
-                                                        let digits_only: String = new_text
-                                                            .chars()
-                                                            .filter(|c| c.is_ascii_digit())
-                                                            .collect();
-                                                        let a = digits_only.parse::<u32>();
-                                                        if let Ok(new_bitrate) = a {
-                                                            ex.emit(AppEvent::ChangeVideoBitrate(
-                                                                taskid.to_string(),
-                                                                Some(new_bitrate),
-                                                            ));
-                                                        } else {
-                                                            ex.set_text("1");
-                                                            ex.emit(AppEvent::ChangeVideoBitrate(
-                                                                taskid.to_string(),
-                                                                None,
-                                                            ));
-                                                            ex.focus_next();
-                                                        }
-                                                    })
-                                                    .width(Pixels(100.0));
-                                            })
-                                            .class("config-row");
-                                            let taskid = Arc::clone(&taskid2);
-                                            HStack::new(cx, |cx| {
-                                                let taskid2 = Arc::clone(&taskid);
-                                                Checkbox::new(cx, is_enable_audio_bitrate).on_toggle(
-                                                move |ex| {
-                                                    if ex.is_checked() {
-                                                        ex.emit(AppEvent::ChangeAudioBitrate(
-                                                            taskid2.to_string(),
-                                                            None,
-                                                        ));
-                                                    } else {
-                                                        ex.emit(AppEvent::ChangeAudioBitrate(
-                                                            taskid2.to_string(),
-                                                            Some(128), // Default bitrate
-                                                        ));
-                                                    }
-                                                });
-                                                Label::new(cx, "Audio Bitrate").width(Stretch(1.0)).padding_left(Pixels(5.0));
-                                                Textbox::new(cx, audio_bitrate)
-                                                    .on_edit(move |ex, new_text| {
-                                                        let digits_only: String = new_text
-                                                            .chars()
-                                                            .filter(|c| c.is_ascii_digit())
-                                                            .collect();
-                                                        let a = digits_only.parse::<u32>();
-                                                        if let Ok(new_bitrate) = a {
-                                                            ex.emit(AppEvent::ChangeAudioBitrate(
-                                                                taskid.to_string(),
-                                                                Some(new_bitrate),
-                                                            ));
-                                                        } else {
-                                                            ex.set_text("1");
-                                                            ex.emit(AppEvent::ChangeAudioBitrate(
-                                                                taskid.to_string(),
-                                                                None,
-                                                            ));
-                                                            ex.focus_next();
-                                                        }
-                                                    })
-                                                    .width(Pixels(100.0));
-                                            })
-                                            .class("config-row");
-                                        });
+                                // 字幕输出没有音视频参数
+                                if let FormatType::Subtitle(_) = format_type {
+                                    if is_plain {
+                                        subtitle_track_row(cx, item, Arc::clone(&taskid));
                                     }
+                                    return;
+                                }
+                                if let FormatType::Image(image) = &format_type {
+                                    let lossy = image.is_lossy();
+                                    image_rows(cx, task_type, Arc::clone(&taskid), lossy);
+                                    return;
+                                }
+                                // GIF 不选编码，只有调色板参数
+                                if let FormatType::Video(Video::Gif) = format_type {
+                                    gif_rows(cx, item, task_type, Arc::clone(&taskid));
+                                    return;
+                                }
+
+                                let video_codecs = format_type.video_codecs();
+                                if !video_codecs.is_empty() {
+                                    let taskid = Arc::clone(&taskid);
+                                    let options = task_type.map(move |_| codec_options(video_codecs));
+                                    let selected = task_type.map(move |tt| {
+                                        tt.video_codec
+                                            .and_then(|c| video_codecs.iter().position(|v| *v == c))
+                                            .map_or(0, |i| i + 1)
+                                    });
+                                    HStack::new(cx, |cx| {
+                                        Label::new(cx, "Video Codec").width(Stretch(1.0));
+                                        PickList::new(cx, options, selected, true)
+                                            .width(Pixels(160.0))
+                                            .on_select(move |ex, idx| {
+                                                ex.emit(AppEvent::ChangeVideoCodec(
+                                                    taskid.to_string(),
+                                                    idx.checked_sub(1).map(|i| video_codecs[i]),
+                                                ));
+                                            });
+                                    })
+                                    .class("config-row");
+                                }
+
+                                let audio_codecs = format_type.audio_codecs();
+                                if !audio_codecs.is_empty() {
+                                    let taskid = Arc::clone(&taskid);
+                                    let options = task_type.map(move |_| codec_options(audio_codecs));
+                                    let selected = task_type.map(move |tt| {
+                                        tt.audio_codec
+                                            .and_then(|c| audio_codecs.iter().position(|a| *a == c))
+                                            .map_or(0, |i| i + 1)
+                                    });
+                                    HStack::new(cx, |cx| {
+                                        Label::new(cx, "Audio Codec").width(Stretch(1.0));
+                                        PickList::new(cx, options, selected, true)
+                                            .width(Pixels(160.0))
+                                            .on_select(move |ex, idx| {
+                                                ex.emit(AppEvent::ChangeAudioCodec(
+                                                    taskid.to_string(),
+                                                    idx.checked_sub(1).map(|i| audio_codecs[i]),
+                                                ));
+                                            });
+                                    })
+                                    .class("config-row");
+                                }
+
+                                if !matches!(format_type, FormatType::Audio(_)) {
+                                    video_rate_row(
+                                        cx,
+                                        task_type,
+                                        Arc::clone(&taskid),
+                                        format_type.effective_video_codec(None),
+                                    );
+                                    resolution_row(cx, task_type, Arc::clone(&taskid));
+                                    // 图片序列没有原始帧率，不选时用默认帧率
+                                    let original = if is_sequence {
+                                        format!("{DEFAULT_SEQUENCE_FPS} fps (Default)")
+                                    } else {
+                                        "Original".to_string()
+                                    };
+                                    frame_rate_row(cx, task_type, Arc::clone(&taskid), original);
+                                }
+                                if matches!(format_type, FormatType::Video(_)) && is_plain {
+                                    subtitle_file_rows(cx, task_type, Arc::clone(&taskid));
+                                }
+                                if matches!(format_type, FormatType::Audio(_)) && is_plain {
+                                    audio_track_row(cx, item, Arc::clone(&taskid));
+                                }
+                                sample_rate_row(cx, task_type, Arc::clone(&taskid));
+                                audio_rate_row(
+                                    cx,
+                                    task_type,
+                                    taskid2,
+                                    format_type.effective_audio_codec(None),
+                                );
+                                // 图片序列没有音频
+                                if !is_sequence {
+                                    normalization_rows(cx, item, task_type, taskid);
                                 }
                             });
                         },
@@ -316,3 +344,1147 @@ pub fn new(cx: &mut Context) -> Handle<VStack> {
         });
     })
 }
+
+/// 套用预设，以及把当前配置另存为预设
+fn preset_rows(cx: &mut Context, taskid: Arc<String>) {
+    let taskid2 = Arc::clone(&taskid);
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Preset").width(Stretch(1.0));
+        // 预设套用后配置就可以再改，所以不标记当前预设，只作为动作入口
+        let options = AppData::presets.map(|presets| {
+            std::iter::once("Apply Preset...".to_string())
+                .chain(presets.iter().map(|p| p.name.clone()))
+                .collect::<Vec<_>>()
+        });
+        PickList::new(cx, options, AppData::presets.map(|_| 0usize), true)
+            .width(Pixels(160.0))
+            .on_select(move |ex, idx| {
+                let Some(i) = idx.checked_sub(1) else {
+                    return;
+                };
+                if let Some(preset) = AppData::presets.get(ex).get(i) {
+                    ex.emit(AppEvent::ApplyPreset(
+                        preset.name.clone(),
+                        Some(vec![taskid.to_string()]),
+                    ));
+                }
+            });
+    })
+    .class("config-row");
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Save as Preset").width(Stretch(1.0));
+        Textbox::new(cx, PresetNameData::name)
+            .on_edit(|ex, new_text| {
+                ex.emit(PresetNameEvent::UpdateName(new_text));
+            })
+            .placeholder("Preset name")
+            .width(Pixels(160.0));
+        Button::new(cx, |cx| Label::new(cx, "Save"))
+            .on_press(move |ex| {
+                let name = PresetNameData::name.get(ex);
+                ex.emit(AppEvent::SavePreset(taskid2.to_string(), name));
+            })
+            .disabled(PresetNameData::name.map(|name| name.trim().is_empty()))
+            .left(Pixels(5.0));
+    })
+    .class("config-row");
+}
+
+/// 拼接任务的输入列表，可以调整顺序、删除和追加
+fn concat_input_rows<L>(cx: &mut Context, task_type: L, taskid: Arc<String>)
+where
+    L: Lens<Target = TaskType> + Copy,
+{
+    let inputs = task_type.map(|tt| match tt {
+        TaskType::Concat(concat_task) => concat_task.inputs.clone(),
+        _ => vec![],
+    });
+    let method = task_type.map(|tt| match tt {
+        TaskType::Concat(concat_task) => match concat_task.method() {
+            Ok(ConcatMethod::Demuxer) => "Streams match, joining without normalizing".to_string(),
+            Ok(ConcatMethod::Filter) => {
+                "Streams differ, normalizing to the first input".to_string()
+            }
+            Err(e) => e,
+        },
+        _ => String::new(),
+    });
+
+    VStack::new(cx, |cx| {
+        HStack::new(cx, |cx| {
+            Label::new(cx, "Inputs").width(Stretch(1.0));
+            let taskid = Arc::clone(&taskid);
+            Button::new(cx, |cx| Label::new(cx, "Add Files")).on_press(move |ex| {
+                ex.emit(AppEvent::AddConcatInputs(taskid.to_string()));
+            });
+        });
+        Label::new(cx, method).class("badge-label").class("lite");
+
+        Binding::new(cx, inputs.map(|inputs| inputs.len()), move |cx, len| {
+            let len = len.get(cx);
+            for index in 0..len {
+                let taskid = Arc::clone(&taskid);
+                let label = inputs.map(move |inputs| {
+                    let Some(input) = inputs.get(index) else {
+                        return String::new();
+                    };
+                    let summary = match &input.media_info {
+                        Some(info) => info.summary(),
+                        None => "probing...".to_string(),
+                    };
+                    format!("{}. {} ({})", index + 1, input.path.display(), summary)
+                });
+                HStack::new(cx, |cx| {
+                    Label::new(cx, label).width(Stretch(1.0));
+
+                    let (up_id, down_id, remove_id) =
+                        (Arc::clone(&taskid), Arc::clone(&taskid), Arc::clone(&taskid));
+                    Button::new(cx, |cx| Label::new(cx, "Up"))
+                        .on_press(move |ex| {
+                            ex.emit(AppEvent::MoveConcatInput(up_id.to_string(), index, -1));
+                        })
+                        .disabled(index == 0);
+                    Button::new(cx, |cx| Label::new(cx, "Down"))
+                        .on_press(move |ex| {
+                            ex.emit(AppEvent::MoveConcatInput(down_id.to_string(), index, 1));
+                        })
+                        .disabled(index + 1 == len)
+                        .left(Pixels(5.0));
+                    Button::new(cx, |cx| Label::new(cx, "Remove"))
+                        .on_press(move |ex| {
+                            ex.emit(AppEvent::RemoveConcatInput(remove_id.to_string(), index));
+                        })
+                        .disabled(len <= 2)
+                        .left(Pixels(5.0));
+                });
+            }
+        });
+    })
+    .class("config-row");
+}
+
+/// 目标容器能装下源编码时直接复制，旁边显示按当前配置会怎么处理
+fn stream_copy_row<L>(cx: &mut Context, item: L, taskid: Arc<String>)
+where
+    L: Lens<Target = Task> + Copy,
+{
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Copy Compatible Streams").width(Stretch(1.0));
+        Label::new(cx, item.map(|task| task.job_kind().to_string()))
+            .class("badge-label")
+            .class("lite")
+            .right(Pixels(5.0));
+        Checkbox::new(cx, item.map(|task| task.task_type.ffmpeg().stream_copy)).on_toggle(
+            move |ex| {
+                ex.emit(AppEvent::ToggleStreamCopy(taskid.to_string()));
+            },
+        );
+    })
+    .class("config-row");
+}
+
+/// 手动选择输出哪些流、按什么顺序，以及 default/forced 标记
+fn stream_rows<L>(cx: &mut Context, item: L, taskid: Arc<String>)
+where
+    L: Lens<Target = Task> + Copy,
+{
+    let streams = item.map(|task| task.task_type.ffmpeg().stream_map.clone());
+
+    VStack::new(cx, |cx| {
+        HStack::new(cx, |cx| {
+            Label::new(cx, "Choose Streams").width(Stretch(1.0));
+            let taskid = Arc::clone(&taskid);
+            Checkbox::new(cx, streams.map(|s| s.is_some()))
+                .on_toggle(move |ex| {
+                    ex.emit(AppEvent::ToggleStreamMap(taskid.to_string()));
+                })
+                .disabled(item.map(|task| task.media_info.is_none()));
+        });
+
+        Binding::new(cx, streams.map(|s| s.as_ref().map(|s| s.len())), move |cx, len| {
+            let Some(len) = len.get(cx) else {
+                return;
+            };
+            for pos in 0..len {
+                let taskid = Arc::clone(&taskid);
+                let stream = streams.map(move |s| s.as_ref().and_then(|s| s.get(pos)).cloned());
+                let label = item.map(move |task| {
+                    let Some(selection) = task
+                        .task_type
+                        .ffmpeg()
+                        .stream_map
+                        .as_ref()
+                        .and_then(|s| s.get(pos))
+                    else {
+                        return String::new();
+                    };
+                    let info = task
+                        .media_info
+                        .as_ref()
+                        .and_then(|m| m.streams.iter().find(|s| s.index == selection.index));
+                    format!(
+                        "#{} {} {}",
+                        selection.index,
+                        selection.kind,
+                        info.map(|s| s.label()).unwrap_or_default()
+                    )
+                });
+
+                HStack::new(cx, |cx| {
+                    let (include_id, up_id, down_id, default_id, forced_id) = (
+                        Arc::clone(&taskid),
+                        Arc::clone(&taskid),
+                        Arc::clone(&taskid),
+                        Arc::clone(&taskid),
+                        Arc::clone(&taskid),
+                    );
+                    Checkbox::new(cx, stream.map(|s| s.as_ref().is_some_and(|s| s.included)))
+                        .on_toggle(move |ex| {
+                            ex.emit(AppEvent::ToggleStreamIncluded(include_id.to_string(), pos));
+                        })
+                        .right(Pixels(5.0));
+                    Label::new(cx, label).width(Stretch(1.0));
+
+                    Button::new(cx, |cx| Label::new(cx, "Up"))
+                        .on_press(move |ex| {
+                            ex.emit(AppEvent::MoveStream(up_id.to_string(), pos, -1));
+                        })
+                        .disabled(pos == 0);
+                    Button::new(cx, |cx| Label::new(cx, "Down"))
+                        .on_press(move |ex| {
+                            ex.emit(AppEvent::MoveStream(down_id.to_string(), pos, 1));
+                        })
+                        .disabled(pos + 1 == len)
+                        .left(Pixels(5.0));
+
+                    Checkbox::new(cx, stream.map(|s| s.as_ref().is_some_and(|s| s.default)))
+                        .on_toggle(move |ex| {
+                            ex.emit(AppEvent::ToggleStreamDisposition(
+                                default_id.to_string(),
+                                pos,
+                                Disposition::Default,
+                            ));
+                        })
+                        .left(Pixels(10.0));
+                    Label::new(cx, "Default").left(Pixels(5.0));
+                    Checkbox::new(cx, stream.map(|s| s.as_ref().is_some_and(|s| s.forced)))
+                        .on_toggle(move |ex| {
+                            ex.emit(AppEvent::ToggleStreamDisposition(
+                                forced_id.to_string(),
+                                pos,
+                                Disposition::Forced,
+                            ));
+                        })
+                        .left(Pixels(10.0));
+                    Label::new(cx, "Forced").left(Pixels(5.0));
+                });
+            }
+        });
+    })
+    .class("config-row");
+}
+
+/// 音频输出时用哪一路音频，提取音轨的任务在这里换轨
+fn audio_track_row<L>(cx: &mut Context, item: L, taskid: Arc<String>)
+where
+    L: Lens<Target = Task> + Copy,
+{
+    let options = item.map(|task| {
+        let tracks = task
+            .media_info
+            .as_ref()
+            .map(|info| info.audio_streams().map(|a| a.label()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        std::iter::once("Default".to_string())
+            .chain(
+                tracks
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, label)| format!("#{} {label}", i + 1)),
+            )
+            .collect::<Vec<_>>()
+    });
+    let selected = item.map(|task| task.task_type.ffmpeg().audio_track.map_or(0, |t| t + 1));
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Audio Track").width(Stretch(1.0));
+        PickList::new(cx, options, selected, true)
+            .width(Pixels(160.0))
+            .on_select(move |ex, idx| {
+                ex.emit(AppEvent::ChangeAudioTrack(taskid.to_string(), idx.checked_sub(1)));
+            });
+    })
+    .class("config-row");
+}
+
+/// 字幕输出时用哪一路字幕，图形字幕不能转成文字，只做标注
+fn subtitle_track_row<L>(cx: &mut Context, item: L, taskid: Arc<String>)
+where
+    L: Lens<Target = Task> + Copy,
+{
+    let options = item.map(|task| {
+        let tracks = task
+            .media_info
+            .as_ref()
+            .map(|info| {
+                info.subtitle_streams()
+                    .map(|s| match s.is_text_subtitle() {
+                        true => s.label(),
+                        false => format!("{} (image)", s.label()),
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        std::iter::once("Default".to_string())
+            .chain(
+                tracks
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, label)| format!("#{} {label}", i + 1)),
+            )
+            .collect::<Vec<_>>()
+    });
+    let selected = item.map(|task| task.task_type.ffmpeg().subtitle_track.map_or(0, |t| t + 1));
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Subtitle Track").width(Stretch(1.0));
+        PickList::new(cx, options, selected, true)
+            .width(Pixels(160.0))
+            .on_select(move |ex, idx| {
+                ex.emit(AppEvent::ChangeSubtitleTrack(taskid.to_string(), idx.checked_sub(1)));
+            });
+    })
+    .class("config-row");
+}
+
+/// 外挂字幕文件，以及封装成字幕轨还是烧录进画面
+fn subtitle_file_rows<L>(cx: &mut Context, task_type: L, taskid: Arc<String>)
+where
+    L: Lens<Target = FfmpegTask> + Copy,
+{
+    let no_file = task_type.map(|tt| tt.subtitle_file.is_none());
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Subtitle File").width(Stretch(1.0));
+        Label::new(
+            cx,
+            task_type.map(|tt| {
+                tt.subtitle_file
+                    .as_ref()
+                    .and_then(|file| file.path.file_name())
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            }),
+        )
+        .right(Pixels(5.0));
+        let taskid_choose = Arc::clone(&taskid);
+        Button::new(cx, |cx| Label::new(cx, "Choose")).on_press(move |ex| {
+            ex.emit(AppEvent::ChooseSubtitleFile(taskid_choose.to_string()));
+        });
+        let taskid_clear = Arc::clone(&taskid);
+        Button::new(cx, |cx| Label::new(cx, "Clear"))
+            .on_press(move |ex| {
+                ex.emit(AppEvent::ClearSubtitleFile(taskid_clear.to_string()));
+            })
+            .disabled(no_file);
+    })
+    .class("config-row");
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Subtitle Mode").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(|_| SubtitleMode::ALL.iter().map(|m| m.to_string()).collect::<Vec<_>>()),
+            task_type.map(|tt| {
+                let mode = tt.subtitle_file.as_ref().map(|file| file.mode).unwrap_or_default();
+                SubtitleMode::ALL
+                    .iter()
+                    .position(|m| *m == mode)
+                    .unwrap_or_default()
+            }),
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            ex.emit(AppEvent::ChangeSubtitleMode(taskid.to_string(), SubtitleMode::ALL[idx]));
+        })
+        .disabled(no_file);
+    })
+    .class("config-row");
+}
+
+/// 图片输出：有损格式的质量、缩放和去掉元数据
+fn image_rows<L>(cx: &mut Context, task_type: L, taskid: Arc<String>, lossy: bool)
+where
+    L: Lens<Target = FfmpegTask> + Copy,
+{
+    if lossy {
+        let taskid = Arc::clone(&taskid);
+        HStack::new(cx, |cx| {
+            Label::new(cx, "Quality").width(Stretch(1.0));
+            Textbox::new(
+                cx,
+                task_type.map(|tt| tt.image_quality.map(|q| q.to_string()).unwrap_or_default()),
+            )
+            .on_edit(move |ex, new_text| {
+                // 清空时退回编码器默认值，超出范围的留到开始转换时报错
+                let digits_only: String = new_text.chars().filter(|c| c.is_ascii_digit()).collect();
+                ex.emit(AppEvent::ChangeImageQuality(
+                    taskid.to_string(),
+                    digits_only.parse::<u32>().ok(),
+                ));
+            })
+            .placeholder("Default")
+            .width(Pixels(80.0))
+            .padding_left(Pixels(5.0));
+            Label::new(cx, "1-100").class("p-decs").padding_left(Pixels(5.0));
+        })
+        .class("config-row");
+    }
+
+    resolution_row(cx, task_type, Arc::clone(&taskid));
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Strip Metadata").width(Stretch(1.0));
+        Checkbox::new(cx, task_type.map(|tt| tt.strip_metadata)).on_toggle(move |ex| {
+            ex.emit(AppEvent::ToggleStripMetadata(taskid.to_string()));
+        });
+    })
+    .class("config-row");
+}
+
+/// GIF 输出：帧率、宽度、循环次数、抖动和颜色数，以及预计的文件大小
+fn gif_rows<L, F>(cx: &mut Context, item: L, task_type: F, taskid: Arc<String>)
+where
+    L: Lens<Target = Task> + Copy,
+    F: Lens<Target = FfmpegTask> + Copy,
+{
+    gif_number_row(
+        cx,
+        task_type,
+        Arc::clone(&taskid),
+        "Frame Rate",
+        "15",
+        |gif| Some(gif.fps),
+        |gif, fps| gif.fps = fps.unwrap_or(GifOptions::default().fps),
+    );
+    gif_number_row(
+        cx,
+        task_type,
+        Arc::clone(&taskid),
+        "Width",
+        "Original",
+        |gif| gif.width,
+        |gif, width| gif.width = width,
+    );
+    gif_number_row(
+        cx,
+        task_type,
+        Arc::clone(&taskid),
+        "Repeat",
+        "Forever",
+        |gif| gif.repeat,
+        |gif, repeat| gif.repeat = repeat,
+    );
+
+    let taskid2 = Arc::clone(&taskid);
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Dither").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(|_| {
+                GifDither::ALL
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<_>>()
+            }),
+            task_type.map(|tt| {
+                GifDither::ALL
+                    .iter()
+                    .position(|d| *d == tt.gif.dither)
+                    .unwrap_or_default()
+            }),
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            let dither = GifDither::ALL[idx];
+            let f = Box::new(move |gif: &mut GifOptions| gif.dither = dither);
+            ex.emit(AppEvent::UpdateGifOptions(taskid2.to_string(), f));
+        });
+    })
+    .class("config-row");
+
+    gif_number_row(
+        cx,
+        task_type,
+        taskid,
+        "Max Colors",
+        "256",
+        |gif| Some(gif.max_colors),
+        |gif, colors| gif.max_colors = colors.unwrap_or(GifOptions::default().max_colors),
+    );
+
+    // 需要探测到时长和画面大小才能估算
+    let estimate = item.map(|task| match task.gif_size_estimate() {
+        Some(bytes) => format!("~{}", format_size(bytes)),
+        None => "Unknown".to_string(),
+    });
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Estimated Size").width(Stretch(1.0));
+        Label::new(cx, estimate).class("p-decs");
+    })
+    .class("config-row");
+}
+
+/// GIF 的数字参数，清空时按 `None` 提交，无法解析的值不提交
+fn gif_number_row<F>(
+    cx: &mut Context,
+    task_type: F,
+    taskid: Arc<String>,
+    label: &'static str,
+    placeholder: &'static str,
+    get: fn(&GifOptions) -> Option<u32>,
+    set: fn(&mut GifOptions, Option<u32>),
+) where
+    F: Lens<Target = FfmpegTask> + Copy,
+{
+    HStack::new(cx, |cx| {
+        Label::new(cx, label).width(Stretch(1.0));
+        let value =
+            task_type.map(move |tt| get(&tt.gif).map(|v| v.to_string()).unwrap_or_default());
+        Textbox::new(cx, value)
+            .on_submit(move |ex, text, _| {
+                let value = match text.trim() {
+                    "" => None,
+                    text => match text.parse::<u32>() {
+                        Ok(value) => Some(value),
+                        Err(_) => return,
+                    },
+                };
+                let f = Box::new(move |gif: &mut GifOptions| set(gif, value));
+                ex.emit(AppEvent::UpdateGifOptions(taskid.to_string(), f));
+            })
+            .placeholder(placeholder)
+            .width(Pixels(80.0));
+    })
+    .class("config-row");
+}
+
+/// 标准化方式在选项里的位置
+fn normalization_kind(tt: &FfmpegTask) -> usize {
+    match tt.normalization {
+        None => 0,
+        Some(Normalization::Loudness(_)) => 1,
+        Some(Normalization::Peak(_)) => 2,
+    }
+}
+
+/// 音频标准化：EBU R128 响度或峰值，以及运行后测得的响度
+fn normalization_rows<L, F>(cx: &mut Context, item: L, task_type: F, taskid: Arc<String>)
+where
+    L: Lens<Target = Task> + Copy,
+    F: Lens<Target = FfmpegTask> + Copy,
+{
+    let taskid2 = Arc::clone(&taskid);
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Normalize Audio").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(|_| {
+                vec![
+                    "Off".to_string(),
+                    "Loudness (EBU R128)".to_string(),
+                    "Peak".to_string(),
+                ]
+            }),
+            task_type.map(normalization_kind),
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            // 选同一种方式时保留已填的值
+            if idx == normalization_kind(&task_type.get(ex)) {
+                return;
+            }
+            let normalization = match idx {
+                0 => None,
+                1 => Some(Normalization::default()),
+                _ => Some(Normalization::Peak(Normalization::DEFAULT_PEAK)),
+            };
+            let taskid = taskid2.to_string();
+            ex.emit(AppEvent::ChangeNormalization(taskid, normalization));
+        });
+    })
+    .class("config-row");
+
+    Binding::new(cx, task_type.map(normalization_kind), move |cx, kind| {
+        let taskid = Arc::clone(&taskid);
+        match kind.get(cx) {
+            0 => return,
+            1 => {
+                normalization_value_row(
+                    cx,
+                    task_type,
+                    Arc::clone(&taskid),
+                    "Target Loudness",
+                    "LUFS",
+                    |n| match n {
+                        Normalization::Loudness(target) => Some(target.integrated),
+                        _ => None,
+                    },
+                    |n, value| {
+                        if let Normalization::Loudness(target) = n {
+                            target.integrated = value;
+                        }
+                    },
+                );
+                normalization_value_row(
+                    cx,
+                    task_type,
+                    Arc::clone(&taskid),
+                    "True Peak",
+                    "dBTP",
+                    |n| match n {
+                        Normalization::Loudness(target) => Some(target.true_peak),
+                        _ => None,
+                    },
+                    |n, value| {
+                        if let Normalization::Loudness(target) = n {
+                            target.true_peak = value;
+                        }
+                    },
+                );
+                normalization_value_row(
+                    cx,
+                    task_type,
+                    taskid,
+                    "Loudness Range",
+                    "LU",
+                    |n| match n {
+                        Normalization::Loudness(target) => Some(target.range),
+                        _ => None,
+                    },
+                    |n, value| {
+                        if let Normalization::Loudness(target) = n {
+                            target.range = value;
+                        }
+                    },
+                );
+            }
+            _ => {
+                normalization_value_row(
+                    cx,
+                    task_type,
+                    taskid,
+                    "Target Peak",
+                    "dBFS",
+                    |n| match n {
+                        Normalization::Peak(peak) => Some(*peak),
+                        _ => None,
+                    },
+                    |n, value| *n = Normalization::Peak(value),
+                );
+            }
+        }
+
+        let measured = item.map(|task| {
+            let Some(loudness) = &task.loudness else {
+                return "Measured when the task runs".to_string();
+            };
+            let normalization = task.task_type.ffmpeg().normalization;
+            match normalization.and_then(|n| n.gain(loudness)) {
+                Some(gain) => format!("{loudness} ({gain:+.1} dB)"),
+                None => loudness.to_string(),
+            }
+        });
+        HStack::new(cx, |cx| {
+            Label::new(cx, "Measured").width(Stretch(1.0));
+            Label::new(cx, measured).class("p-decs");
+        })
+        .class("config-row");
+    });
+}
+
+/// 标准化的一个目标值，无法解析的值不提交，超出范围的弹窗提示
+fn normalization_value_row<F>(
+    cx: &mut Context,
+    task_type: F,
+    taskid: Arc<String>,
+    label: &'static str,
+    unit: &'static str,
+    get: fn(&Normalization) -> Option<f64>,
+    set: fn(&mut Normalization, f64),
+) where
+    F: Lens<Target = FfmpegTask> + Copy,
+{
+    HStack::new(cx, |cx| {
+        Label::new(cx, label).width(Stretch(1.0));
+        let value = task_type.map(move |tt| {
+            tt.normalization
+                .as_ref()
+                .and_then(get)
+                .map(|v| v.to_string())
+                .unwrap_or_default()
+        });
+        Textbox::new(cx, value)
+            .on_submit(move |ex, text, _| {
+                let normalization = task_type.get(ex).normalization;
+                let (Some(mut normalization), Ok(value)) = (normalization, text.trim().parse())
+                else {
+                    return;
+                };
+                set(&mut normalization, value);
+                let taskid = taskid.to_string();
+                ex.emit(AppEvent::ChangeNormalization(taskid, Some(normalization)));
+            })
+            .width(Pixels(80.0))
+            .padding_left(Pixels(5.0));
+        Label::new(cx, unit).class("p-decs").padding_left(Pixels(5.0));
+    })
+    .class("config-row");
+}
+
+/// 图片序列的输入：文件名模式和帧数
+fn sequence_input_row<L>(cx: &mut Context, task_type: L)
+where
+    L: Lens<Target = TaskType> + Copy,
+{
+    let label = task_type.map(|tt| match tt {
+        TaskType::ImageSequence(sequence_task) => {
+            let sequence = &sequence_task.sequence;
+            let path = sequence.dir.join(&sequence.pattern);
+            format!("{} ({})", path.display(), sequence_task.summary())
+        }
+        _ => String::new(),
+    });
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Input").padding_right(Pixels(10.0));
+        Label::new(cx, label).width(Stretch(1.0));
+    })
+    .class("config-row");
+}
+
+/// 导出帧的取样方式：每秒几帧，或每隔 N 帧取一帧
+fn frame_sampling_rows<L>(cx: &mut Context, task_type: L, taskid: Arc<String>)
+where
+    L: Lens<Target = TaskType> + Copy,
+{
+    let sampling = move |tt: &TaskType| match tt {
+        TaskType::FrameExport(export_task) => export_task.sampling,
+        _ => FrameSampling::default(),
+    };
+
+    let taskid2 = Arc::clone(&taskid);
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Export Frames").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(|_| vec!["Per Second".to_string(), "Every Nth Frame".to_string()]),
+            task_type.map(move |tt| matches!(sampling(tt), FrameSampling::EveryNth(_)) as usize),
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            let new_sampling = match idx {
+                0 => FrameSampling::default(),
+                _ => FrameSampling::EveryNth(30),
+            };
+            // 选同一种方式时保留已填的值
+            if std::mem::discriminant(&sampling(&task_type.get(ex)))
+                != std::mem::discriminant(&new_sampling)
+            {
+                let taskid = taskid.to_string();
+                ex.emit(AppEvent::ChangeFrameSampling(taskid, new_sampling));
+            }
+        });
+    })
+    .class("config-row");
+
+    HStack::new(cx, |cx| {
+        let label = task_type.map(move |tt| match sampling(tt) {
+            FrameSampling::Fps(_) => "Frames per Second".to_string(),
+            FrameSampling::EveryNth(_) => "Frame Interval".to_string(),
+        });
+        Label::new(cx, label).width(Stretch(1.0));
+        let value = task_type.map(move |tt| match sampling(tt) {
+            FrameSampling::Fps(fps) => fps.to_string(),
+            FrameSampling::EveryNth(n) => n.to_string(),
+        });
+        // 无法解析的值不提交，超出范围的留到开始转换时报错
+        Textbox::new(cx, value)
+            .on_submit(move |ex, text, _| {
+                let text = text.trim();
+                let new_sampling = match sampling(&task_type.get(ex)) {
+                    FrameSampling::Fps(_) => text.parse().ok().map(FrameSampling::Fps),
+                    FrameSampling::EveryNth(_) => text.parse().ok().map(FrameSampling::EveryNth),
+                };
+                if let Some(new_sampling) = new_sampling {
+                    let taskid = taskid2.to_string();
+                    ex.emit(AppEvent::ChangeFrameSampling(taskid, new_sampling));
+                }
+            })
+            .width(Pixels(80.0));
+    })
+    .class("config-row");
+}
+
+/// 截取片段：起点、终点或时长，以及起点的定位方式
+fn trim_rows<L>(cx: &mut Context, task_type: L, taskid: Arc<String>)
+where
+    L: Lens<Target = FfmpegTask> + Copy,
+{
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Trim").width(Stretch(1.0));
+        for (point, placeholder) in [
+            (TrimPoint::Start, "Start"),
+            (TrimPoint::End, "End"),
+            (TrimPoint::Duration, "Duration"),
+        ] {
+            let taskid = Arc::clone(&taskid);
+            let value = task_type.map(move |tt| {
+                tt.trim.get(point).map(format_timestamp_ms).unwrap_or_default()
+            });
+            // 输入到一半的时间无法解析，等回车或失焦再提交
+            Textbox::new(cx, value)
+                .on_submit(move |ex, text, _| {
+                    ex.emit(AppEvent::ChangeTrimPoint(taskid.to_string(), point, text));
+                })
+                .placeholder(placeholder)
+                .width(Pixels(110.0))
+                .padding_left(Pixels(5.0));
+        }
+    })
+    .class("config-row");
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Seek Mode").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(|_| SeekMode::ALL.iter().map(|m| m.to_string()).collect::<Vec<_>>()),
+            task_type.map(|tt| {
+                SeekMode::ALL
+                    .iter()
+                    .position(|m| *m == tt.trim.seek)
+                    .unwrap_or_default()
+            }),
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            ex.emit(AppEvent::ChangeSeekMode(taskid.to_string(), SeekMode::ALL[idx]));
+        })
+        .disabled(task_type.map(|tt| tt.trim.start.is_none()));
+    })
+    .class("config-row");
+}
+
+/// 编码下拉框的选项，第一项是容器默认编码
+fn codec_options<T: std::fmt::Display>(codecs: &[T]) -> Vec<String> {
+    std::iter::once("Auto".to_string())
+        .chain(codecs.iter().map(|c| c.to_string()))
+        .collect()
+}
+
+/// 视频码率控制：模式下拉框 + 数值 + 取值提示
+fn video_rate_row<L>(
+    cx: &mut Context,
+    task_type: L,
+    taskid: Arc<String>,
+    default_codec: Option<VideoCodec>,
+) where
+    L: Lens<Target = FfmpegTask> + Copy,
+{
+    let taskid2 = Arc::clone(&taskid);
+    let taskid3 = Arc::clone(&taskid);
+    let mode = task_type.map(|tt| match tt.video_rate {
+        None => 0,
+        Some(VideoRateControl::Bitrate(_)) => 1,
+        Some(VideoRateControl::Quality(_)) => 2,
+        Some(VideoRateControl::TargetSize(_)) => 3,
+    });
+    let value = task_type.map(|tt| tt.video_rate.map(|r| r.value().to_string()).unwrap_or_default());
+    let hint = task_type.map(move |tt| match tt.video_rate {
+        None => String::new(),
+        Some(VideoRateControl::Bitrate(_)) => "kbps".to_string(),
+        Some(VideoRateControl::Quality(_)) => match tt.video_codec.or(default_codec) {
+            Some(codec) => {
+                let scale = codec.quality_scale();
+                format!("{} {}", scale.arg.trim_start_matches('-'), scale.hint())
+            }
+            None => "not available for this format".to_string(),
+        },
+        Some(VideoRateControl::TargetSize(_)) => "MB".to_string(),
+    });
+    let is_default = task_type.map(|tt| tt.video_rate.is_none());
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Video Rate").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(|_| {
+                vec![
+                    "Default".to_string(),
+                    "Bitrate".to_string(),
+                    "Constant Quality".to_string(),
+                    "Target Size".to_string(),
+                ]
+            }),
+            mode,
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            let quality = task_type
+                .get(ex)
+                .video_codec
+                .or(default_codec)
+                .map_or(23, |c| c.quality_scale().default);
+            let rate = match idx {
+                1 => Some(VideoRateControl::Bitrate(VideoRateControl::DEFAULT_BITRATE)),
+                2 => Some(VideoRateControl::Quality(quality)),
+                3 => Some(VideoRateControl::TargetSize(VideoRateControl::DEFAULT_TARGET_SIZE)),
+                _ => None,
+            };
+            ex.emit(AppEvent::ChangeVideoRate(taskid.to_string(), rate));
+        });
+        Textbox::new(cx, value)
+            .on_edit(move |ex, new_text| {
+                let digits_only: String = new_text.chars().filter(|c| c.is_ascii_digit()).collect();
+                if let (Ok(new_value), Some(rate)) =
+                    (digits_only.parse::<u32>(), task_type.get(ex).video_rate)
+                {
+                    ex.emit(AppEvent::ChangeVideoRate(
+                        taskid2.to_string(),
+                        Some(rate.with_value(new_value)),
+                    ));
+                }
+            })
+            .disabled(is_default)
+            .width(Pixels(80.0))
+            .padding_left(Pixels(5.0));
+        Label::new(cx, hint).class("p-decs").padding_left(Pixels(5.0));
+    })
+    .class("config-row");
+
+    let two_pass_unavailable = task_type.map(|tt| {
+        !matches!(
+            tt.video_rate,
+            Some(VideoRateControl::Bitrate(_) | VideoRateControl::TargetSize(_))
+        )
+    });
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Two-Pass Encoding").width(Stretch(1.0));
+        Checkbox::new(cx, task_type.map(|tt| tt.two_pass))
+            .on_toggle(move |ex| {
+                ex.emit(AppEvent::ToggleTwoPass(taskid3.to_string()));
+            })
+            .disabled(two_pass_unavailable);
+    })
+    .class("config-row");
+}
+
+/// 音频码率控制：固定码率或 VBR 质量等级
+fn audio_rate_row<L>(
+    cx: &mut Context,
+    task_type: L,
+    taskid: Arc<String>,
+    default_codec: Option<AudioCodec>,
+) where
+    L: Lens<Target = FfmpegTask> + Copy,
+{
+    let taskid2 = Arc::clone(&taskid);
+    let mode = task_type.map(|tt| match tt.audio_rate {
+        None => 0,
+        Some(AudioRateControl::Bitrate(_)) => 1,
+        Some(AudioRateControl::Quality(_)) => 2,
+    });
+    let value = task_type.map(|tt| tt.audio_rate.map(|r| r.value().to_string()).unwrap_or_default());
+    let hint = task_type.map(move |tt| match tt.audio_rate {
+        None => String::new(),
+        Some(AudioRateControl::Bitrate(_)) => "kbps".to_string(),
+        Some(AudioRateControl::Quality(_)) => {
+            match tt.audio_codec.or(default_codec).map(|c| (c, c.quality_scale())) {
+                Some((_, Some(scale))) => format!("q:a {}", scale.hint()),
+                Some((codec, None)) => format!("not supported by {codec}"),
+                None => "not available for this format".to_string(),
+            }
+        }
+    });
+    let is_default = task_type.map(|tt| tt.audio_rate.is_none());
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Audio Rate").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(|_| {
+                vec![
+                    "Default".to_string(),
+                    "Bitrate".to_string(),
+                    "VBR Quality".to_string(),
+                ]
+            }),
+            mode,
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            let quality = task_type
+                .get(ex)
+                .audio_codec
+                .or(default_codec)
+                .and_then(|c| c.quality_scale())
+                .map_or(0, |scale| scale.default);
+            let rate = match idx {
+                1 => Some(AudioRateControl::Bitrate(AudioRateControl::DEFAULT_BITRATE)),
+                2 => Some(AudioRateControl::Quality(quality)),
+                _ => None,
+            };
+            ex.emit(AppEvent::ChangeAudioRate(taskid.to_string(), rate));
+        });
+        Textbox::new(cx, value)
+            .on_edit(move |ex, new_text| {
+                let digits_only: String = new_text.chars().filter(|c| c.is_ascii_digit()).collect();
+                if let (Ok(new_value), Some(rate)) =
+                    (digits_only.parse::<u32>(), task_type.get(ex).audio_rate)
+                {
+                    ex.emit(AppEvent::ChangeAudioRate(
+                        taskid2.to_string(),
+                        Some(rate.with_value(new_value)),
+                    ));
+                }
+            })
+            .disabled(is_default)
+            .width(Pixels(80.0))
+            .padding_left(Pixels(5.0));
+        Label::new(cx, hint).class("p-decs").padding_left(Pixels(5.0));
+    })
+    .class("config-row");
+}
+
+/// 分辨率：按高度的预设保持比例，自定义时填 宽x高
+fn resolution_row<L>(cx: &mut Context, task_type: L, taskid: Arc<String>)
+where
+    L: Lens<Target = FfmpegTask> + Copy,
+{
+    let taskid2 = Arc::clone(&taskid);
+    let custom_idx = Resolution::PRESETS.len() + 1;
+    let selected = task_type.map(move |tt| match tt.resolution {
+        None => 0,
+        Some(r) => Resolution::PRESETS
+            .iter()
+            .position(|p| *p == r)
+            .map_or(custom_idx, |i| i + 1),
+    });
+    let custom_value = task_type.map(move |tt| match tt.resolution {
+        Some(r) if !Resolution::PRESETS.contains(&r) => r.to_string(),
+        _ => String::new(),
+    });
+    let is_preset = task_type.map(|tt| {
+        tt.resolution
+            .map_or(true, |r| Resolution::PRESETS.contains(&r))
+    });
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Resolution").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(|_| {
+                std::iter::once("Original".to_string())
+                    .chain(Resolution::PRESETS.iter().map(|r| r.to_string()))
+                    .chain(std::iter::once("Custom".to_string()))
+                    .collect::<Vec<_>>()
+            }),
+            selected,
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            let resolution = match idx {
+                0 => None,
+                i if i <= Resolution::PRESETS.len() => Some(Resolution::PRESETS[i - 1]),
+                _ => Some(Resolution::Exact(1920, 1080)),
+            };
+            ex.emit(AppEvent::ChangeResolution(taskid.to_string(), resolution));
+        });
+        Textbox::new(cx, custom_value)
+            .on_edit(move |ex, new_text| {
+                // 输入过程中的半截值不提交
+                if let Ok(resolution) = Resolution::parse(&new_text) {
+                    ex.emit(AppEvent::ChangeResolution(
+                        taskid2.to_string(),
+                        Some(resolution),
+                    ));
+                }
+            })
+            .placeholder("1280x720")
+            .disabled(is_preset)
+            .width(Pixels(100.0))
+            .padding_left(Pixels(5.0));
+    })
+    .class("config-row");
+}
+
+/// `original` 是不改帧率时显示的选项
+fn frame_rate_row<L>(cx: &mut Context, task_type: L, taskid: Arc<String>, original: String)
+where
+    L: Lens<Target = FfmpegTask> + Copy,
+{
+    let selected = task_type.map(|tt| {
+        tt.frame_rate
+            .and_then(|fps| FRAME_RATE_PRESETS.iter().position(|p| *p == fps))
+            .map_or(0, |i| i + 1)
+    });
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Frame Rate").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(move |_| {
+                std::iter::once(original.clone())
+                    .chain(FRAME_RATE_PRESETS.iter().map(|fps| format!("{fps} fps")))
+                    .collect::<Vec<_>>()
+            }),
+            selected,
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            let fps = idx.checked_sub(1).map(|i| FRAME_RATE_PRESETS[i]);
+            ex.emit(AppEvent::ChangeFrameRate(taskid.to_string(), fps));
+        });
+    })
+    .class("config-row");
+}
+
+fn sample_rate_row<L>(cx: &mut Context, task_type: L, taskid: Arc<String>)
+where
+    L: Lens<Target = FfmpegTask> + Copy,
+{
+    let selected = task_type.map(|tt| {
+        tt.sample_rate
+            .and_then(|rate| SAMPLE_RATE_PRESETS.iter().position(|p| *p == rate))
+            .map_or(0, |i| i + 1)
+    });
+
+    HStack::new(cx, |cx| {
+        Label::new(cx, "Sample Rate").width(Stretch(1.0));
+        PickList::new(
+            cx,
+            task_type.map(|_| {
+                std::iter::once("Original".to_string())
+                    .chain(SAMPLE_RATE_PRESETS.iter().map(|rate| format!("{rate} Hz")))
+                    .collect::<Vec<_>>()
+            }),
+            selected,
+            true,
+        )
+        .width(Pixels(160.0))
+        .on_select(move |ex, idx| {
+            let rate = idx.checked_sub(1).map(|i| SAMPLE_RATE_PRESETS[i]);
+            ex.emit(AppEvent::ChangeSampleRate(taskid.to_string(), rate));
+        });
+    })
+    .class("config-row");
+}
@@ -74,19 +74,27 @@ pub fn popup(cx: &mut Context) -> Handle<Window> {
                                 let formats_arc = Arc::clone(&supported_output_formats_arc);
                                 Binding::new(cx, fmt, move |cx, format| {
                                     let format = format.get(cx);
-                                    let format_name = format.as_any().get_ext();
+                                    let format_name = format.as_any().get_label();
                                     let format_decs =
                                         format.as_any().get_decs().unwrap_or_default();
                                     let formats = formats_arc.get(cx);
                                     let this_task_idx = formats
                                         .iter()
-                                        .position(|f| f.as_any().get_ext() == format_name)
+                                        .position(|f| Arc::ptr_eq(f, &format))
                                         .unwrap_or(0);
                                     let task_id = task_id_for_binding.clone();
                                     let format_type = match format.as_any().get_format_type(){
                                         FormatType::Audio(_) => "Audio",
                                         FormatType::Video(_) => "Video",
+                                        FormatType::AudioVisual(_) => "Audio → Video",
+                                        FormatType::Subtitle(_) => "Subtitle",
+                                        FormatType::Image(_) => "Image",
                                     };
+                                    // 未探测到能力时不做限制
+                                    let unavailable_reason = AppData::ffmpeg_caps
+                                        .get(cx)
+                                        .and_then(|caps| caps.check_format(format.as_any()).err());
+                                    let is_available = unavailable_reason.is_none();
 
 
                                     HStack::new(cx, |cx| {
@@ -96,9 +104,13 @@ pub fn popup(cx: &mut Context) -> Handle<Window> {
                                                 Label::new(cx, format_type).class("badge-label").class("lite");
                                             }).height(Auto);
                                             Label::new(cx, format_decs).class("p-decs");
+                                            if let Some(reason) = unavailable_reason {
+                                                Label::new(cx, reason).class("p-decs").class("warning");
+                                            }
                                         })
                                         .alignment(Alignment::Left);
                                     })
+                                    .toggle_class("disabled", !is_available)
                                     .bind(selected_output_format, move |handle, res| {
                                         if res.get(&handle) == this_task_idx {
                                             handle.toggle_class("selected", true);
@@ -107,7 +119,7 @@ pub fn popup(cx: &mut Context) -> Handle<Window> {
                                         }
                                     })
                                     .on_mouse_down(move |ex, button| {
-                                        if button == MouseButton::Left {
+                                        if button == MouseButton::Left && is_available {
                                             ex.emit(AppEvent::ChangeOutputFormat(
                                                 task_id.to_string(),
                                                 this_task_idx,
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use vizia::prelude::*;
+use vizia::{icons::ICON_TRASH, prelude::*};
 
 use crate::{
     controllers::main::{app_data::AppData, app_event::AppEvent},
@@ -32,6 +32,106 @@ pub fn new(cx: &mut Context) -> Handle<Window> {
                     .width(Stretch(1.0));
             })
             .class("setting-row");
+
+            HStack::new(cx, |cx| {
+                Label::new(cx, "Max Parallel Jobs").padding_right(Pixels(10.0));
+
+                let max_jobs_binding = AppData::settings
+                    .then(AppSettings::max_parallel_jobs)
+                    .map(|n| n.to_string());
+
+                Textbox::new(cx, max_jobs_binding)
+                    .on_edit(move |cx, new_text| {
+                        if let Ok(n) = new_text.trim().parse::<usize>() {
+                            if n > 0 {
+                                cx.emit(AppEvent::UpdateMaxParallelJobs(n));
+                            }
+                        }
+                    })
+                    .width(Pixels(80.0));
+            })
+            .class("setting-row");
+
+            HStack::new(cx, |cx| {
+                Label::new(cx, "Presets").width(Stretch(1.0));
+                Button::new(cx, |cx| Label::new(cx, "Import"))
+                    .on_press(|ex| ex.emit(AppEvent::ImportPresets));
+                Button::new(cx, |cx| Label::new(cx, "Export"))
+                    .on_press(|ex| ex.emit(AppEvent::ExportPresets))
+                    .left(Pixels(5.0));
+            })
+            .class("setting-row");
+
+            Binding::new(cx, AppData::presets, |cx, presets| {
+                VStack::new(cx, |cx| {
+                    for preset in presets.get(cx) {
+                        HStack::new(cx, |cx| {
+                            Label::new(cx, preset.name.clone()).width(Stretch(1.0));
+                            Label::new(cx, preset.format.clone())
+                                .class("badge-label")
+                                .class("lite");
+                            if preset.builtin {
+                                Label::new(cx, "built-in").class("p-decs").left(Pixels(5.0));
+                            } else {
+                                let name = preset.name.clone();
+                                Button::new(cx, |cx| Svg::new(cx, ICON_TRASH))
+                                    .class("rounded-btn")
+                                    .class("del-btn")
+                                    .variant(ButtonVariant::Text)
+                                    .on_press(move |ex| {
+                                        ex.emit(AppEvent::DeletePreset(name.clone()));
+                                    })
+                                    .left(Pixels(5.0));
+                            }
+                        })
+                        .class("setting-row");
+                    }
+                })
+                .height(Auto);
+            });
+
+            Binding::new(cx, AppData::ffmpeg_caps, |cx, caps| {
+                let Some(caps) = caps.get(cx) else {
+                    Label::new(cx, "FFmpeg capabilities unknown").class("p-decs");
+                    return;
+                };
+
+                VStack::new(cx, |cx| {
+                    HStack::new(cx, |cx| {
+                        Label::new(cx, "FFmpeg Version").padding_right(Pixels(10.0));
+                        Label::new(cx, caps.version.clone()).class("p-decs");
+                    })
+                    .class("setting-row");
+
+                    HStack::new(cx, |cx| {
+                        Label::new(cx, "License").padding_right(Pixels(10.0));
+                        Label::new(cx, caps.license()).class("p-decs");
+                    })
+                    .class("setting-row");
+
+                    HStack::new(cx, |cx| {
+                        Label::new(cx, "Hardware Acceleration").padding_right(Pixels(10.0));
+                        let hwaccels = if caps.hwaccels.is_empty() {
+                            "none".to_string()
+                        } else {
+                            caps.hwaccels.join(", ")
+                        };
+                        Label::new(cx, hwaccels).class("p-decs");
+                    })
+                    .class("setting-row");
+
+                    let unavailable = caps.unavailable_formats();
+                    if !unavailable.is_empty() {
+                        Label::new(cx, "Unavailable Output Formats").padding_top(Pixels(5.0));
+                        for (ext, reason) in unavailable {
+                            Label::new(cx, format!("{ext}: {reason}"))
+                                .class("p-decs")
+                                .class("warning");
+                        }
+                    }
+                })
+                .height(Auto);
+            });
         })
         .class("settings-window-content");
     })